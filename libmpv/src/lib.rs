@@ -2,6 +2,7 @@ use khronos_egl::DynamicInstance;
 use std::{
     collections::HashMap,
     ffi::{c_void, CStr, CString},
+    fmt,
     mem::MaybeUninit,
     os::raw::{c_char, c_int},
     ptr,
@@ -31,6 +32,18 @@ extern "C" fn on_mpv_render_update(ctx: *mut c_void) {
     }
 }
 
+// mpv render context can't be created for the window, e.g. SDL uses a window system mpv has no display handle for
+#[derive(Debug)]
+pub struct RenderContextError(pub String);
+
+impl fmt::Display for RenderContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RenderContextError {}
+
 pub struct RenderContext {
     handle: *mut sys::mpv_render_context,
     update_requested: Mutex<bool>,
@@ -115,9 +128,11 @@ pub enum Event {
     VideoReconfig,
     FileLoaded,
     EndFile,
+    // file failed to load or playback was aborted because of an error, contains mpv error description
+    EndFileError(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Node {
     I64(i64),
     F64(f64),
@@ -186,6 +201,22 @@ pub struct Property {
     pub value: PropertyValue,
 }
 
+// Values come from config, a bad one is logged and mpv keeps its default.
+unsafe fn set_option_string(handle: *mut sys::mpv_handle, name: &str, value: &str) {
+    let (name_c, value_c) = match (CString::new(name), CString::new(value)) {
+        (Ok(name_c), Ok(value_c)) => (name_c, value_c),
+        _ => {
+            log::error!("invalid mpv option {}={:?}, it contains NUL", name, value);
+            return;
+        }
+    };
+    let err = sys::mpv_set_option_string(handle, name_c.as_ptr(), value_c.as_ptr());
+    if err < 0 {
+        let msg = CStr::from_ptr(sys::mpv_error_string(err)).to_string_lossy();
+        log::error!("failed setting mpv option {}={}: {}", name, value, msg);
+    }
+}
+
 fn convert_node(n: *const sys::mpv_node) -> Option<Node> {
     unsafe {
         match (*n).format {
@@ -201,44 +232,32 @@ fn convert_node(n: *const sys::mpv_node) -> Option<Node> {
             sys::MPV_FORMAT_INT64 => Some(Node::I64((*n).u.int64)),
             sys::MPV_FORMAT_DOUBLE => Some(Node::F64((*n).u.double_)),
             sys::MPV_FORMAT_NODE_ARRAY => {
-                let num = (*(*n).u.list).num;
-                let mut out = Vec::with_capacity(num as usize);
-                let mut ptr = (*(*n).u.list).values;
-                for _ in 0..num {
-                    let val = match convert_node(ptr) {
-                        Some(val) => val,
-                        None => {
-                            ptr = ptr.add(1);
-                            continue;
-                        }
-                    };
-                    out.push(val);
-                    ptr = ptr.add(1);
+                let list = &*(*n).u.list;
+                let mut out = Vec::with_capacity(list.num.max(0) as usize);
+                for i in 0..list.num.max(0) as usize {
+                    if let Some(val) = convert_node(list.values.add(i)) {
+                        out.push(val);
+                    }
                 }
                 Some(Node::Array(out))
             }
             sys::MPV_FORMAT_NODE_MAP => {
-                let num = (*(*n).u.list).num;
-                let mut out = HashMap::with_capacity(num as usize);
-                let mut pkey = (*(*n).u.list).keys;
-                let mut pval = (*(*n).u.list).values;
-                for _ in 0..num {
-                    let val = match convert_node(pval) {
+                let list = &*(*n).u.list;
+                let mut out = HashMap::with_capacity(list.num.max(0) as usize);
+                // key and value are looked up by the same index, so skipping an unsupported value can't shift the
+                // keys of the following entries
+                for i in 0..list.num.max(0) as usize {
+                    let val = match convert_node(list.values.add(i)) {
                         Some(val) => val,
-                        None => {
-                            pkey = pkey.add(1);
-                            pval = pval.add(1);
-                            continue;
-                        }
+                        None => continue,
                     };
-                    let key = if *pkey != ptr::null_mut() {
-                        CStr::from_ptr(*pkey).to_string_lossy().to_string()
+                    let pkey = *list.keys.add(i);
+                    let key = if pkey != ptr::null_mut() {
+                        CStr::from_ptr(pkey).to_string_lossy().to_string()
                     } else {
                         String::new()
                     };
                     out.insert(key, val);
-                    pkey = pkey.add(1);
-                    pval = pval.add(1);
                 }
                 Some(Node::Map(out))
             }
@@ -247,8 +266,82 @@ fn convert_node(n: *const sys::mpv_node) -> Option<Node> {
     }
 }
 
+// Owns memory of an mpv_node tree built from Node, has to outlive the mpv call the tree is passed to. Buffers are
+// never touched once pushed, so the pointers stay valid.
+#[derive(Default)]
+struct NodeStorage {
+    strings: Vec<CString>,
+    values: Vec<Vec<sys::mpv_node>>,
+    keys: Vec<Vec<*mut c_char>>,
+    lists: Vec<Box<sys::mpv_node_list>>,
+}
+
+impl NodeStorage {
+    fn build_node(&mut self, node: &Node) -> sys::mpv_node {
+        let mut out: sys::mpv_node = unsafe { std::mem::zeroed() };
+        match node {
+            Node::I64(v) => {
+                out.format = sys::MPV_FORMAT_INT64;
+                out.u.int64 = *v;
+            }
+            Node::F64(v) => {
+                out.format = sys::MPV_FORMAT_DOUBLE;
+                out.u.double_ = *v;
+            }
+            Node::Bool(v) => {
+                out.format = sys::MPV_FORMAT_FLAG;
+                out.u.flag = *v as c_int;
+            }
+            Node::String(v) => {
+                out.format = sys::MPV_FORMAT_STRING;
+                out.u.string = self.push_string(v);
+            }
+            Node::Array(v) => {
+                let values = v.iter().map(|n| self.build_node(n)).collect();
+                out.format = sys::MPV_FORMAT_NODE_ARRAY;
+                out.u.list = self.push_list(values, None);
+            }
+            Node::Map(v) => {
+                let mut keys = Vec::with_capacity(v.len());
+                let mut values = Vec::with_capacity(v.len());
+                for (key, val) in v {
+                    keys.push(self.push_string(key));
+                    values.push(self.build_node(val));
+                }
+                out.format = sys::MPV_FORMAT_NODE_MAP;
+                out.u.list = self.push_list(values, Some(keys));
+            }
+        }
+        out
+    }
+
+    fn push_string(&mut self, s: &str) -> *mut c_char {
+        let s = CString::new(s).unwrap();
+        let ptr = s.as_ptr() as *mut c_char;
+        self.strings.push(s);
+        ptr
+    }
+
+    fn push_list(&mut self, mut values: Vec<sys::mpv_node>, keys: Option<Vec<*mut c_char>>) -> *mut sys::mpv_node_list {
+        let mut list = Box::new(sys::mpv_node_list {
+            num: values.len() as c_int,
+            values: values.as_mut_ptr(),
+            keys: ptr::null_mut(),
+        });
+        self.values.push(values);
+        if let Some(mut keys) = keys {
+            list.keys = keys.as_mut_ptr();
+            self.keys.push(keys);
+        }
+        let ptr = list.as_mut() as *mut sys::mpv_node_list;
+        self.lists.push(list);
+        ptr
+    }
+}
+
 impl Context {
-    pub fn create() -> Box<Context> {
+    // hwdec and profile are mpv options of the same name, empty profile keeps mpv's default
+    pub fn create(hwdec: &str, profile: &str) -> Box<Context> {
         unsafe {
             let handle = sys::mpv_create();
             if handle == ptr::null_mut() {
@@ -264,12 +357,10 @@ impl Context {
 
             sys::mpv_request_log_messages(handle, "debug\0".as_ptr() as *const i8);
 
-            sys::mpv_set_option_string(handle, "hwdec\0".as_ptr() as *const i8, "no\0".as_ptr() as *const i8);
-            sys::mpv_set_option_string(
-                handle,
-                "profile\0".as_ptr() as *const i8,
-                "sw-fast\0".as_ptr() as *const i8,
-            );
+            set_option_string(handle, "hwdec", hwdec);
+            if !profile.is_empty() {
+                set_option_string(handle, "profile", profile);
+            }
 
             ctx
         }
@@ -292,6 +383,16 @@ impl Context {
         }
     }
 
+    // structured version of command_async, args is an array of the command name and its arguments, which unlike
+    // command_async can be maps (e.g. loadfile options) or non-string values, mpv copies the tree before returning
+    pub fn command_node_async(&self, args: &Node) {
+        let mut storage = NodeStorage::default();
+        let mut node = storage.build_node(args);
+        unsafe {
+            sys::mpv_command_node_async(self.handle, 0, &mut node);
+        }
+    }
+
     pub fn observe_property(&self, name: &str) {
         unsafe {
             let cstr = CString::new(name).unwrap();
@@ -314,8 +415,12 @@ impl Context {
         self.get_property_async("percent-pos\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE)
     }
 
+    pub fn get_time_pos_async(&self) {
+        self.get_property_async("time-pos\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
+    }
+
     pub fn get_duration_async(&self) {
-        self.get_property_async("duration\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
+        self.get_property_async("duration\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
     }
 
     pub fn get_pause_async(&self) {
@@ -354,6 +459,78 @@ impl Context {
         self.get_property_async("aid\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
     }
 
+    pub fn get_brightness_async(&self) {
+        self.get_property_async("brightness\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
+    }
+
+    pub fn get_contrast_async(&self) {
+        self.get_property_async("contrast\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
+    }
+
+    pub fn get_gamma_async(&self) {
+        self.get_property_async("gamma\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
+    }
+
+    pub fn get_saturation_async(&self) {
+        self.get_property_async("saturation\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
+    }
+
+    pub fn get_sub_scale_async(&self) {
+        self.get_property_async("sub-scale\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
+    }
+
+    pub fn get_sub_pos_async(&self) {
+        self.get_property_async("sub-pos\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
+    }
+
+    pub fn get_video_bitrate_async(&self) {
+        self.get_property_async("video-bitrate\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
+    }
+
+    pub fn get_estimated_vf_fps_async(&self) {
+        self.get_property_async("estimated-vf-fps\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
+    }
+
+    pub fn get_frame_drop_count_async(&self) {
+        self.get_property_async("frame-drop-count\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
+    }
+
+    pub fn get_volume_async(&self) {
+        self.get_property_async("volume\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
+    }
+
+    pub fn get_volume_max_async(&self) {
+        self.get_property_async("volume-max\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
+    }
+
+    pub fn get_speed_async(&self) {
+        self.get_property_async("speed\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
+    }
+
+    pub fn get_deinterlace_async(&self) {
+        self.get_property_async("deinterlace\0".as_ptr() as *const i8, sys::MPV_FORMAT_FLAG);
+    }
+
+    pub fn get_idle_active_async(&self) {
+        self.get_property_async("idle-active\0".as_ptr() as *const i8, sys::MPV_FORMAT_FLAG);
+    }
+
+    pub fn get_paused_for_cache_async(&self) {
+        self.get_property_async("paused-for-cache\0".as_ptr() as *const i8, sys::MPV_FORMAT_FLAG);
+    }
+
+    pub fn get_cache_buffering_state_async(&self) {
+        self.get_property_async("cache-buffering-state\0".as_ptr() as *const i8, sys::MPV_FORMAT_INT64);
+    }
+
+    pub fn get_demuxer_cache_duration_async(&self) {
+        self.get_property_async("demuxer-cache-duration\0".as_ptr() as *const i8, sys::MPV_FORMAT_DOUBLE);
+    }
+
+    pub fn get_loop_file_async(&self) {
+        self.get_property_async("loop-file\0".as_ptr() as *const i8, sys::MPV_FORMAT_STRING);
+    }
+
     pub fn drain_events(&mut self) -> Vec<Event> {
         {
             let mut has_events = self.has_events.lock().unwrap();
@@ -399,7 +576,14 @@ impl Context {
                 } else if (*event).event_id == sys::MPV_EVENT_VIDEO_RECONFIG {
                     video_reconfig = true;
                 } else if (*event).event_id == sys::MPV_EVENT_END_FILE {
-                    end_file = true
+                    end_file = true;
+                    let ef = (*event).data as *const sys::mpv_event_end_file;
+                    if (*ef).reason == sys::MPV_END_FILE_REASON_ERROR as c_int {
+                        let msg = CStr::from_ptr(sys::mpv_error_string((*ef).error))
+                            .to_string_lossy()
+                            .to_string();
+                        events.push(Event::EndFileError(msg));
+                    }
                 } else if (*event).event_id == sys::MPV_EVENT_FILE_LOADED {
                     file_loaded = true;
                 } else if (*event).event_id == sys::MPV_EVENT_PROPERTY_CHANGE {
@@ -480,18 +664,41 @@ impl Context {
         &self,
         egl: &DynamicInstance<khronos_egl::EGL1_2>,
         window: &sdl2::video::Window,
-    ) -> Box<RenderContext> {
+    ) -> Result<Box<RenderContext>, RenderContextError> {
         let version = sdl2::version::version();
         let mut wminfo: sdl2_sys::SDL_SysWMinfo = MaybeUninit::zeroed().assume_init();
         wminfo.version.major = version.major;
         wminfo.version.minor = version.minor;
         wminfo.version.patch = version.patch;
         if sdl2_sys::SDL_GetWindowWMInfo(window.raw(), &mut wminfo) != sdl2_sys::SDL_bool::SDL_TRUE {
-            panic!("SDL_GetWindowWMInfo failed");
-        }
-        if wminfo.subsystem != sdl2_sys::SDL_SYSWM_TYPE::SDL_SYSWM_X11 {
-            panic!("not X11 system");
+            return Err(RenderContextError(format!(
+                "SDL_GetWindowWMInfo failed: {}",
+                sdl2::get_error()
+            )));
         }
+        // mpv needs native display handle, which one depends on the window system SDL ended up using
+        let display_param = match wminfo.subsystem {
+            sdl2_sys::SDL_SYSWM_TYPE::SDL_SYSWM_X11 => {
+                log::info!("creating mpv render context for X11 display");
+                sys::mpv_render_param {
+                    type_: sys::MPV_RENDER_PARAM_X11_DISPLAY,
+                    data: wminfo.info.x11.display as *mut c_void,
+                }
+            }
+            sdl2_sys::SDL_SYSWM_TYPE::SDL_SYSWM_WAYLAND => {
+                log::info!("creating mpv render context for wayland display");
+                sys::mpv_render_param {
+                    type_: sys::MPV_RENDER_PARAM_WL_DISPLAY,
+                    data: wminfo.info.wl.display as *mut c_void,
+                }
+            }
+            subsystem => {
+                return Err(RenderContextError(format!(
+                    "unsupported window system: {:?}, only X11 and wayland are supported",
+                    subsystem
+                )))
+            }
+        };
 
         let mut handle: *mut sys::mpv_render_context = ptr::null_mut();
 
@@ -502,10 +709,7 @@ impl Context {
         };
 
         let mut params = [
-            sys::mpv_render_param {
-                type_: sys::MPV_RENDER_PARAM_X11_DISPLAY,
-                data: wminfo.info.x11.display as *mut c_void,
-            },
+            display_param,
             sys::mpv_render_param {
                 type_: sys::MPV_RENDER_PARAM_API_TYPE,
                 data: sys::MPV_RENDER_API_TYPE_OPENGL.as_ptr() as *mut c_void,
@@ -526,7 +730,10 @@ impl Context {
 
         let result = sys::mpv_render_context_create(&mut handle, self.handle, &mut params[0]);
         if result < 0 {
-            panic!("mpv_render_context_create() failed: {}", result);
+            return Err(RenderContextError(format!(
+                "mpv_render_context_create() failed: {}",
+                result
+            )));
         }
         let mut ctx = Box::new(RenderContext {
             handle,
@@ -538,6 +745,166 @@ impl Context {
             Some(on_mpv_render_update),
             ctx.as_mut() as *mut RenderContext as *mut c_void,
         );
-        ctx
+        Ok(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(format: sys::mpv_format) -> sys::mpv_node {
+        let mut n: sys::mpv_node = unsafe { std::mem::zeroed() };
+        n.format = format;
+        n
+    }
+
+    fn string_node(s: *const c_char) -> sys::mpv_node {
+        let mut n = node(sys::MPV_FORMAT_STRING);
+        n.u.string = s as *mut c_char;
+        n
+    }
+
+    fn i64_node(v: i64) -> sys::mpv_node {
+        let mut n = node(sys::MPV_FORMAT_INT64);
+        n.u.int64 = v;
+        n
+    }
+
+    // list has to outlive the returned node
+    fn list_node(format: sys::mpv_format, list: &mut sys::mpv_node_list) -> sys::mpv_node {
+        let mut n = node(format);
+        n.u.list = list;
+        n
+    }
+
+    // keys are only read for maps
+    fn node_list(values: &mut [sys::mpv_node], keys: Option<&mut [*mut c_char]>) -> sys::mpv_node_list {
+        sys::mpv_node_list {
+            num: values.len() as c_int,
+            values: values.as_mut_ptr(),
+            keys: keys.map(|k| k.as_mut_ptr()).unwrap_or(ptr::null_mut()),
+        }
+    }
+
+    #[test]
+    fn converts_scalars() {
+        let mut flag = node(sys::MPV_FORMAT_FLAG);
+        flag.u.flag = 1;
+        let mut double = node(sys::MPV_FORMAT_DOUBLE);
+        double.u.double_ = 1.5;
+        let s = CString::new("hello").unwrap();
+
+        assert_eq!(convert_node(&flag), Some(Node::Bool(true)));
+        assert_eq!(convert_node(&i64_node(-7)), Some(Node::I64(-7)));
+        assert_eq!(convert_node(&double), Some(Node::F64(1.5)));
+        assert_eq!(
+            convert_node(&string_node(s.as_ptr())),
+            Some(Node::String("hello".to_owned()))
+        );
+        assert_eq!(convert_node(&node(sys::MPV_FORMAT_NONE)), None);
+    }
+
+    #[test]
+    fn empty_and_null_strings_become_empty() {
+        let empty = CString::new("").unwrap();
+        assert_eq!(
+            convert_node(&string_node(empty.as_ptr())),
+            Some(Node::String(String::new()))
+        );
+        assert_eq!(
+            convert_node(&string_node(ptr::null())),
+            Some(Node::String(String::new()))
+        );
+    }
+
+    #[test]
+    fn array_skips_unsupported_values() {
+        let mut values = [i64_node(1), node(sys::MPV_FORMAT_NONE), i64_node(3)];
+        let mut list = node_list(&mut values, None);
+        let n = list_node(sys::MPV_FORMAT_NODE_ARRAY, &mut list);
+        assert_eq!(convert_node(&n), Some(Node::Array(vec![Node::I64(1), Node::I64(3)])));
+    }
+
+    #[test]
+    fn map_null_key_becomes_empty_and_unsupported_values_are_skipped() {
+        let a = CString::new("a").unwrap();
+        let b = CString::new("b").unwrap();
+        let mut values = [i64_node(1), i64_node(2), node(sys::MPV_FORMAT_NONE)];
+        let mut keys = [a.as_ptr() as *mut c_char, ptr::null_mut(), b.as_ptr() as *mut c_char];
+        let mut list = node_list(&mut values, Some(&mut keys));
+        let n = list_node(sys::MPV_FORMAT_NODE_MAP, &mut list);
+
+        let expected = HashMap::from([("a".to_owned(), Node::I64(1)), (String::new(), Node::I64(2))]);
+        assert_eq!(convert_node(&n), Some(Node::Map(expected)));
+    }
+
+    #[test]
+    fn map_keeps_keys_paired_after_unsupported_value() {
+        let keys: Vec<CString> = ["a", "b", "c", "d"].iter().map(|k| CString::new(*k).unwrap()).collect();
+        let mut values = [i64_node(1), node(sys::MPV_FORMAT_NONE), i64_node(3), i64_node(4)];
+        let mut key_ptrs: Vec<*mut c_char> = keys.iter().map(|k| k.as_ptr() as *mut c_char).collect();
+        let mut list = node_list(&mut values, Some(&mut key_ptrs));
+        let n = list_node(sys::MPV_FORMAT_NODE_MAP, &mut list);
+
+        let expected = HashMap::from([
+            ("a".to_owned(), Node::I64(1)),
+            ("c".to_owned(), Node::I64(3)),
+            ("d".to_owned(), Node::I64(4)),
+        ]);
+        assert_eq!(convert_node(&n), Some(Node::Map(expected)));
+    }
+
+    #[test]
+    fn round_trips_through_mpv_node() {
+        let options = HashMap::from([
+            ("start".to_owned(), Node::String("10".to_owned())),
+            ("pause".to_owned(), Node::Bool(true)),
+            ("".to_owned(), Node::F64(0.5)),
+        ]);
+        let command = Node::Array(vec![
+            Node::String("loadfile".to_owned()),
+            Node::String(String::new()),
+            Node::I64(-1),
+            Node::Map(options),
+            Node::Array(Vec::new()),
+        ]);
+        let mut storage = NodeStorage::default();
+        let n = storage.build_node(&command);
+        assert_eq!(convert_node(&n), Some(command));
+    }
+
+    #[test]
+    fn converts_nested_structures() {
+        // {"tracks": [{"id": 1, "title": "x"}, []]}
+        let id = CString::new("id").unwrap();
+        let title = CString::new("title").unwrap();
+        let tracks = CString::new("tracks").unwrap();
+        let x = CString::new("x").unwrap();
+
+        let mut track_values = [i64_node(1), string_node(x.as_ptr())];
+        let mut track_keys = [id.as_ptr() as *mut c_char, title.as_ptr() as *mut c_char];
+        let mut track_list = node_list(&mut track_values, Some(&mut track_keys));
+        let mut no_values: [sys::mpv_node; 0] = [];
+        let mut empty_list = node_list(&mut no_values, None);
+        let mut array_values = [
+            list_node(sys::MPV_FORMAT_NODE_MAP, &mut track_list),
+            list_node(sys::MPV_FORMAT_NODE_ARRAY, &mut empty_list),
+        ];
+        let mut array_list = node_list(&mut array_values, None);
+        let mut root_values = [list_node(sys::MPV_FORMAT_NODE_ARRAY, &mut array_list)];
+        let mut root_keys = [tracks.as_ptr() as *mut c_char];
+        let mut root_list = node_list(&mut root_values, Some(&mut root_keys));
+        let root = list_node(sys::MPV_FORMAT_NODE_MAP, &mut root_list);
+
+        let track = HashMap::from([
+            ("id".to_owned(), Node::I64(1)),
+            ("title".to_owned(), Node::String("x".to_owned())),
+        ]);
+        let expected = HashMap::from([(
+            "tracks".to_owned(),
+            Node::Array(vec![Node::Map(track), Node::Array(Vec::new())]),
+        )]);
+        assert_eq!(convert_node(&root), Some(Node::Map(expected)));
     }
 }
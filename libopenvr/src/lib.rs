@@ -3,6 +3,7 @@ use glam::{Mat4, Vec4};
 use lazy_static::lazy_static;
 use std::{
     ffi::{c_void, CStr, CString},
+    fmt,
     mem::MaybeUninit,
     ptr,
     sync::Mutex,
@@ -19,7 +20,10 @@ pub enum Eye {
     Right = sys::EVREye_Eye_Right as isize,
 }
 
-#[derive(Copy, Clone)]
+pub const MAX_TRACKED_DEVICE_COUNT: usize = sys::k_unMaxTrackedDeviceCount as usize;
+pub const HMD_DEVICE_INDEX: usize = sys::k_unTrackedDeviceIndex_Hmd as usize;
+
+#[derive(Copy, Clone, PartialEq)]
 pub enum TrackedDeviceClass {
     Invalid = sys::ETrackedDeviceClass_TrackedDeviceClass_Invalid as isize,
     HMD = sys::ETrackedDeviceClass_TrackedDeviceClass_HMD as isize,
@@ -29,26 +33,103 @@ pub enum TrackedDeviceClass {
     DisplayRedirect = sys::ETrackedDeviceClass_TrackedDeviceClass_DisplayRedirect as isize,
 }
 
-fn load<T>(suffix: &[u8]) -> *const T {
+// openvr runtime is not available (e.g. SteamVR is not running) or it failed to provide an interface
+#[derive(Debug)]
+pub struct VrInitError(pub String);
+
+impl fmt::Display for VrInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for VrInitError {}
+
+fn init_error_description(error: sys::EVRInitError) -> String {
+    unsafe { CStr::from_ptr(sys::VR_GetVRInitErrorAsEnglishDescription(error)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn load<T>(suffix: &[u8]) -> Result<&'static T, VrInitError> {
     let mut magic = Vec::from(b"FnTable:".as_ref());
     magic.extend(suffix);
     let mut error = sys::EVRInitError_VRInitError_None;
     let result = unsafe { sys::VR_GetGenericInterface(magic.as_ptr() as *const i8, &mut error) };
-    if error != sys::EVRInitError_VRInitError_None {
-        let msg = unsafe { CStr::from_ptr(sys::VR_GetVRInitErrorAsEnglishDescription(error)) }
-            .to_str()
-            .unwrap();
-        panic!("openvr subsystem init failure: {}", msg);
+    if error != sys::EVRInitError_VRInitError_None || result == 0 {
+        return Err(VrInitError(format!(
+            "openvr subsystem init failure: {}",
+            init_error_description(error)
+        )));
     }
-    result as *const T
+    Ok(unsafe { &*(result as *const T) })
 }
 
 pub struct System(&'static sys::VR_IVRSystem_FnTable);
-pub struct Compositor(&'static sys::VR_IVRCompositor_FnTable);
+// system table is needed to look up device classes of the returned poses
+pub struct Compositor(
+    &'static sys::VR_IVRCompositor_FnTable,
+    &'static sys::VR_IVRSystem_FnTable,
+);
+pub struct RenderModels(&'static sys::VR_IVRRenderModels_FnTable);
 
 pub struct Context {
     pub system: System,
     pub compositor: Compositor,
+    pub render_models: RenderModels,
+}
+
+// subset of openvr events we care about, others are skipped by poll_events
+#[derive(Copy, Clone, Debug)]
+pub enum VrEvent {
+    DashboardActivated,
+    DashboardDeactivated,
+    // user put on/took off the device (proximity sensor for HMD), contains device index
+    UserInteractionStarted(usize),
+    UserInteractionEnded(usize),
+}
+
+#[derive(Copy, Clone)]
+pub struct TrackedPose {
+    // device to tracking space transform, valid only if is_valid is true
+    pub device_to_absolute: Mat4,
+    // false when tracking is lost, e.g. device is out of sight of base stations
+    pub is_valid: bool,
+    pub device_class: TrackedDeviceClass,
+}
+
+#[derive(Copy, Clone)]
+pub struct ControllerState {
+    // bit mask of pressed buttons, bit index is openvr EVRButtonId
+    pub buttons_pressed: u64,
+}
+
+impl ControllerState {
+    pub fn is_trigger_pressed(&self) -> bool {
+        self.buttons_pressed & (1u64 << sys::EVRButtonId_k_EButton_SteamVR_Trigger) != 0
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct RenderModelVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub texcoord: [f32; 2],
+}
+
+pub struct RenderModel {
+    pub vertices: Vec<RenderModelVertex>,
+    // 3 indices per triangle
+    pub indices: Vec<u16>,
+    // negative if model has no texture
+    pub diffuse_texture_id: i32,
+}
+
+pub struct RenderModelTexture {
+    pub width: u32,
+    pub height: u32,
+    // RGBA8, width * height * 4 bytes
+    pub data: Vec<u8>,
 }
 
 fn hmd_matrix44_to_glam(m: sys::HmdMatrix44_t) -> Mat4 {
@@ -69,21 +150,28 @@ lazy_static! {
 }
 
 impl Context {
-    pub fn create(typ: ApplicationType) -> Box<Context> {
-        unsafe {
-            let mut error = sys::EVRInitError_VRInitError_None;
-            sys::VR_InitInternal(&mut error, typ as sys::EVRApplicationType);
-            if error != sys::EVRInitError_VRInitError_None {
-                let msg = CStr::from_ptr(sys::VR_GetVRInitErrorAsEnglishDescription(error))
-                    .to_str()
-                    .unwrap();
-                panic!("openvr init failure: {}", msg);
-            }
-            Box::new(Context {
-                system: System(&*load(sys::IVRSystem_Version)),
-                compositor: Compositor(&*load(sys::IVRCompositor_Version)),
-            })
+    pub fn create(typ: ApplicationType) -> Result<Box<Context>, VrInitError> {
+        let mut error = sys::EVRInitError_VRInitError_None;
+        unsafe { sys::VR_InitInternal(&mut error, typ as sys::EVRApplicationType) };
+        if error != sys::EVRInitError_VRInitError_None {
+            return Err(VrInitError(format!(
+                "openvr init failure: {}",
+                init_error_description(error)
+            )));
         }
+        let load_all = || -> Result<Box<Context>, VrInitError> {
+            let system = load(sys::IVRSystem_Version)?;
+            Ok(Box::new(Context {
+                system: System(system),
+                compositor: Compositor(load(sys::IVRCompositor_Version)?, system),
+                render_models: RenderModels(load(sys::IVRRenderModels_Version)?),
+            }))
+        };
+        // runtime was initialized, it has to be shut down if we can't use it
+        load_all().map_err(|e| {
+            unsafe { sys::VR_ShutdownInternal() };
+            e
+        })
     }
 
     pub fn shutdown(&self) {
@@ -93,6 +181,18 @@ impl Context {
     }
 }
 
+fn tracked_device_class(system: &sys::VR_IVRSystem_FnTable, index: usize) -> TrackedDeviceClass {
+    let class = unsafe { system.GetTrackedDeviceClass.unwrap()(index as sys::TrackedDeviceIndex_t) };
+    match class {
+        sys::ETrackedDeviceClass_TrackedDeviceClass_HMD => TrackedDeviceClass::HMD,
+        sys::ETrackedDeviceClass_TrackedDeviceClass_Controller => TrackedDeviceClass::Controller,
+        sys::ETrackedDeviceClass_TrackedDeviceClass_GenericTracker => TrackedDeviceClass::GenericTracker,
+        sys::ETrackedDeviceClass_TrackedDeviceClass_TrackingReference => TrackedDeviceClass::TrackingReference,
+        sys::ETrackedDeviceClass_TrackedDeviceClass_DisplayRedirect => TrackedDeviceClass::DisplayRedirect,
+        _ => TrackedDeviceClass::Invalid,
+    }
+}
+
 impl System {
     pub fn get_projection_matrix(&self, eye: Eye, near: f32, far: f32) -> Mat4 {
         unsafe { hmd_matrix44_to_glam(self.0.GetProjectionMatrix.unwrap()(eye as sys::EVREye, near, -far)) }
@@ -110,6 +210,72 @@ impl System {
         result
     }
 
+    pub fn get_tracked_device_class(&self, index: usize) -> TrackedDeviceClass {
+        tracked_device_class(self.0, index)
+    }
+
+    // drains the event queue, should be called every frame
+    pub fn poll_events(&self) -> Vec<VrEvent> {
+        let mut events = Vec::new();
+        unsafe {
+            let mut event: sys::VREvent_t = MaybeUninit::zeroed().assume_init();
+            while self.0.PollNextEvent.unwrap()(&mut event, std::mem::size_of::<sys::VREvent_t>() as u32) {
+                let index = event.trackedDeviceIndex as usize;
+                let ev = match event.eventType as sys::EVREventType {
+                    sys::EVREventType_VREvent_DashboardActivated => VrEvent::DashboardActivated,
+                    sys::EVREventType_VREvent_DashboardDeactivated => VrEvent::DashboardDeactivated,
+                    sys::EVREventType_VREvent_TrackedDeviceUserInteractionStarted => {
+                        VrEvent::UserInteractionStarted(index)
+                    }
+                    sys::EVREventType_VREvent_TrackedDeviceUserInteractionEnded => VrEvent::UserInteractionEnded(index),
+                    _ => continue,
+                };
+                events.push(ev);
+            }
+        }
+        events
+    }
+
+    pub fn get_controller_state(&self, index: usize) -> Option<ControllerState> {
+        unsafe {
+            let mut state: sys::VRControllerState_t = MaybeUninit::zeroed().assume_init();
+            let ok = self.0.GetControllerState.unwrap()(
+                index as sys::TrackedDeviceIndex_t,
+                &mut state,
+                std::mem::size_of::<sys::VRControllerState_t>() as u32,
+            );
+            ok.then(|| ControllerState {
+                buttons_pressed: state.ulButtonPressed,
+            })
+        }
+    }
+
+    pub fn get_render_model_name(&self, index: usize) -> Option<String> {
+        unsafe {
+            let get = self.0.GetStringTrackedDeviceProperty.unwrap();
+            let prop = sys::ETrackedDeviceProperty_Prop_RenderModelName_String;
+            let mut error = sys::ETrackedPropertyError_TrackedProp_Success;
+            // first call returns required buffer size including null terminator
+            let size = get(index as sys::TrackedDeviceIndex_t, prop, ptr::null_mut(), 0, &mut error);
+            if size == 0 {
+                return None;
+            }
+            let mut buf: Vec<u8> = vec![0; size as usize];
+            get(
+                index as sys::TrackedDeviceIndex_t,
+                prop,
+                buf.as_mut_ptr() as *mut i8,
+                size,
+                &mut error,
+            );
+            if error != sys::ETrackedPropertyError_TrackedProp_Success {
+                return None;
+            }
+            buf.truncate((size - 1) as usize);
+            String::from_utf8(buf).ok()
+        }
+    }
+
     pub fn get_output_device_for_vulkan(&self, instance: ash::vk::Instance) -> ash::vk::PhysicalDevice {
         let mut result: u64 = 0;
         unsafe {
@@ -190,14 +356,33 @@ impl Compositor {
         }
     }
 
-    pub fn wait_get_hmd_pose(&self) -> Mat4 {
+    // Returns poses of all tracked devices indexed by device index, None for disconnected devices. HMD is at
+    // HMD_DEVICE_INDEX.
+    pub fn wait_get_poses(&self) -> Vec<Option<TrackedPose>> {
         unsafe {
-            let mut poses: [sys::TrackedDevicePose_t; 1] = MaybeUninit::zeroed().assume_init();
-            self.0.WaitGetPoses.unwrap()(poses.as_mut_ptr(), 1, ptr::null_mut(), 0);
-            hmd_matrix34_to_glam(poses[0].mDeviceToAbsoluteTracking)
+            let mut poses: [sys::TrackedDevicePose_t; MAX_TRACKED_DEVICE_COUNT] = MaybeUninit::zeroed().assume_init();
+            self.0.WaitGetPoses.unwrap()(poses.as_mut_ptr(), MAX_TRACKED_DEVICE_COUNT as u32, ptr::null_mut(), 0);
+            poses
+                .iter()
+                .enumerate()
+                .map(|(index, p)| {
+                    p.bDeviceIsConnected.then(|| TrackedPose {
+                        device_to_absolute: hmd_matrix34_to_glam(p.mDeviceToAbsoluteTracking),
+                        is_valid: p.bPoseIsValid,
+                        device_class: tracked_device_class(self.1, index),
+                    })
+                })
+                .collect()
         }
     }
 
+    // blocks until it's time to render, same as wait_get_poses, so only one of them should be called per frame
+    pub fn wait_get_hmd_pose(&self) -> Mat4 {
+        self.wait_get_poses()[HMD_DEVICE_INDEX]
+            .map(|p| p.device_to_absolute)
+            .unwrap_or(Mat4::IDENTITY)
+    }
+
     pub fn submit_opengl(&self, eye: Eye, texture: i32) {
         unsafe {
             let mut texture = sys::Texture_t {
@@ -238,3 +423,72 @@ impl Compositor {
         }
     }
 }
+
+fn render_model_error_name(fns: &sys::VR_IVRRenderModels_FnTable, error: sys::EVRRenderModelError) -> String {
+    unsafe {
+        CStr::from_ptr(fns.GetRenderModelErrorNameFromEnum.unwrap()(error))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+// Loading is asynchronous, both functions return Ok(None) while openvr is still loading the data, the caller is
+// supposed to retry every frame until it gets the result. The data is copied out and openvr side is freed right away.
+impl RenderModels {
+    pub fn load_render_model(&self, name: &str) -> Result<Option<RenderModel>, String> {
+        let name = CString::new(name).map_err(|e| e.to_string())?;
+        unsafe {
+            let mut model: *mut sys::RenderModel_t = ptr::null_mut();
+            let error = self.0.LoadRenderModel_Async.unwrap()(name.as_ptr() as *mut i8, &mut model);
+            if error == sys::EVRRenderModelError_VRRenderModelError_Loading {
+                return Ok(None);
+            }
+            if error != sys::EVRRenderModelError_VRRenderModelError_None {
+                return Err(render_model_error_name(self.0, error));
+            }
+            let m = &*model;
+            let vertices = std::slice::from_raw_parts(m.rVertexData, m.unVertexCount as usize)
+                .iter()
+                .map(|v| RenderModelVertex {
+                    position: v.vPosition.v,
+                    normal: v.vNormal.v,
+                    texcoord: v.rfTextureCoord,
+                })
+                .collect();
+            let indices = std::slice::from_raw_parts(m.rIndexData, m.unTriangleCount as usize * 3).to_vec();
+            let diffuse_texture_id = m.diffuseTextureId;
+            self.0.FreeRenderModel.unwrap()(model);
+            Ok(Some(RenderModel {
+                vertices,
+                indices,
+                diffuse_texture_id,
+            }))
+        }
+    }
+
+    pub fn load_texture(&self, id: i32) -> Result<Option<RenderModelTexture>, String> {
+        unsafe {
+            let mut texture: *mut sys::RenderModel_TextureMap_t = ptr::null_mut();
+            let error = self.0.LoadTexture_Async.unwrap()(id, &mut texture);
+            if error == sys::EVRRenderModelError_VRRenderModelError_Loading {
+                return Ok(None);
+            }
+            if error != sys::EVRRenderModelError_VRRenderModelError_None {
+                return Err(render_model_error_name(self.0, error));
+            }
+            let t = &*texture;
+            let width = t.unWidth as u32;
+            let height = t.unHeight as u32;
+            // RGBA8, size comes from the runtime, so it's not trusted to fit
+            let size = (width as usize)
+                .checked_mul(height as usize)
+                .and_then(|v| v.checked_mul(4));
+            let data = size.map(|size| std::slice::from_raw_parts(t.rubTextureMapData, size).to_vec());
+            self.0.FreeTexture.unwrap()(texture);
+            match data {
+                Some(data) => Ok(Some(RenderModelTexture { width, height, data })),
+                None => Err(format!("render model texture is too large: {}x{}", width, height)),
+            }
+        }
+    }
+}
@@ -1,28 +1,91 @@
+use std::collections::VecDeque;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Action {
     None,
     Quit,
     ToggleUI,
+    // compact play/pause, seek and volume bar, mutually exclusive with the full UI
+    ToggleMiniUI,
+    // small projection picker for the current file, mutually exclusive with the full and mini UI
+    ToggleQuickProjection,
+    // FPS and frame time stats window
+    ToggleStats,
+    // desktop window fullscreen
+    ToggleFullscreen,
     ResetWorldOrigin,
+    // move flat screen and UI panel in front of the viewer, world origin stays as is
+    RecenterScreen,
+    // remember current view direction for the current file, it becomes world origin whenever the file is loaded
+    SaveFileOrientation,
+    // pinned UI panel stays where it is instead of following the viewer every time it's shown
+    TogglePinUI,
+    // session-only mono override, stored per-file mode is kept intact
+    ToggleForceMono,
+    // level companion camera, keeping its position and yaw
+    ResetCameraRoll,
+    // rotate companion camera by Config::snap_turn_degrees, only when snap turn is enabled
+    SnapTurnLeft,
+    SnapTurnRight,
+    // next aspect ratio of the current file, flat projection only
+    CycleAspectRatio,
+    // load next/previous video file in the directory of the current file
+    NextFile,
+    PreviousFile,
+    // FileDB export/import to/from default export path
+    ExportFileDB,
+    ImportFileDB,
+    // revert the last UI change of current file's projection, mode, convergence or screen placement
+    Undo,
+    // current playback position as HH:MM:SS to system clipboard
+    CopyTimestamp,
+    // drop stored settings of the current file
+    ForgetCurrentFile,
+    // reset config to defaults, optionally forgetting all per-file settings as well
+    ResetAllSettings { forget_files: bool },
     Command(Vec<String>),
 }
 
+// actions are queued, several sources (UI, keyboard, mouse, controllers) can put actions within a single frame
 pub struct ActionBin {
-    action: Option<Action>,
+    actions: VecDeque<Action>,
 }
 
 impl ActionBin {
     pub fn create() -> ActionBin {
-        ActionBin { action: None }
+        ActionBin {
+            actions: VecDeque::new(),
+        }
     }
 
     pub fn put(&mut self, a: Action) {
-        self.action = Some(a);
+        self.actions.push_back(a);
     }
 
+    // next action in the order they were put, call until it returns None
     pub fn dispatch(&mut self) -> Option<Action> {
-        std::mem::replace(&mut self.action, None)
+        self.actions.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatches_all_actions_in_order() {
+        let mut bin = ActionBin::create();
+        bin.put(Action::ToggleUI);
+        bin.put(Action::NextFile);
+        bin.put(Action::Command(vec!["cycle".to_owned(), "pause".to_owned()]));
+        assert_eq!(bin.dispatch(), Some(Action::ToggleUI));
+        assert_eq!(bin.dispatch(), Some(Action::NextFile));
+        assert_eq!(
+            bin.dispatch(),
+            Some(Action::Command(vec!["cycle".to_owned(), "pause".to_owned()]))
+        );
+        assert_eq!(bin.dispatch(), None);
     }
 }
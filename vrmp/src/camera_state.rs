@@ -1,5 +1,5 @@
 use bytemuck_derive::{Pod, Zeroable};
-use glam::{Mat3, Mat4};
+use glam::{Mat3, Mat4, Vec2};
 
 use crate::{
     enums::{Mode, Projection},
@@ -16,7 +16,20 @@ pub struct CameraState {
     eye_index: u32,
     mode: u32,
     stereo_adjust: f32,
-    shader_debug: f32,
+    debug_mode: u32,
+    eye_gain: f32,
+    vignette: f32,
+    // offset is 8-byte aligned, as vec2 in WGSL requires
+    flat_pan: Vec2,
+    flat_zoom: f32,
+    // bit 0 - horizontal, bit 1 - vertical
+    flip: u32,
+    // ER 180 vertical coverage and pitch offset in radians, see FileData::er180_v_fov
+    er180_v_fov: f32,
+    er180_pitch: f32,
+    // vec4 is 16-byte aligned in WGSL, offset is 240
+    // linear RGB, alpha is unused, see Config::background_color
+    background: [f32; 4],
 }
 
 impl CameraState {
@@ -25,6 +38,8 @@ impl CameraState {
         view_mat: Mat4,
         world_origin: Mat4,
         eye_index: u32,
+        // effective stereo mode, might differ from fdata's one, see Global::force_mono
+        mode: Mode,
         fdata: Option<&FileData>,
         g: &General,
     ) -> CameraState {
@@ -40,7 +55,10 @@ impl CameraState {
             })
             .unwrap_or(0.0)
             * cond!(eye_index == 0, -1.0, 1.0);
-        let mode = fdata.map(|d| d.mode).unwrap_or(Mode::Mono);
+        // gain is per viewer's eye, i.e. it's what the eye sees regardless of stereo mode
+        let eye_gain = fdata
+            .map(|d| cond!(eye_index == 0, d.left_eye_gain, d.right_eye_gain))
+            .unwrap_or(1.0);
         let (eye_index, mode) = match mode {
             Mode::Mono => (eye_index, 0),
             Mode::LeftRight => (eye_index, 1),
@@ -55,7 +73,98 @@ impl CameraState {
             eye_index,
             mode,
             stereo_adjust: stereo_adjust,
-            shader_debug: g.shader_debug,
+            debug_mode: g.debug_mode as u32,
+            eye_gain,
+            vignette: 0.0,
+            flat_pan: fdata.map(|d| d.flat_pan).unwrap_or(Vec2::ZERO),
+            flat_zoom: fdata.map(|d| d.flat_zoom).unwrap_or(1.0),
+            flip: fdata
+                .map(|d| cond!(d.flip_horizontal, 1, 0) | cond!(d.flip_vertical, 2, 0))
+                .unwrap_or(0),
+            er180_v_fov: fdata.map(|d| d.er180_v_fov).unwrap_or(180.0).to_radians(),
+            er180_pitch: fdata.map(|d| d.er180_pitch_offset).unwrap_or(0.0).to_radians(),
+            background: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    // comfort vignette is only drawn in VR, see Scene::comfort_vignette
+    pub fn with_vignette(self, vignette: f32) -> CameraState {
+        CameraState { vignette, ..self }
+    }
+
+    pub fn with_background(self, [r, g, b]: [f32; 3]) -> CameraState {
+        CameraState {
+            background: [r, g, b, 1.0],
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn camera_state(eye_index: u32, mode: Mode, fdata: Option<&FileData>) -> CameraState {
+        let g = General::new();
+        CameraState::from_proj_and_view(
+            Mat4::IDENTITY,
+            Mat4::IDENTITY,
+            Mat4::IDENTITY,
+            eye_index,
+            mode,
+            fdata,
+            &g,
+        )
+    }
+
+    #[test]
+    fn maps_eye_index_and_mode() {
+        // (mode, viewer's eye) => (eye index in the frame, shader mode)
+        let cases = [
+            (Mode::Mono, 0, (0, 0)),
+            (Mode::Mono, 1, (1, 0)),
+            (Mode::LeftRight, 0, (0, 1)),
+            (Mode::LeftRight, 1, (1, 1)),
+            (Mode::RightLeft, 0, (1, 1)),
+            (Mode::RightLeft, 1, (0, 1)),
+            (Mode::TopBottom, 0, (0, 2)),
+            (Mode::TopBottom, 1, (1, 2)),
+            (Mode::BottomTop, 0, (1, 2)),
+            (Mode::BottomTop, 1, (0, 2)),
+        ];
+        for (mode, eye, expected) in cases {
+            let cs = camera_state(eye, mode, None);
+            assert_eq!((cs.eye_index, cs.mode), expected, "mode {}, eye {}", mode as u32, eye);
+        }
+    }
+
+    #[test]
+    fn stereo_adjust_sign_depends_on_viewer_eye() {
+        let mut fdata = FileData::new(&Config::defaults());
+        fdata.stereo_convergence = 2.0;
+        fdata.stereo_convergence_flat = 0.5;
+        for mode in [Mode::LeftRight, Mode::RightLeft, Mode::TopBottom, Mode::BottomTop] {
+            fdata.projection = Projection::Er180;
+            assert_eq!(camera_state(0, mode, Some(&fdata)).stereo_adjust, -2.0f32.to_radians());
+            assert_eq!(camera_state(1, mode, Some(&fdata)).stereo_adjust, 2.0f32.to_radians());
+            fdata.projection = Projection::Flat;
+            assert_eq!(camera_state(0, mode, Some(&fdata)).stereo_adjust, -0.5);
+            assert_eq!(camera_state(1, mode, Some(&fdata)).stereo_adjust, 0.5);
+        }
+        assert_eq!(camera_state(0, Mode::LeftRight, None).stereo_adjust, 0.0);
+        assert_eq!(camera_state(1, Mode::LeftRight, None).stereo_adjust, 0.0);
+    }
+
+    #[test]
+    fn eye_gain_follows_viewer_eye() {
+        let mut fdata = FileData::new(&Config::defaults());
+        fdata.left_eye_gain = 0.5;
+        fdata.right_eye_gain = 1.5;
+        // swapped modes sample the other half of the frame, but gain is still per viewer's eye
+        for mode in [Mode::LeftRight, Mode::RightLeft, Mode::BottomTop] {
+            assert_eq!(camera_state(0, mode, Some(&fdata)).eye_gain, 0.5);
+            assert_eq!(camera_state(1, mode, Some(&fdata)).eye_gain, 1.5);
         }
     }
 }
@@ -1,32 +1,213 @@
+use crate::enums::{Mode, PresentMode, Projection, SharedOutputSource, SortMode};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+const MAX_RECENT_FILES: usize = 20;
+
+// Named video format, applied to the current file in one click, see FileData::apply_preset. Convergence goes to the
+// flat or the immersive convergence depending on projection. FOV is the ER 180 vertical coverage in degrees, None
+// keeps the file's value.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProjectionPreset {
+    pub name: String,
+    pub projection: Projection,
+    pub mode: Mode,
+    #[serde(default)]
+    pub stereo_convergence: f32,
+    #[serde(default)]
+    pub er180_v_fov: Option<f32>,
+}
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default = "default_favorite_directories")]
     pub favorite_directories: Vec<PathBuf>,
+    // most recently played first
+    #[serde(default = "default_recent_files")]
+    pub recent_files: Vec<PathBuf>,
     #[serde(default = "default_show_video_files_only")]
     pub show_video_files_only: bool,
     #[serde(default = "default_show_hidden_files")]
     pub show_hidden_files: bool,
+    // file name extensions (without the dot) recognized as video files, case-insensitive
+    #[serde(default = "default_video_extensions")]
+    pub video_extensions: Vec<String>,
+    // "continue watching" lists files with seen percentage within this range
+    #[serde(default = "default_continue_watching_min_percent")]
+    pub continue_watching_min_percent: f32,
+    #[serde(default = "default_continue_watching_max_percent")]
+    pub continue_watching_max_percent: f32,
+    // file browser entries order, directories are always listed first
+    #[serde(default = "default_sort_mode")]
+    pub sort_mode: SortMode,
+    #[serde(default = "default_sort_descending")]
+    pub sort_descending: bool,
     #[serde(default = "default_ui_distance")]
     pub ui_distance: f32,
     #[serde(default = "default_ui_angle")]
     pub ui_angle: f32,
     #[serde(default = "default_ui_scale")]
     pub ui_scale: f32,
+    // 0 - flat UI panel, 1 - panel is bent around the viewer at ui_distance, see scene::ui_curvature_radius
+    #[serde(default = "default_ui_curvature")]
+    pub ui_curvature: f32,
     #[serde(default = "default_camera_movement_speed")]
     pub camera_movement_speed: f32,
     #[serde(default = "default_camera_sensitivity")]
     pub camera_sensitivity: f32,
     #[serde(default = "default_cursor_sensitivity")]
     pub cursor_sensitivity: f32,
+    // companion camera turns in fixed steps (Q/E) instead of following horizontal mouse motion, not used in VR
+    #[serde(default = "default_snap_turn")]
+    pub snap_turn: bool,
+    #[serde(default = "default_snap_turn_degrees")]
+    pub snap_turn_degrees: f32,
+    // flat screen distance/scale used for files which were never seen before
+    #[serde(default = "default_flat_distance")]
+    pub flat_distance: f32,
+    #[serde(default = "default_flat_scale")]
+    pub flat_scale: f32,
+    // stereo convergence in degrees used for files which were never seen before
+    #[serde(default = "default_stereo_convergence")]
+    pub default_stereo_convergence: f32,
+    // vertical FOV of the companion window camera, clamped to 30..120
+    #[serde(default = "default_companion_fov_degrees")]
+    pub companion_fov_degrees: f32,
+    // desktop window present mode, Fifo caps it at monitor refresh rate
+    #[serde(default = "default_present_mode")]
+    pub present_mode: PresentMode,
+    // sRGB color of the void around the video, see background_color_linear
+    #[serde(default = "default_background_color")]
+    pub background_color: [f32; 3],
+    // clip planes in meters, for both companion window and VR eyes, see clip_planes
+    #[serde(default = "default_near_plane")]
+    pub near_plane: f32,
+    #[serde(default = "default_far_plane")]
+    pub far_plane: f32,
+    // bigger values make content feel bigger by bringing virtual eyes closer together, see scale_eye_to_head_mat
+    #[serde(default = "default_world_scale")]
+    pub world_scale: f32,
+    // while paused and nothing moves, submit previous eye frames instead of rendering them again
+    #[serde(default = "default_static_frame_skip")]
+    pub static_frame_skip: bool,
+    // draw ER 360/180 video on a sphere mesh instead of computing the projection per pixel of a fullscreen triangle,
+    // gives smoother texture filtering near the poles
+    #[serde(default = "default_sphere_mesh")]
+    pub sphere_mesh: bool,
+    // eye texture size multiplier applied to the size recommended by openvr
+    #[serde(default = "default_supersample_factor")]
+    pub supersample_factor: f32,
+    // longest side of the video texture in pixels, bigger videos are downscaled by mpv, 0 disables the limit. The
+    // scale filter runs on CPU, so hardware decoded frames of such videos are copied back from GPU memory, which costs
+    // CPU time and bandwidth on every frame
+    #[serde(default = "default_max_video_size")]
+    pub max_video_size: u32,
+    // MSAA sample count for eyes and companion window (1 or 4), applied at startup
+    #[serde(default = "default_msaa_samples")]
+    pub msaa_samples: u32,
+    // use external semaphores for GL<->VK sync, otherwise fall back to glFinish and fences, applied at startup
+    #[serde(default = "default_gl_vk_semaphore_sync")]
+    pub gl_vk_semaphore_sync: bool,
+    // mpv renders into a half-float texture in linear light instead of 8-bit sRGB one, avoids banding at the cost of
+    // twice the memory, HDR highlights are still clipped by 8-bit eye targets, applied at startup
+    #[serde(default = "default_hdr_shared_texture")]
+    pub hdr_shared_texture: bool,
+    // pick stereo mode from video metadata for files which were never seen before
+    #[serde(default = "default_auto_detect_stereo_mode")]
+    pub auto_detect_stereo_mode: bool,
+    // recenter world origin to the current head direction every time a new file loads
+    #[serde(default = "default_reset_origin_on_file_load")]
+    pub reset_origin_on_file_load: bool,
+    // in VR mode companion window shows the left eye view instead of its own WASD/mouse camera
+    #[serde(default = "default_companion_mirror_vr")]
+    pub companion_mirror_vr: bool,
+    // darken the periphery in VR while the view moves fast relative to the world, reduces motion sickness
+    #[serde(default = "default_comfort_vignette")]
+    pub comfort_vignette: bool,
+    // 0..1, vignette intensity at full speed
+    #[serde(default = "default_comfort_vignette_strength")]
+    pub comfort_vignette_strength: f32,
+    // rotation speed in degrees per second at which vignette reaches full strength
+    #[serde(default = "default_comfort_vignette_speed")]
+    pub comfort_vignette_speed: f32,
+    // briefly narrow the field of view in VR when world origin is recentered
+    #[serde(default = "default_comfort_fov_reduction")]
+    pub comfort_fov_reduction: bool,
+    // how long it takes to restore the full field of view
+    #[serde(default = "default_comfort_fov_reduction_secs")]
+    pub comfort_fov_reduction_secs: f32,
+    // drop VR eye resolution while playback is paused for this many seconds, see Global::update_power_saving
+    #[serde(default = "default_idle_power_saving")]
+    pub idle_power_saving: bool,
+    #[serde(default = "default_idle_power_saving_secs")]
+    pub idle_power_saving_secs: f32,
+    // ramp volume up from zero when a file starts, over this many seconds, 0 disables it
+    #[serde(default = "default_volume_fade_in_secs")]
+    pub volume_fade_in_secs: f32,
+    // volume of files without a saved one (see FileData::volume), so that they don't inherit the previous file's
+    #[serde(default = "default_volume")]
+    pub default_volume: f64,
+    // desktop window goes fullscreen when a file starts playing and back to windowed when playback stops,
+    // ignored in VR mode
+    #[serde(default = "default_fullscreen_on_play")]
+    pub fullscreen_on_play: bool,
+    // pause playback while the desktop window is not focused, ignored in VR mode
+    #[serde(default = "default_pause_on_focus_loss")]
+    pub pause_on_focus_loss: bool,
+    // d-pad and face buttons of a gamepad move focus between UI items while UI is shown
+    #[serde(default = "default_gamepad_ui_navigation")]
+    pub gamepad_ui_navigation: bool,
+    // relative seek step in seconds for Left/Right arrows and Shift+Left/Right arrows
+    #[serde(default = "default_seek_small_secs")]
+    pub seek_small_secs: f64,
+    #[serde(default = "default_seek_large_secs")]
+    pub seek_large_secs: f64,
+    // expose rendered frames to other applications, applied at startup
+    #[serde(default = "default_shared_output")]
+    pub shared_output: SharedOutputSource,
+    // mpv "hwdec" and "profile" options, applied at startup, hwdec can be toggled in the UI afterwards, empty profile
+    // means mpv's default one
+    #[serde(default = "default_mpv_hwdec")]
+    pub mpv_hwdec: String,
+    #[serde(default = "default_mpv_profile")]
+    pub mpv_profile: String,
+    // file database journal mode and how long a write waits for a lock held by another connection before failing,
+    // applied at startup
+    #[serde(default = "default_sqlite_wal")]
+    pub sqlite_wal: bool,
+    #[serde(default = "default_sqlite_busy_timeout_ms")]
+    pub sqlite_busy_timeout_ms: u64,
+    // listed in the "Preset" combo box of video settings
+    #[serde(default = "default_projection_presets")]
+    pub projection_presets: Vec<ProjectionPreset>,
+}
+
+fn default_projection_presets() -> Vec<ProjectionPreset> {
+    let preset = |name: &str, projection, mode| ProjectionPreset {
+        name: name.to_owned(),
+        projection,
+        mode,
+        stereo_convergence: 0.0,
+        er180_v_fov: None,
+    };
+    vec![
+        preset("VR180 SBS", Projection::Er180, Mode::LeftRight),
+        preset("VR180 Fisheye SBS", Projection::Fisheye, Mode::LeftRight),
+        preset("360 Mono", Projection::Er360, Mode::Mono),
+        preset("360 TB", Projection::Er360, Mode::TopBottom),
+        preset("360 EAC TB", Projection::Eac, Mode::TopBottom),
+        preset("3D Movie SBS", Projection::Flat, Mode::LeftRight),
+    ]
 }
 
 fn default_favorite_directories() -> Vec<PathBuf> {
     Default::default()
 }
 
+fn default_recent_files() -> Vec<PathBuf> {
+    Default::default()
+}
+
 fn default_show_video_files_only() -> bool {
     true
 }
@@ -35,6 +216,32 @@ fn default_show_hidden_files() -> bool {
     Default::default()
 }
 
+fn default_video_extensions() -> Vec<String> {
+    [
+        "avi", "flv", "m4p", "m4v", "mkv", "mov", "mp2", "mp4", "mpe", "mpeg", "mpg", "mpv", "ogg", "qt", "swf",
+        "webm", "wmv",
+    ]
+    .into_iter()
+    .map(|v| v.to_owned())
+    .collect()
+}
+
+fn default_continue_watching_min_percent() -> f32 {
+    5.0
+}
+
+fn default_continue_watching_max_percent() -> f32 {
+    90.0
+}
+
+fn default_sort_mode() -> SortMode {
+    SortMode::Name
+}
+
+fn default_sort_descending() -> bool {
+    false
+}
+
 fn default_ui_distance() -> f32 {
     0.45
 }
@@ -43,6 +250,10 @@ fn default_ui_angle() -> f32 {
     35.0
 }
 
+fn default_ui_curvature() -> f32 {
+    0.0
+}
+
 fn default_ui_scale() -> f32 {
     0.45
 }
@@ -51,6 +262,14 @@ fn default_camera_movement_speed() -> f32 {
     5.0
 }
 
+fn default_snap_turn() -> bool {
+    false
+}
+
+fn default_snap_turn_degrees() -> f32 {
+    30.0
+}
+
 fn default_camera_sensitivity() -> f32 {
     0.05
 }
@@ -59,7 +278,181 @@ fn default_cursor_sensitivity() -> f32 {
     1.0
 }
 
+pub fn default_flat_distance() -> f32 {
+    5.0
+}
+
+pub fn default_flat_scale() -> f32 {
+    4.0
+}
+
+fn default_stereo_convergence() -> f32 {
+    0.0
+}
+
+pub fn default_world_scale() -> f32 {
+    1.0
+}
+
+fn default_sphere_mesh() -> bool {
+    false
+}
+
+fn default_static_frame_skip() -> bool {
+    false
+}
+
+fn default_supersample_factor() -> f32 {
+    2.0
+}
+
+fn default_msaa_samples() -> u32 {
+    1
+}
+
+fn default_hdr_shared_texture() -> bool {
+    false
+}
+
+fn default_gl_vk_semaphore_sync() -> bool {
+    true
+}
+
+fn default_auto_detect_stereo_mode() -> bool {
+    true
+}
+
+fn default_reset_origin_on_file_load() -> bool {
+    false
+}
+
+fn default_companion_mirror_vr() -> bool {
+    false
+}
+
+fn default_volume_fade_in_secs() -> f32 {
+    0.0
+}
+
+fn default_volume() -> f64 {
+    100.0
+}
+
+fn default_fullscreen_on_play() -> bool {
+    false
+}
+
+pub fn default_background_color() -> [f32; 3] {
+    [0.0, 0.0, 0.0]
+}
+
+fn default_present_mode() -> PresentMode {
+    PresentMode::Mailbox
+}
+
+fn default_companion_fov_degrees() -> f32 {
+    90.0
+}
+
+fn default_near_plane() -> f32 {
+    0.1
+}
+
+fn default_far_plane() -> f32 {
+    100.0
+}
+
+fn default_max_video_size() -> u32 {
+    8192
+}
+
+fn default_comfort_vignette() -> bool {
+    false
+}
+
+fn default_comfort_vignette_strength() -> f32 {
+    0.7
+}
+
+fn default_comfort_vignette_speed() -> f32 {
+    120.0
+}
+
+fn default_comfort_fov_reduction() -> bool {
+    false
+}
+
+fn default_comfort_fov_reduction_secs() -> f32 {
+    0.5
+}
+
+fn default_idle_power_saving() -> bool {
+    false
+}
+
+fn default_idle_power_saving_secs() -> f32 {
+    30.0
+}
+
+fn default_pause_on_focus_loss() -> bool {
+    false
+}
+
+fn default_gamepad_ui_navigation() -> bool {
+    true
+}
+
+fn default_seek_small_secs() -> f64 {
+    5.0
+}
+
+fn default_seek_large_secs() -> f64 {
+    60.0
+}
+
+fn default_shared_output() -> SharedOutputSource {
+    SharedOutputSource::Disabled
+}
+
+fn default_mpv_hwdec() -> String {
+    "no".to_owned()
+}
+
+fn default_mpv_profile() -> String {
+    "sw-fast".to_owned()
+}
+
+fn default_sqlite_wal() -> bool {
+    true
+}
+
+fn default_sqlite_busy_timeout_ms() -> u64 {
+    5000
+}
+
 impl Config {
+    // sanitized (near, far), config file can be edited by hand
+    pub fn clip_planes(&self) -> (f32, f32) {
+        let near = self.near_plane.max(0.001);
+        (near, self.far_plane.max(near * 2.0))
+    }
+
+    pub fn push_recent_file(&mut self, path: PathBuf) {
+        self.recent_files.retain(|p| p != &path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    // render targets are sRGB, so clear color and shader outputs are in linear space
+    pub fn background_color_linear(&self) -> [f32; 3] {
+        let to_linear = |c: f32| {
+            let c = c.clamp(0.0, 1.0);
+            cond!(c <= 0.04045, c / 12.92, ((c + 0.055) / 1.055).powf(2.4))
+        };
+        let [r, g, b] = self.background_color;
+        [to_linear(r), to_linear(g), to_linear(b)]
+    }
+
     pub fn load() -> Result<Config, anyhow::Error> {
         let dirs = xdg::BaseDirectories::with_prefix("vrmp")?;
         if let Some(file) = dirs.find_config_file("config.ron") {
@@ -67,10 +460,15 @@ impl Config {
             let s = String::from_utf8(bytes)?;
             Ok(ron::from_str(&s)?)
         } else {
-            Ok(Config::default())
+            Ok(Config::defaults())
         }
     }
 
+    // empty struct gets all the serde defaults, unlike Config::default()
+    pub fn defaults() -> Config {
+        ron::from_str("()").expect("empty config must deserialize")
+    }
+
     pub fn save(&self) -> Result<(), anyhow::Error> {
         let dirs = xdg::BaseDirectories::with_prefix("vrmp")?;
         let path = dirs.place_config_file("config.ron")?;
@@ -1,3 +1,4 @@
+use sdl2::controller::Button;
 use sdl2::keyboard::Keycode;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -8,6 +9,9 @@ pub enum Trigger {
     None,
     #[serde(serialize_with = "keycode_se", deserialize_with = "keycode_de")]
     Key(Keycode),
+    // game controller button, named as in SDL game controller mappings: "a", "leftshoulder", "start", etc.
+    #[serde(serialize_with = "button_se", deserialize_with = "button_de")]
+    Button(Button),
 }
 
 fn keycode_se<S>(v: &Keycode, s: S) -> Result<S::Ok, S::Error>
@@ -28,15 +32,95 @@ where
     }
 }
 
+fn button_se<S>(v: &Button, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.serialize_str(&v.string())
+}
+
+fn button_de<'de, D>(d: D) -> Result<Button, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: &str = Deserialize::deserialize(d)?;
+    match Button::from_string(s) {
+        Some(b) => Ok(b),
+        None => Err(serde::de::Error::custom("invalid game controller button")),
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Controls {
+    // extra key bindings, checked after built-in keys, see key_action
     #[serde(default = "default_control_map")]
     control_map: Vec<(Trigger, Action)>,
+    // actions for extra mouse buttons, right click always toggles the UI, on flat files middle drag pans the picture
+    // if middle click is None, shift + middle drag does it otherwise
+    #[serde(default = "default_middle_click")]
+    pub middle_click: Action,
+    #[serde(default = "default_x1_click")]
+    pub x1_click: Action,
+    #[serde(default = "default_x2_click")]
+    pub x2_click: Action,
 }
 
 fn default_control_map() -> Vec<(Trigger, Action)> {
-    vec![(
-        Trigger::Key(Keycode::Space),
-        Action::Command(vec!["cycle".to_owned(), "pause".to_owned()]),
-    )]
+    let cmd = |args: &[&str]| Action::Command(args.iter().map(|s| s.to_string()).collect());
+    vec![
+        (Trigger::Key(Keycode::V), Action::CycleAspectRatio),
+        (Trigger::Key(Keycode::P), Action::ToggleQuickProjection),
+        (Trigger::Key(Keycode::Q), Action::SnapTurnLeft),
+        (Trigger::Key(Keycode::E), Action::SnapTurnRight),
+        (Trigger::Button(Button::A), cmd(&["cycle", "pause"])),
+        (Trigger::Button(Button::LeftShoulder), cmd(&["seek", "-5", "relative"])),
+        (Trigger::Button(Button::RightShoulder), cmd(&["seek", "5", "relative"])),
+        (Trigger::Button(Button::Start), Action::ToggleUI),
+        (Trigger::Button(Button::Back), Action::ResetWorldOrigin),
+    ]
+}
+
+fn default_middle_click() -> Action {
+    Action::ToggleMiniUI
+}
+
+// X1/X2 are usually "back" and "forward" side buttons
+fn default_x1_click() -> Action {
+    Action::PreviousFile
+}
+
+fn default_x2_click() -> Action {
+    Action::NextFile
+}
+
+impl Controls {
+    pub fn load() -> Result<Controls, anyhow::Error> {
+        let dirs = xdg::BaseDirectories::with_prefix("vrmp")?;
+        if let Some(file) = dirs.find_config_file("controls.ron") {
+            let bytes = std::fs::read(&file)?;
+            let s = String::from_utf8(bytes)?;
+            Ok(ron::from_str(&s)?)
+        } else {
+            Ok(Controls::defaults())
+        }
+    }
+
+    pub fn key_action(&self, keycode: Keycode) -> Option<&Action> {
+        self.control_map.iter().find_map(|(trigger, action)| match trigger {
+            Trigger::Key(k) if *k == keycode => Some(action),
+            _ => None,
+        })
+    }
+
+    pub fn button_action(&self, button: Button) -> Option<&Action> {
+        self.control_map.iter().find_map(|(trigger, action)| match trigger {
+            Trigger::Button(b) if *b == button => Some(action),
+            _ => None,
+        })
+    }
+
+    // empty struct gets all the serde defaults
+    pub fn defaults() -> Controls {
+        ron::from_str("()").expect("empty controls must deserialize")
+    }
 }
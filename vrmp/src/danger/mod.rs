@@ -1,5 +1,6 @@
 pub mod cmdpool;
 pub mod egl_bridge;
 pub mod opengl;
+pub mod shared_output;
 pub mod shared_texture;
 pub mod vulkan;
@@ -6,10 +6,14 @@ use std::{
 
 use super::vulkan::VulkanSharedTexture;
 
-pub struct OpenGLSharedTexture {
-    pub gl_texture: u32,
+pub struct OpenGLSharedSemaphores {
     pub gl_ready: u32,
     pub gl_complete: u32,
+}
+
+pub struct OpenGLSharedTexture {
+    pub gl_texture: u32,
+    pub semaphores: Option<OpenGLSharedSemaphores>,
     pub gl_memory: u32,
     pub gl_fbo: u32,
     pub width: u32,
@@ -19,21 +23,24 @@ pub struct OpenGLSharedTexture {
 impl OpenGLSharedTexture {
     pub unsafe fn create(vk: &VulkanSharedTexture) -> OpenGLSharedTexture {
         let mut gl_texture = 0u32;
-        let mut gl_ready = 0u32;
-        let mut gl_complete = 0u32;
         let mut gl_memory = 0u32;
         let mut gl_fbo = 0u32;
 
         // create objects
         gl::GenTextures(1, &mut gl_texture);
-        gl::GenSemaphoresEXT(1, &mut gl_ready);
-        gl::GenSemaphoresEXT(1, &mut gl_complete);
         gl::CreateMemoryObjectsEXT(1, &mut gl_memory);
         gl::GenFramebuffers(1, &mut gl_fbo);
 
         // import FDs
-        gl::ImportSemaphoreFdEXT(gl_ready, gl::HANDLE_TYPE_OPAQUE_FD_EXT, vk.gl_ready_fd);
-        gl::ImportSemaphoreFdEXT(gl_complete, gl::HANDLE_TYPE_OPAQUE_FD_EXT, vk.gl_complete_fd);
+        let semaphores = vk.semaphores.as_ref().map(|sem| {
+            let mut gl_ready = 0u32;
+            let mut gl_complete = 0u32;
+            gl::GenSemaphoresEXT(1, &mut gl_ready);
+            gl::GenSemaphoresEXT(1, &mut gl_complete);
+            gl::ImportSemaphoreFdEXT(gl_ready, gl::HANDLE_TYPE_OPAQUE_FD_EXT, sem.gl_ready_fd);
+            gl::ImportSemaphoreFdEXT(gl_complete, gl::HANDLE_TYPE_OPAQUE_FD_EXT, sem.gl_complete_fd);
+            OpenGLSharedSemaphores { gl_ready, gl_complete }
+        });
         gl::ImportMemoryFdEXT(
             gl_memory,
             vk.memory_size,
@@ -47,19 +54,27 @@ impl OpenGLSharedTexture {
         gl::TexStorageMem2DEXT(
             gl::TEXTURE_2D,
             1,
-            gl::SRGB8,
+            vk.format.gl_internal_format(),
             vk.width as i32,
             vk.height as i32,
             gl_memory,
             0,
         );
 
+        // memory layout is defined by the VK image, GL storage with a different format would reinterpret it
+        let mut internal_format = 0i32;
+        gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_INTERNAL_FORMAT, &mut internal_format);
+        assert_eq!(
+            internal_format as u32,
+            vk.format.gl_internal_format(),
+            "GL shared texture format doesn't match VK one"
+        );
+
         gl::BindFramebuffer(gl::FRAMEBUFFER, gl_fbo);
         gl::FramebufferTexture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl_texture, 0);
         OpenGLSharedTexture {
             gl_texture,
-            gl_ready,
-            gl_complete,
+            semaphores,
             gl_memory,
             gl_fbo,
             width: vk.width,
@@ -72,8 +87,10 @@ impl OpenGLSharedTexture {
             gl::DeleteFramebuffers(1, &self.gl_fbo);
             gl::DeleteTextures(1, &self.gl_texture);
             gl::DeleteMemoryObjectsEXT(1, &self.gl_memory);
-            gl::DeleteSemaphoresEXT(1, &self.gl_ready);
-            gl::DeleteSemaphoresEXT(1, &self.gl_complete);
+            if let Some(sem) = &self.semaphores {
+                gl::DeleteSemaphoresEXT(1, &sem.gl_ready);
+                gl::DeleteSemaphoresEXT(1, &sem.gl_complete);
+            }
         }
     }
 }
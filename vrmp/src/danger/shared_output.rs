@@ -0,0 +1,209 @@
+use std::{
+    io::ErrorKind,
+    mem,
+    os::unix::{
+        io::{AsRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    ptr,
+};
+
+use ash::vk;
+
+use super::vulkan::VulkanSharedOutputImage;
+
+// Old images will be destroyed after this number of frames, same as shared texture garbage.
+const DESTROY_AFTER_NUM_FRAMES: u32 = 60;
+
+const MESSAGE_MAGIC: &[u8; 4] = b"VRMP";
+const MESSAGE_VERSION: u32 = 1;
+
+// Rendered frame is exposed to other processes (e.g. an OBS plugin) as exported vulkan memory. Clients connect to
+// $XDG_RUNTIME_DIR/vrmp/output.sock and receive a 32 byte message with the memory FD attached (SCM_RIGHTS):
+//
+//   magic: [u8; 4] = "VRMP", version: u32, width: u32, height: u32, vk_format: u32 (always B8G8R8A8_SRGB),
+//   vk_tiling: u32 (always LINEAR), memory_size: u64, all little endian
+//
+// The memory is imported via VK_KHR_external_memory_fd (or GL_EXT_memory_object_fd) with a dedicated allocation, on
+// the same GPU and driver. A new message is sent every time the image is recreated (on resize). There is no
+// synchronization, consumers simply read the latest contents, it's meant for capture where an occasional torn frame
+// is fine.
+pub struct SharedOutput {
+    path: PathBuf,
+    listener: UnixListener,
+    clients: Vec<UnixStream>,
+    image: Option<VulkanSharedOutputImage>,
+    garbage: Vec<(u32, VulkanSharedOutputImage)>,
+    // size the image couldn't be created at, it's not retried every frame
+    failed_size: Option<(u32, u32)>,
+}
+
+fn socket_path() -> Result<PathBuf, anyhow::Error> {
+    let dirs = xdg::BaseDirectories::with_prefix("vrmp")?;
+    Ok(dirs.place_runtime_file("output.sock")?)
+}
+
+fn message(image: &VulkanSharedOutputImage) -> [u8; 32] {
+    let mut msg = [0u8; 32];
+    msg[0..4].copy_from_slice(MESSAGE_MAGIC);
+    msg[4..8].copy_from_slice(&MESSAGE_VERSION.to_le_bytes());
+    msg[8..12].copy_from_slice(&image.width.to_le_bytes());
+    msg[12..16].copy_from_slice(&image.height.to_le_bytes());
+    msg[16..20].copy_from_slice(&(vk::Format::B8G8R8A8_SRGB.as_raw() as u32).to_le_bytes());
+    msg[20..24].copy_from_slice(&(vk::ImageTiling::LINEAR.as_raw() as u32).to_le_bytes());
+    msg[24..32].copy_from_slice(&image.memory_size.to_le_bytes());
+    msg
+}
+
+unsafe fn send_with_fd(stream: &UnixStream, data: &[u8], fd: RawFd) -> std::io::Result<()> {
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    let cmsg_space = libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) as usize;
+    let mut cmsg_buf = vec![0u8; cmsg_space];
+    let mut msg: libc::msghdr = mem::zeroed();
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = cmsg_space as _;
+
+    let cmsg = libc::CMSG_FIRSTHDR(&msg);
+    (*cmsg).cmsg_level = libc::SOL_SOCKET;
+    (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+    (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+    ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+
+    if libc::sendmsg(stream.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl SharedOutput {
+    pub fn create() -> Option<SharedOutput> {
+        let path = match socket_path() {
+            Ok(path) => path,
+            Err(e) => {
+                log::error!("failed locating shared output socket, shared output is disabled: {}", e);
+                return None;
+            }
+        };
+        // stale socket from a crashed instance would make bind fail
+        let _ = std::fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path).and_then(|l| l.set_nonblocking(true).map(|_| l)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("failed creating shared output socket, shared output is disabled: {}", e);
+                return None;
+            }
+        };
+        log::info!("shared output is available at {}", path.display());
+        Some(SharedOutput {
+            path,
+            listener,
+            clients: Vec::new(),
+            image: None,
+            garbage: Vec::new(),
+            failed_size: None,
+        })
+    }
+
+    pub fn texture_view(&self) -> Option<&wgpu::TextureView> {
+        self.image.as_ref().map(|img| &img.texture_view)
+    }
+
+    pub fn texture(&self) -> Option<&wgpu::Texture> {
+        self.image.as_ref().map(|img| &img.texture)
+    }
+
+    pub fn resize_maybe(
+        &mut self,
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        wgpu_device: &wgpu::Device,
+        w: u32,
+        h: u32,
+    ) {
+        if self
+            .image
+            .as_ref()
+            .map_or(false, |img| img.width == w && img.height == h)
+            || w == 0
+            || h == 0
+            || self.failed_size == Some((w, h))
+        {
+            return;
+        }
+        log::info!("creating shared output image {}x{}", w, h);
+        // old image has the wrong size either way, without a new one there is no output until the next resize
+        if let Some(old_image) = self.image.take() {
+            self.garbage.push((0, old_image));
+        }
+        match unsafe { VulkanSharedOutputImage::create(instance, device, physical_device, wgpu_device, w, h) } {
+            Ok(image) => {
+                self.image = Some(image);
+                self.failed_size = None;
+            }
+            Err(e) => {
+                log::error!("failed creating shared output image: {}", e);
+                self.failed_size = Some((w, h));
+                return;
+            }
+        }
+        let clients = mem::take(&mut self.clients);
+        for client in clients {
+            self.send_image(client);
+        }
+    }
+
+    // keeps the client only if the message was sent successfully
+    fn send_image(&mut self, client: UnixStream) {
+        if let Some(img) = &self.image {
+            if let Err(e) = unsafe { send_with_fd(&client, &message(img), img.memory_fd) } {
+                log::info!("dropping shared output client: {}", e);
+                return;
+            }
+        }
+        self.clients.push(client);
+    }
+
+    pub fn update(&mut self, device: &ash::Device) {
+        loop {
+            match self.listener.accept() {
+                Ok((client, _)) => {
+                    log::info!("shared output client connected");
+                    self.send_image(client);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::error!("failed accepting shared output client: {}", e);
+                    break;
+                }
+            }
+        }
+
+        for (frame_lifetime, _) in &mut self.garbage {
+            *frame_lifetime += 1;
+        }
+        for (_, img) in self.garbage.iter().filter(|(n, _)| *n > DESTROY_AFTER_NUM_FRAMES) {
+            log::info!("destroying garbage shared output image {}x{}", img.width, img.height);
+            img.shutdown(device);
+        }
+        self.garbage.retain(|(n, _)| *n <= DESTROY_AFTER_NUM_FRAMES);
+    }
+
+    pub fn shutdown(&self, device: &ash::Device) {
+        if let Some(img) = &self.image {
+            img.shutdown(device);
+        }
+        for (_, img) in &self.garbage {
+            img.shutdown(device);
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::error!("failed removing shared output socket: {}", e);
+        }
+    }
+}
@@ -2,7 +2,11 @@ use std::{ptr, sync::Arc};
 
 use ash::vk;
 
-use super::{opengl::OpenGLSharedTexture, vulkan::VulkanSharedTexture};
+use super::{
+    cmdpool::CmdPool,
+    opengl::OpenGLSharedTexture,
+    vulkan::{SharedTextureFormat, VulkanSharedTexture},
+};
 
 // Garbage items will be destroyed after this number of frames. It's implied that they are not used during that period.
 const DESTROY_AFTER_NUM_FRAMES: u32 = 60;
@@ -19,6 +23,9 @@ pub struct SharedTexture {
 
     ready: bool,
     gl_did_draw: bool,
+    // fallback sync when semaphores are not used: GL waits on this fence before drawing and VK signals it after
+    // rendering, VK in turn relies on glFinish
+    vk_done: Option<vk::Fence>,
     // this is a list of textures to destroy, I don't properly wait on a fence to destroy it, just delay destruction by
     // a couple of frames after use
     garbage: Vec<Garbage>,
@@ -32,18 +39,40 @@ impl SharedTexture {
         physical_device: vk::PhysicalDevice,
         wgpu_device: &wgpu::Device,
         bind_group_layout: Arc<wgpu::BindGroupLayout>,
+        cmd_pool: &mut CmdPool,
+        queue: vk::Queue,
         w: u32,
         h: u32,
+        format: SharedTextureFormat,
+        with_semaphores: bool,
     ) -> SharedTexture {
         unsafe {
-            let vk =
-                VulkanSharedTexture::create(instance, device, physical_device, wgpu_device, bind_group_layout, w, h);
+            let vk = VulkanSharedTexture::create(
+                instance,
+                device,
+                physical_device,
+                wgpu_device,
+                bind_group_layout,
+                cmd_pool,
+                queue,
+                w,
+                h,
+                format,
+                with_semaphores,
+            );
             let gl = OpenGLSharedTexture::create(&vk);
+            let vk_done = (!with_semaphores).then(|| {
+                let vk_info = vk::FenceCreateInfo::builder()
+                    .flags(vk::FenceCreateFlags::SIGNALED)
+                    .build();
+                device.create_fence(&vk_info, None).unwrap()
+            });
             SharedTexture {
                 ready: false,
                 vk,
                 gl,
                 gl_did_draw: false,
+                vk_done,
                 garbage: Vec::new(),
                 resize_requested: None,
             }
@@ -66,6 +95,8 @@ impl SharedTexture {
         device: &ash::Device,
         physical_device: vk::PhysicalDevice,
         wgpu_device: &wgpu::Device,
+        cmd_pool: &mut CmdPool,
+        queue: vk::Queue,
     ) {
         unsafe {
             if let Some((w, h)) = self.resize_requested {
@@ -77,8 +108,12 @@ impl SharedTexture {
                     physical_device,
                     wgpu_device,
                     self.vk.bind_group_layout.clone(),
+                    cmd_pool,
+                    queue,
                     w,
                     h,
+                    self.vk.format,
+                    self.vk.semaphores.is_some(),
                 );
                 let new_gl = OpenGLSharedTexture::create(&new_vk);
                 let old_vk = std::mem::replace(&mut self.vk, new_vk);
@@ -99,25 +134,32 @@ impl SharedTexture {
     }
 
     pub fn before_vk(&mut self, device: &ash::Device, queue: vk::Queue) {
-        // wait on semaphore only if GL did draw something
+        // wait on semaphore only if GL did draw something, without semaphores glFinish already did the waiting
         if self.gl_did_draw {
             self.gl_did_draw = false;
 
-            unsafe {
-                let vk_info = vk::SubmitInfo::builder()
-                    .wait_dst_stage_mask(&[vk::PipelineStageFlags::FRAGMENT_SHADER])
-                    .wait_semaphores(&[self.vk.gl_complete])
-                    .build();
-                device.queue_submit(queue, &[vk_info], vk::Fence::null()).unwrap();
+            if let Some(sem) = &self.vk.semaphores {
+                unsafe {
+                    let vk_info = vk::SubmitInfo::builder()
+                        .wait_dst_stage_mask(&[vk::PipelineStageFlags::FRAGMENT_SHADER])
+                        .wait_semaphores(&[sem.gl_complete])
+                        .build();
+                    device.queue_submit(queue, &[vk_info], vk::Fence::null()).unwrap();
+                }
             }
         }
     }
 
     pub fn after_vk(&mut self, device: &ash::Device, queue: vk::Queue) {
-        // vk always signals a semaphore
+        // vk always signals a semaphore (or a fence)
         unsafe {
-            let vk_info = vk::SubmitInfo::builder().signal_semaphores(&[self.vk.gl_ready]).build();
-            device.queue_submit(queue, &[vk_info], vk::Fence::null()).unwrap();
+            if let Some(sem) = &self.vk.semaphores {
+                let vk_info = vk::SubmitInfo::builder().signal_semaphores(&[sem.gl_ready]).build();
+                device.queue_submit(queue, &[vk_info], vk::Fence::null()).unwrap();
+            } else if let Some(fence) = self.vk_done {
+                device.reset_fences(&[fence]).unwrap();
+                device.queue_submit(queue, &[], fence).unwrap();
+            }
         }
 
         // destroy garbage if any
@@ -140,26 +182,47 @@ impl SharedTexture {
     pub fn shutdown(&self, device: &ash::Device) {
         self.vk.shutdown(device);
         self.gl.shutdown();
+        for item in &self.garbage {
+            item.vk.shutdown(device);
+            item.gl.shutdown();
+        }
+        if let Some(fence) = self.vk_done {
+            unsafe {
+                device.destroy_fence(fence, None);
+            }
+        }
     }
 
-    pub fn draw_gl<F: FnOnce() -> bool>(&mut self, f: F) {
+    // returns whether f drew a new frame
+    pub fn draw_gl<F: FnOnce() -> bool>(&mut self, device: &ash::Device, f: F) -> bool {
+        let did_draw;
         unsafe {
+            // the image is created in shader read only layout (see initialize_shared_image) and always handed back in it
             let x = gl::LAYOUT_SHADER_READ_ONLY_EXT;
             let y = gl::LAYOUT_SHADER_READ_ONLY_EXT;
-            gl::WaitSemaphoreEXT(self.gl.gl_ready, 0, ptr::null(), 1, &self.gl.gl_texture, &x);
+            if let Some(sem) = &self.gl.semaphores {
+                gl::WaitSemaphoreEXT(sem.gl_ready, 0, ptr::null(), 1, &self.gl.gl_texture, &x);
+            } else if let Some(fence) = self.vk_done {
+                device.wait_for_fences(&[fence], true, u64::MAX).unwrap();
+            }
 
             gl::Viewport(0, 0, self.gl.width as i32, self.gl.height as i32);
             gl::BindFramebuffer(gl::FRAMEBUFFER, self.gl.gl_fbo);
 
-            let did_draw = f();
+            did_draw = f();
             if did_draw {
                 self.ready = true;
             }
 
-            gl::SignalSemaphoreEXT(self.gl.gl_complete, 0, ptr::null(), 1, &self.gl.gl_texture, &y);
-
-            gl::Flush();
+            if let Some(sem) = &self.gl.semaphores {
+                gl::SignalSemaphoreEXT(sem.gl_complete, 0, ptr::null(), 1, &self.gl.gl_texture, &y);
+                gl::Flush();
+            } else {
+                // slow, but doesn't depend on semaphore interop which is broken on some drivers
+                gl::Finish();
+            }
         }
         self.gl_did_draw = true;
+        did_draw
     }
 }
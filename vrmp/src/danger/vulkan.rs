@@ -5,50 +5,95 @@ use ash::vk;
 use itertools::Itertools;
 use libopenvr::Context;
 use std::{ffi::CStr, sync::Arc};
-use wgpu_hal::{api::Vulkan, Api, InstanceFlags};
+use wgpu_hal::{api::Vulkan, Adapter as _, Api, Instance as _, InstanceFlags};
 
-unsafe fn is_good_device(instance: ash::Instance, pdevice: vk::PhysicalDevice) -> bool {
+unsafe fn is_discrete_gpu(instance: &ash::Instance, pdevice: vk::PhysicalDevice) -> bool {
     let props = instance.get_physical_device_properties(pdevice);
     props.device_type == vk::PhysicalDeviceType::DISCRETE_GPU
 }
 
+unsafe fn device_name(instance: &ash::Instance, pdevice: vk::PhysicalDevice) -> String {
+    let props = instance.get_physical_device_properties(pdevice);
+    CStr::from_ptr(props.device_name.as_ptr()).to_string_lossy().to_string()
+}
+
+// Device preference: forced by index > VR output device > first discrete GPU > whatever is available.
+unsafe fn select_physical_device(
+    instance: &ash::Instance,
+    vr_pdevice: Option<vk::PhysicalDevice>,
+    forced_index: Option<usize>,
+) -> vk::PhysicalDevice {
+    let devices = instance.enumerate_physical_devices().unwrap();
+    for (i, &d) in devices.iter().enumerate() {
+        log::info!("vulkan device {}: {}", i, device_name(instance, d));
+    }
+
+    if let Some(i) = forced_index {
+        match devices.get(i) {
+            Some(&d) => {
+                log::info!(
+                    "using vulkan device {} ({}): forced via --gpu",
+                    i,
+                    device_name(instance, d)
+                );
+                if vr_pdevice.map_or(false, |v| v != d) {
+                    log::warn!("forced vulkan device is not the one openvr outputs to");
+                }
+                return d;
+            }
+            None => log::error!(
+                "--gpu {} is out of range ({} devices available), ignoring",
+                i,
+                devices.len()
+            ),
+        }
+    }
+
+    if let Some((i, &d)) = devices.iter().find_position(|&&d| Some(d) == vr_pdevice) {
+        log::info!(
+            "using vulkan device {} ({}): VR output device",
+            i,
+            device_name(instance, d)
+        );
+        return d;
+    }
+
+    if let Some((i, &d)) = devices.iter().find_position(|&&d| is_discrete_gpu(instance, d)) {
+        log::info!(
+            "using vulkan device {} ({}): first discrete GPU",
+            i,
+            device_name(instance, d)
+        );
+        return d;
+    }
+
+    let &d = devices.first().expect("failed to find any vulkan physical device");
+    log::info!(
+        "using vulkan device 0 ({}): no discrete GPU found",
+        device_name(instance, d)
+    );
+    d
+}
+
 pub struct LoadVulkanWGPUParams<'a, W: raw_window_handle::HasRawWindowHandle> {
     pub vr_ctx: Option<&'a libopenvr::Context>,
     pub window: &'a W,
     pub features: wgpu::Features,
     pub limits: wgpu::Limits,
     pub flags: InstanceFlags,
+    // forces specific physical device by its index in the enumeration order
+    pub gpu_index: Option<usize>,
 }
 
-pub struct VulkanSharedTexture {
+pub struct VulkanSharedSemaphores {
     pub gl_complete: vk::Semaphore,
     pub gl_ready: vk::Semaphore,
-    pub memory: vk::DeviceMemory,
     pub gl_complete_fd: i32,
     pub gl_ready_fd: i32,
-    pub gl_memory_fd: i32,
-
-    pub memory_size: u64,
-    pub width: u32,
-    pub height: u32,
-
-    pub image: vk::Image,
-    pub texture: wgpu::Texture,
-    pub texture_view: wgpu::TextureView,
-    pub bind_group_layout: Arc<wgpu::BindGroupLayout>,
-    pub bind_group: wgpu::BindGroup,
 }
 
-impl VulkanSharedTexture {
-    pub unsafe fn create(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        physical_device: vk::PhysicalDevice,
-        wgpu_device: &wgpu::Device,
-        bind_group_layout: Arc<wgpu::BindGroupLayout>,
-        w: u32,
-        h: u32,
-    ) -> VulkanSharedTexture {
+impl VulkanSharedSemaphores {
+    unsafe fn create(instance: &ash::Instance, device: &ash::Device) -> VulkanSharedSemaphores {
         let mut vk_info = vk::ExportSemaphoreCreateInfo::builder()
             .handle_types(vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD)
             .build();
@@ -76,25 +121,117 @@ impl VulkanSharedTexture {
             )
             .unwrap();
 
+        VulkanSharedSemaphores {
+            gl_complete,
+            gl_ready,
+            gl_complete_fd: gl_complete_handle,
+            gl_ready_fd: gl_ready_handle,
+        }
+    }
+}
+
+// Format of the image mpv renders into, VK and GL sides must agree on it, since they share the memory. Both are sampled
+// as linear values:
+//  - sRGB: mpv writes sRGB encoded values (target-trc=srgb, GL_FRAMEBUFFER_SRGB is off, so GL stores them as is), the
+//    VK sampler decodes them
+//  - float: mpv writes linear light (target-trc=linear), the VK sampler returns them as is, extra precision avoids
+//    banding, values above 1.0 are clipped anyway since eye and swapchain targets are 8-bit sRGB (no tone mapping)
+// See Global::init and Global::gl_render.
+#[derive(Copy, Clone, PartialEq)]
+pub enum SharedTextureFormat {
+    Srgb8,
+    Rgba16Float,
+}
+
+impl SharedTextureFormat {
+    pub fn vk_format(self) -> vk::Format {
+        match self {
+            SharedTextureFormat::Srgb8 => vk::Format::R8G8B8A8_SRGB,
+            SharedTextureFormat::Rgba16Float => vk::Format::R16G16B16A16_SFLOAT,
+        }
+    }
+
+    pub fn wgpu_format(self) -> wgpu::TextureFormat {
+        match self {
+            SharedTextureFormat::Srgb8 => wgpu::TextureFormat::Rgba8UnormSrgb,
+            SharedTextureFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+
+    // texture storage format on GL side, also passed to mpv as FBO internal format
+    pub fn gl_internal_format(self) -> u32 {
+        match self {
+            SharedTextureFormat::Srgb8 => gl::SRGB8_ALPHA8,
+            SharedTextureFormat::Rgba16Float => gl::RGBA16F,
+        }
+    }
+
+    // mpv "target-trc" matching the way the texture is sampled
+    pub fn mpv_target_trc(self) -> &'static str {
+        match self {
+            SharedTextureFormat::Srgb8 => "srgb",
+            SharedTextureFormat::Rgba16Float => "linear",
+        }
+    }
+}
+
+pub struct VulkanSharedTexture {
+    // None when GL<->VK sync is done without external semaphores, only memory is shared then
+    pub semaphores: Option<VulkanSharedSemaphores>,
+    pub memory: vk::DeviceMemory,
+    pub gl_memory_fd: i32,
+
+    pub memory_size: u64,
+    pub width: u32,
+    pub height: u32,
+    pub format: SharedTextureFormat,
+
+    pub image: vk::Image,
+    pub texture: wgpu::Texture,
+    pub texture_view: wgpu::TextureView,
+    pub bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl VulkanSharedTexture {
+    pub unsafe fn create(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        wgpu_device: &wgpu::Device,
+        bind_group_layout: Arc<wgpu::BindGroupLayout>,
+        cmd_pool: &mut CmdPool,
+        queue: vk::Queue,
+        w: u32,
+        h: u32,
+        format: SharedTextureFormat,
+        with_semaphores: bool,
+    ) -> VulkanSharedTexture {
+        let semaphores = with_semaphores.then(|| VulkanSharedSemaphores::create(instance, device));
+
         let mut ext_vk_info = vk::ExternalMemoryImageCreateInfo::builder()
             .handle_types(vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD)
             .build();
 
         let vk_info = vk::ImageCreateInfo::builder()
             .image_type(vk::ImageType::TYPE_2D)
-            .format(vk::Format::R8G8B8A8_SRGB)
+            .format(format.vk_format())
             .mip_levels(1)
             .array_layers(1)
             .samples(vk::SampleCountFlags::TYPE_1)
             .extent(vk::Extent3D::builder().depth(1).width(w).height(h).build())
-            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+            .usage(
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::SAMPLED
+                    | vk::ImageUsageFlags::TRANSFER_DST,
+            )
             .tiling(vk::ImageTiling::LINEAR)
             .push_next(&mut ext_vk_info)
             .build();
 
         let image = device.create_image(&vk_info, None).unwrap();
         let mem_reqs = device.get_image_memory_requirements(image);
-        let mem_type = get_memory_type(
+        let mem_type_index = get_memory_type(
             instance,
             physical_device,
             mem_reqs.memory_type_bits,
@@ -109,7 +246,7 @@ impl VulkanSharedTexture {
 
         let vk_info = vk::MemoryAllocateInfo::builder()
             .allocation_size(mem_reqs.size)
-            .memory_type_index(mem_type.heap_index)
+            .memory_type_index(mem_type_index)
             .push_next(&mut ext_vk_info)
             .push_next(&mut ext_vk_info2)
             .build();
@@ -118,6 +255,7 @@ impl VulkanSharedTexture {
         // correctly my AMD device worked without it. But let's keep it that way.
         let memory = device.allocate_memory(&vk_info, None).unwrap();
         device.bind_image_memory(image, memory, 0).unwrap();
+        initialize_shared_image(device, cmd_pool, queue, image);
 
         let ext_memory = ash::extensions::khr::ExternalMemoryFd::new(instance, &device);
         let gl_memory_handle = ext_memory
@@ -141,7 +279,7 @@ impl VulkanSharedTexture {
                     depth_or_array_layers: 1,
                 },
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format: format.wgpu_format(),
                 mip_level_count: 1,
                 sample_count: 1,
                 memory_flags: wgpu_hal::MemoryFlags::empty(),
@@ -159,7 +297,7 @@ impl VulkanSharedTexture {
                     depth_or_array_layers: 1,
                 },
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format: format.wgpu_format(),
                 mip_level_count: 1,
                 sample_count: 1,
                 usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -176,10 +314,7 @@ impl VulkanSharedTexture {
         });
 
         VulkanSharedTexture {
-            gl_complete,
-            gl_ready,
-            gl_complete_fd: gl_complete_handle,
-            gl_ready_fd: gl_ready_handle,
+            semaphores,
             image,
             memory,
             memory_size: mem_reqs.size,
@@ -188,6 +323,7 @@ impl VulkanSharedTexture {
             texture_view,
             width: w,
             height: h,
+            format,
             bind_group,
             bind_group_layout,
         }
@@ -197,12 +333,241 @@ impl VulkanSharedTexture {
         unsafe {
             device.destroy_image(self.image, None);
             device.free_memory(self.memory, None);
-            device.destroy_semaphore(self.gl_complete, None);
-            device.destroy_semaphore(self.gl_ready, None);
+            if let Some(sem) = &self.semaphores {
+                device.destroy_semaphore(sem.gl_complete, None);
+                device.destroy_semaphore(sem.gl_ready, None);
+            }
         }
     }
 }
 
+// Image with exported memory for other processes to consume, see shared_output.rs. Same as VulkanSharedTexture, but in
+// the format of eye textures, so that eye textures can be copied into it and the scene can be rendered into it directly.
+pub struct VulkanSharedOutputImage {
+    pub memory: vk::DeviceMemory,
+    pub memory_fd: i32,
+
+    pub memory_size: u64,
+    pub width: u32,
+    pub height: u32,
+
+    pub image: vk::Image,
+    pub texture: wgpu::Texture,
+    pub texture_view: wgpu::TextureView,
+}
+
+impl VulkanSharedOutputImage {
+    pub unsafe fn create(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        wgpu_device: &wgpu::Device,
+        w: u32,
+        h: u32,
+    ) -> Result<VulkanSharedOutputImage, String> {
+        let usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | vk::ImageUsageFlags::SAMPLED
+            | vk::ImageUsageFlags::TRANSFER_DST
+            | vk::ImageUsageFlags::TRANSFER_SRC;
+        // format and tiling are part of the protocol (see shared_output.rs), so there is no fallback, linear images
+        // are optional for color attachments and some drivers don't support them at all
+        let props = instance
+            .get_physical_device_image_format_properties(
+                physical_device,
+                vk::Format::B8G8R8A8_SRGB,
+                vk::ImageType::TYPE_2D,
+                vk::ImageTiling::LINEAR,
+                usage,
+                vk::ImageCreateFlags::empty(),
+            )
+            .map_err(|e| format!("linear B8G8R8A8_SRGB images are not supported: {}", e))?;
+        if w > props.max_extent.width || h > props.max_extent.height {
+            return Err(format!(
+                "{}x{} exceeds max linear image size {}x{}",
+                w, h, props.max_extent.width, props.max_extent.height
+            ));
+        }
+
+        let mut ext_vk_info = vk::ExternalMemoryImageCreateInfo::builder()
+            .handle_types(vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD)
+            .build();
+
+        let vk_info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(vk::Format::B8G8R8A8_SRGB)
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .extent(vk::Extent3D::builder().depth(1).width(w).height(h).build())
+            .usage(usage)
+            .tiling(vk::ImageTiling::LINEAR)
+            .push_next(&mut ext_vk_info)
+            .build();
+
+        let image = device
+            .create_image(&vk_info, None)
+            .map_err(|e| format!("failed creating image: {}", e))?;
+        let mem_reqs = device.get_image_memory_requirements(image);
+        let mem_type_index = match get_memory_type(
+            instance,
+            physical_device,
+            mem_reqs.memory_type_bits,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        ) {
+            Some(index) => index,
+            None => {
+                device.destroy_image(image, None);
+                return Err("no device local memory type for the image".to_owned());
+            }
+        };
+
+        let mut ext_vk_info = vk::ExportMemoryAllocateInfo::builder()
+            .handle_types(vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD)
+            .build();
+        let mut ext_vk_info2 = vk::MemoryDedicatedAllocateInfo::builder().image(image).build();
+
+        let vk_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(mem_reqs.size)
+            .memory_type_index(mem_type_index)
+            .push_next(&mut ext_vk_info)
+            .push_next(&mut ext_vk_info2)
+            .build();
+
+        let memory = device.allocate_memory(&vk_info, None).unwrap();
+        device.bind_image_memory(image, memory, 0).unwrap();
+
+        let ext_memory = ash::extensions::khr::ExternalMemoryFd::new(instance, &device);
+        let memory_fd = ext_memory
+            .get_memory_fd(
+                &vk::MemoryGetFdInfoKHR::builder()
+                    .memory(memory)
+                    .handle_type(vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD)
+                    .build(),
+            )
+            .unwrap();
+
+        let hal_texture = <Vulkan as Api>::Device::texture_from_raw(
+            image,
+            &wgpu_hal::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                mip_level_count: 1,
+                sample_count: 1,
+                memory_flags: wgpu_hal::MemoryFlags::empty(),
+                usage: wgpu_hal::TextureUses::COLOR_TARGET | wgpu_hal::TextureUses::COPY_DST,
+            },
+            Some(Box::new(())),
+        );
+        let texture = wgpu_device.create_texture_from_hal::<Vulkan>(
+            hal_texture,
+            &wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+            },
+        );
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Ok(VulkanSharedOutputImage {
+            image,
+            memory,
+            memory_size: mem_reqs.size,
+            memory_fd,
+            texture,
+            texture_view,
+            width: w,
+            height: h,
+        })
+    }
+
+    pub fn shutdown(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_image(self.image, None);
+            device.free_memory(self.memory, None);
+            libc::close(self.memory_fd);
+        }
+    }
+}
+
+// Clears freshly created shared image to black and leaves it in SHADER_READ_ONLY_OPTIMAL layout. That's the layout GL
+// expects when it acquires the image (see draw_gl), without it first frame contents are undefined.
+unsafe fn initialize_shared_image(device: &ash::Device, cmd_pool: &mut CmdPool, queue: vk::Queue, image: vk::Image) {
+    let cmd_buf = cmd_pool.get_buf();
+
+    let vk_info = vk::CommandBufferBeginInfo::builder()
+        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+        .build();
+
+    device.begin_command_buffer(cmd_buf, &vk_info).unwrap();
+
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1)
+        .build();
+    let undefined_to_transfer_barrier = vk::ImageMemoryBarrier::builder()
+        .image(image)
+        .subresource_range(subresource_range)
+        .src_access_mask(vk::AccessFlags::empty())
+        .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .old_layout(vk::ImageLayout::UNDEFINED)
+        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .build();
+    device.cmd_pipeline_barrier(
+        cmd_buf,
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::DependencyFlags::empty(),
+        &[],
+        &[],
+        &[undefined_to_transfer_barrier],
+    );
+    device.cmd_clear_color_image(
+        cmd_buf,
+        image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &vk::ClearColorValue {
+            float32: [0.0, 0.0, 0.0, 1.0],
+        },
+        &[subresource_range],
+    );
+    let transfer_to_shader_read_barrier = vk::ImageMemoryBarrier::builder()
+        .image(image)
+        .subresource_range(subresource_range)
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ)
+        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .build();
+    device.cmd_pipeline_barrier(
+        cmd_buf,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::FRAGMENT_SHADER,
+        vk::DependencyFlags::empty(),
+        &[],
+        &[],
+        &[transfer_to_shader_read_barrier],
+    );
+    device.end_command_buffer(cmd_buf).unwrap();
+
+    let vk_info = vk::SubmitInfo::builder().command_buffers(&[cmd_buf]).build();
+    device.queue_submit(queue, &[vk_info], vk::Fence::null()).unwrap();
+}
+
 pub struct VulkanWGPU {
     pub instance: wgpu::Instance,
     pub surface: wgpu::Surface,
@@ -215,6 +580,8 @@ pub struct VulkanWGPU {
     pub vk_physical_device: vk::PhysicalDevice,
     pub vk_queue: vk::Queue,
     pub vk_queue_family_index: u32,
+    // supported by the window surface, empty if unknown, wgpu 0.12 doesn't expose them
+    pub present_modes: Vec<wgpu::PresentMode>,
 
     pub cmd_pool: CmdPool,
 }
@@ -244,17 +611,21 @@ pub enum ImageTransitionDir {
     BToA,
 }
 
+// returns memory type index, which is what vkAllocateMemory wants (not the heap index)
 unsafe fn get_memory_type(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
     mut bits: u32,
     property_flags: vk::MemoryPropertyFlags,
-) -> Option<vk::MemoryType> {
+) -> Option<u32> {
     let mprops = instance.get_physical_device_memory_properties(physical_device);
-    for mt in mprops.memory_types {
+    for (i, mt) in mprops.memory_types[..mprops.memory_type_count as usize]
+        .iter()
+        .enumerate()
+    {
         if bits & 1 == 1 {
             if mt.property_flags.contains(property_flags) {
-                return Some(mt);
+                return Some(i as u32);
             }
         }
         bits >>= 1;
@@ -269,17 +640,25 @@ impl VulkanWGPU {
         }
     }
 
-    pub unsafe fn submit_eye_textures(&mut self, vr_ctx: &Context, left_eye: &EyeData, right_eye: &EyeData) {
-        self.transition_image(
-            &image_transition_spec_vr(),
-            left_eye.raw_handle,
-            ImageTransitionDir::AToB,
-        );
-        self.transition_image(
-            &image_transition_spec_vr(),
-            right_eye.raw_handle,
-            ImageTransitionDir::AToB,
-        );
+    // eyes which are None are not submitted
+    pub unsafe fn submit_eye_textures(
+        &mut self,
+        vr_ctx: &Context,
+        left_eye: Option<&EyeData>,
+        right_eye: Option<&EyeData>,
+    ) {
+        let eyes: Vec<(libopenvr::Eye, &EyeData)> =
+            [(libopenvr::Eye::Left, left_eye), (libopenvr::Eye::Right, right_eye)]
+                .into_iter()
+                .filter_map(|(eye, data)| data.map(|d| (eye, d)))
+                .collect();
+        for (_, eye_data) in &eyes {
+            self.transition_image(
+                &image_transition_spec_vr(),
+                eye_data.raw_handle,
+                ImageTransitionDir::AToB,
+            );
+        }
         {
             let texture_bounds = libopenvr::TextureBounds {
                 u_min: 0.0,
@@ -287,46 +666,32 @@ impl VulkanWGPU {
                 u_max: 1.0,
                 v_max: 1.0,
             };
-            let shared_texture_data = libopenvr::VulkanTextureData {
-                device: self.ash_device.handle(),
-                instance: self.ash_instance.handle(),
-                format: vk::Format::B8G8R8A8_SRGB,
-                width: left_eye.width,
-                height: left_eye.height,
-                physical_device: self.vk_physical_device,
-                queue: self.vk_queue,
-                queue_family_index: self.vk_queue_family_index,
-                sample_count: vk::SampleCountFlags::TYPE_1,
-                image: vk::Image::null(),
-            };
-
-            vr_ctx.compositor.submit_vulkan(
-                libopenvr::Eye::Left,
-                &libopenvr::VulkanTextureData {
-                    image: left_eye.raw_handle,
-                    ..shared_texture_data
-                },
-                &texture_bounds,
-            );
-            vr_ctx.compositor.submit_vulkan(
-                libopenvr::Eye::Right,
-                &libopenvr::VulkanTextureData {
-                    image: right_eye.raw_handle,
-                    ..shared_texture_data
-                },
-                &texture_bounds,
+            for (eye, eye_data) in &eyes {
+                vr_ctx.compositor.submit_vulkan(
+                    *eye,
+                    &libopenvr::VulkanTextureData {
+                        device: self.ash_device.handle(),
+                        instance: self.ash_instance.handle(),
+                        format: vk::Format::B8G8R8A8_SRGB,
+                        width: eye_data.width,
+                        height: eye_data.height,
+                        physical_device: self.vk_physical_device,
+                        queue: self.vk_queue,
+                        queue_family_index: self.vk_queue_family_index,
+                        sample_count: vk::SampleCountFlags::TYPE_1,
+                        image: eye_data.raw_handle,
+                    },
+                    &texture_bounds,
+                );
+            }
+        }
+        for (_, eye_data) in &eyes {
+            self.transition_image(
+                &image_transition_spec_vr(),
+                eye_data.raw_handle,
+                ImageTransitionDir::BToA,
             );
         }
-        self.transition_image(
-            &image_transition_spec_vr(),
-            left_eye.raw_handle,
-            ImageTransitionDir::BToA,
-        );
-        self.transition_image(
-            &image_transition_spec_vr(),
-            right_eye.raw_handle,
-            ImageTransitionDir::BToA,
-        );
     }
 
     pub unsafe fn transition_image(&mut self, spec: &ImageTransitionSpec, image: vk::Image, dir: ImageTransitionDir) {
@@ -484,13 +849,7 @@ impl VulkanWGPU {
             .vr_ctx
             .map(|v| v.system.get_output_device_for_vulkan(ash_instance.handle()));
 
-        let vk_physical_device = ash_instance
-            .enumerate_physical_devices()
-            .unwrap()
-            .iter()
-            .cloned()
-            .find(|&device| Some(device) == vr_pdevice || is_good_device(ash_instance.clone(), device))
-            .expect("failed to find physical device required by openvr");
+        let vk_physical_device = select_physical_device(&ash_instance, vr_pdevice, p.gpu_index);
 
         let vk_queue_family_index = ash_instance
             .get_physical_device_queue_family_properties(vk_physical_device)
@@ -516,6 +875,23 @@ impl VulkanWGPU {
             .expose_adapter(vk_physical_device)
             .expect("failed exposing wgpu-hal adapater");
 
+        // queried on a temporary surface of the same window, wgpu surface is created later from the wrapped instance
+        let present_modes = match hal_instance.create_surface(p.window) {
+            Ok(surface) => {
+                let modes = hal_adapter
+                    .adapter
+                    .surface_capabilities(&surface)
+                    .map(|caps| caps.present_modes)
+                    .unwrap_or_default();
+                hal_instance.destroy_surface(surface);
+                modes
+            }
+            Err(e) => {
+                log::error!("failed creating surface for present mode query: {}", e);
+                Vec::new()
+            }
+        };
+
         let (hal_device, vk_queue, ash_device) = {
             let uab_types = wgpu_hal::UpdateAfterBindTypes::from_limits(&p.limits, &plimits);
             let mut device_extensions = hal_adapter.adapter.required_device_extensions(p.features);
@@ -614,6 +990,7 @@ impl VulkanWGPU {
             ash_instance,
             vk_queue,
             vk_queue_family_index,
+            present_modes,
             cmd_pool,
         }
     }
@@ -633,13 +1010,16 @@ pub struct EyeData {
     pub depth_texture: wgpu::Texture,
     pub texture_view: wgpu::TextureView,
     pub depth_texture_view: wgpu::TextureView,
+    // with MSAA enabled scene is rendered here and resolved into "texture", which is then submitted to openvr
+    pub msaa_texture_view: Option<wgpu::TextureView>,
     pub raw_handle: vk::Image,
     pub width: u32,
     pub height: u32,
+    pub sample_count: u32,
 }
 
 impl EyeData {
-    pub fn create(device: &wgpu::Device, w: u32, h: u32) -> EyeData {
+    pub fn create(device: &wgpu::Device, w: u32, h: u32, sample_count: u32) -> EyeData {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
                 width: w,
@@ -656,6 +1036,23 @@ impl EyeData {
             label: None,
         });
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let msaa_texture_view = (sample_count > 1).then(|| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    size: wgpu::Extent3d {
+                        width: w,
+                        height: h,
+                        ..Default::default()
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    label: None,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
         let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
                 width: w,
@@ -663,7 +1060,7 @@ impl EyeData {
                 ..Default::default()
             },
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Depth32Float,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -683,9 +1080,47 @@ impl EyeData {
             depth_texture,
             texture_view,
             depth_texture_view,
+            msaa_texture_view,
             raw_handle,
             width: w,
             height: h,
+            sample_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_texture_formats_agree() {
+        // VK image, wgpu texture and GL texture are views of the same memory, see VulkanSharedTexture::create and
+        // opengl.rs, formats must have the same layout and encoding
+        let cases = [
+            (
+                SharedTextureFormat::Srgb8,
+                vk::Format::R8G8B8A8_SRGB,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                gl::SRGB8_ALPHA8,
+                "srgb",
+            ),
+            (
+                SharedTextureFormat::Rgba16Float,
+                vk::Format::R16G16B16A16_SFLOAT,
+                wgpu::TextureFormat::Rgba16Float,
+                gl::RGBA16F,
+                "linear",
+            ),
+        ];
+        for (f, vk_format, wgpu_format, gl_format, trc) in cases {
+            assert_eq!(f.vk_format(), vk_format);
+            assert_eq!(f.wgpu_format(), wgpu_format);
+            assert_eq!(f.gl_internal_format(), gl_format);
+            assert_eq!(f.mpv_target_trc(), trc);
         }
+        // GL enum values, so that a wrong constant in the gl bindings doesn't go unnoticed
+        assert_eq!(gl::SRGB8_ALPHA8, 0x8C43);
+        assert_eq!(gl::RGBA16F, 0x881A);
     }
 }
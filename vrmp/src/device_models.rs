@@ -0,0 +1,153 @@
+use std::{collections::HashMap, sync::Arc};
+
+use glam::Mat4;
+use libopenvr::{RenderModel, RenderModelTexture, TrackedDeviceClass, TrackedPose, MAX_TRACKED_DEVICE_COUNT};
+
+use crate::pipeline::render_model::{GpuRenderModel, RenderModelPipeline};
+
+enum ModelState {
+    LoadingModel,
+    LoadingTexture(RenderModel),
+    Ready(GpuRenderModel),
+    // logged once, never retried
+    Failed,
+}
+
+// Controller models are loaded asynchronously by openvr, loading is advanced every frame and models are drawn only
+// once both mesh and texture are uploaded. Models are cached by render model name, the same model is shared by all
+// devices using it.
+pub struct DeviceModels {
+    texture_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    // render model name per tracked device index, looked up when a controller connects
+    names: Vec<Option<String>>,
+    poses: Vec<Option<TrackedPose>>,
+    models: HashMap<String, ModelState>,
+}
+
+fn white_texture() -> RenderModelTexture {
+    RenderModelTexture {
+        width: 1,
+        height: 1,
+        data: vec![255, 255, 255, 255],
+    }
+}
+
+// advances loading as far as possible without waiting, returns while openvr is still loading
+fn advance(
+    name: &str,
+    state: &mut ModelState,
+    vr: &libopenvr::Context,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+) {
+    loop {
+        let next = match state {
+            ModelState::LoadingModel => match vr.render_models.load_render_model(name) {
+                Ok(Some(model)) => ModelState::LoadingTexture(model),
+                Ok(None) => return,
+                Err(e) => {
+                    log::error!("failed loading render model {}: {}", name, e);
+                    ModelState::Failed
+                }
+            },
+            ModelState::LoadingTexture(model) => {
+                let texture = if model.diffuse_texture_id < 0 {
+                    white_texture()
+                } else {
+                    match vr.render_models.load_texture(model.diffuse_texture_id) {
+                        Ok(Some(texture)) => texture,
+                        Ok(None) => return,
+                        Err(e) => {
+                            log::error!("failed loading render model {} texture: {}", name, e);
+                            white_texture()
+                        }
+                    }
+                };
+                log::info!("loaded render model {}", name);
+                ModelState::Ready(GpuRenderModel::create(
+                    device,
+                    queue,
+                    texture_bind_group_layout,
+                    model,
+                    &texture,
+                ))
+            }
+            ModelState::Ready(_) | ModelState::Failed => return,
+        };
+        *state = next;
+    }
+}
+
+impl DeviceModels {
+    pub fn new(texture_bind_group_layout: Arc<wgpu::BindGroupLayout>) -> DeviceModels {
+        DeviceModels {
+            texture_bind_group_layout,
+            names: vec![None; MAX_TRACKED_DEVICE_COUNT],
+            poses: Vec::new(),
+            models: HashMap::new(),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        vr: &libopenvr::Context,
+        poses: Vec<Option<TrackedPose>>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        for (index, pose) in poses.iter().enumerate() {
+            let pose = match pose {
+                Some(pose) => pose,
+                None => {
+                    self.names[index] = None;
+                    continue;
+                }
+            };
+            if self.names[index].is_none() && pose.device_class == TrackedDeviceClass::Controller {
+                self.names[index] = vr.system.get_render_model_name(index);
+            }
+            if let Some(name) = &self.names[index] {
+                let state = self.models.entry(name.clone()).or_insert(ModelState::LoadingModel);
+                advance(name, state, vr, device, queue, &self.texture_bind_group_layout);
+            }
+        }
+        self.poses = poses;
+    }
+
+    // poses of the models draw() would render, used to tell whether anything moved between frames
+    pub fn drawn_poses(&self) -> Vec<Mat4> {
+        self.names
+            .iter()
+            .zip(&self.poses)
+            .filter_map(|(name, pose)| match (name, pose) {
+                (Some(name), Some(pose))
+                    if pose.is_valid && matches!(self.models.get(name), Some(ModelState::Ready(_))) =>
+                {
+                    Some(pose.device_to_absolute)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    // swap_z converts openvr tracking space into world space, world origin is not applied as controllers are
+    // physical objects
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, pipeline: &'a RenderModelPipeline, swap_z: Mat4) {
+        rpass.set_pipeline(&pipeline.pipeline);
+        for (name, pose) in self.names.iter().zip(&self.poses) {
+            let pose = match pose {
+                Some(pose) if pose.is_valid => pose,
+                _ => continue,
+            };
+            if let Some(ModelState::Ready(model)) = name.as_ref().and_then(|n| self.models.get(n)) {
+                let m = swap_z * pose.device_to_absolute;
+                rpass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytemuck::bytes_of(&m));
+                rpass.set_bind_group(1, &model.bind_group, &[]);
+                rpass.set_vertex_buffer(0, model.vertex_buf.slice(..));
+                rpass.set_index_buffer(model.index_buf.slice(..), wgpu::IndexFormat::Uint16);
+                rpass.draw_indexed(0..model.index_count, 0, 0..1);
+            }
+        }
+    }
+}
@@ -18,9 +18,137 @@ pub enum Mode {
     BottomTop,
 }
 
+impl Mode {
+    // Maps mpv's "video-params/stereo-in" value (e.g. "sbs2l", "ab2r") to a stereo mode. Half-resolution and
+    // full-resolution variants are treated the same, anaglyph and other modes are not supported. "mono" is what mpv
+    // reports for untagged files as well, so it's treated as no metadata.
+    pub fn from_mpv_stereo_in(s: &str) -> Option<Mode> {
+        if s.starts_with("sbs") {
+            Some(cond!(s.ends_with('r'), Mode::RightLeft, Mode::LeftRight))
+        } else if s.starts_with("ab") {
+            Some(cond!(s.ends_with('r'), Mode::BottomTop, Mode::TopBottom))
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AspectRatio {
     Half,
     One,
     Two,
 }
+
+impl AspectRatio {
+    // wraps around, see Action::CycleAspectRatio
+    pub fn next(self) -> AspectRatio {
+        match self {
+            AspectRatio::Half => AspectRatio::One,
+            AspectRatio::One => AspectRatio::Two,
+            AspectRatio::Two => AspectRatio::Half,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AspectRatio::Half => "1/2",
+            AspectRatio::One => "1",
+            AspectRatio::Two => "2",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortMode {
+    Name,
+    DateModified,
+    Size,
+}
+
+// shader debug visualizations, value is passed to shaders as is (see debug_visualization in inc_util.wgsl)
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DebugMode {
+    Off = 0,
+    // texture coordinates used for sampling the video
+    Uv = 1,
+    // eye index used for sampling (after left/right swap), red is left, green is right
+    EyeIndex = 2,
+    // linear view space depth, white at 10m and beyond
+    Depth = 3,
+    // world space ray direction, only meaningful for 360/180 projections
+    RayDirection = 4,
+}
+
+// which VR eyes are rendered and submitted, the other one is skipped entirely (debug only)
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum EyeFilter {
+    Both,
+    LeftOnly,
+    RightOnly,
+}
+
+impl EyeFilter {
+    pub fn left(self) -> bool {
+        self != EyeFilter::RightOnly
+    }
+
+    pub fn right(self) -> bool {
+        self != EyeFilter::LeftOnly
+    }
+}
+
+// what is exposed to other applications via shared output, see danger/shared_output.rs
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SharedOutputSource {
+    Disabled,
+    Companion,
+    // falls back to companion window without VR
+    LeftEye,
+}
+
+impl Default for SharedOutputSource {
+    fn default() -> SharedOutputSource {
+        SharedOutputSource::Disabled
+    }
+}
+
+// desktop window presentation, Fifo is vsync, Mailbox is vsync without blocking, Immediate tears
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentMode {
+    Fifo,
+    Mailbox,
+    Immediate,
+}
+
+impl PresentMode {
+    pub const ALL: [PresentMode; 3] = [PresentMode::Fifo, PresentMode::Mailbox, PresentMode::Immediate];
+
+    pub fn wgpu(self) -> wgpu::PresentMode {
+        match self {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PresentMode::Fifo => "Fifo (VSync)",
+            PresentMode::Mailbox => "Mailbox",
+            PresentMode::Immediate => "Immediate",
+        }
+    }
+}
+
+impl Default for PresentMode {
+    fn default() -> PresentMode {
+        PresentMode::Mailbox
+    }
+}
+
+impl Default for SortMode {
+    fn default() -> SortMode {
+        SortMode::Name
+    }
+}
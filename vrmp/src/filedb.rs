@@ -1,13 +1,32 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
 
+use glam::Vec2;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
 
+use crate::config::{Config, ProjectionPreset};
 use crate::enums::{AspectRatio, Mode, Projection};
 use rusqlite::OptionalExtension;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
 
-pub fn load_file_size_and_hash<P>(path: P) -> Option<(u64, u64)>
+// How many bytes from the start and from the end of the file are hashed.
+const HASHED_CHUNK_SIZE: usize = 128 * 1024;
+
+// File identity. Original hash covers first 128kb only and it's easy to get collisions with it (same encoder/muxer
+// produces the same headers), so there is a second hash which also includes last 128kb and the file size. Old hash is
+// still computed to find DB entries made before hash_v2 was introduced.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct FileKey {
+    pub size: u64,
+    pub hash: u64,
+    pub hash_v2: u64,
+}
+
+pub fn load_file_key<P>(path: P) -> Option<FileKey>
 where
     P: AsRef<Path>,
 {
@@ -15,12 +34,32 @@ where
         Ok(mut f) => match f.metadata() {
             Ok(md) => {
                 let size = md.len();
-                let mut data = vec![0u8; 128 * 1024];
-                if let Ok(hash_size) = f.read(&mut data) {
-                    data.resize(hash_size, 0u8);
-                    let hash = fxhash::hash64(&data);
-                    return Some((size, hash));
+                let mut head = vec![0u8; HASHED_CHUNK_SIZE];
+                let head_size = match f.read(&mut head) {
+                    Ok(n) => n,
+                    Err(e) => {
+                        log::error!("failed reading file: {}", e);
+                        return None;
+                    }
+                };
+                head.resize(head_size, 0u8);
+
+                // tail doesn't overlap with head, for small files it's simply empty
+                let tail_start = size.saturating_sub(HASHED_CHUNK_SIZE as u64).max(head_size as u64);
+                let mut tail = Vec::new();
+                let tail_result = f
+                    .seek(SeekFrom::Start(tail_start))
+                    .and_then(|_| f.by_ref().take(HASHED_CHUNK_SIZE as u64).read_to_end(&mut tail));
+                if let Err(e) = tail_result {
+                    log::error!("failed reading file: {}", e);
+                    return None;
                 }
+
+                return Some(FileKey {
+                    size,
+                    hash: fxhash::hash64(&head),
+                    hash_v2: fxhash::hash64(&(&head, &tail, size)),
+                });
             }
             Err(e) => log::error!("failed reading file metadata: {}", e),
         },
@@ -29,41 +68,30 @@ where
     None
 }
 
-pub fn load_file_hash<P>(path: P) -> Option<u64>
-where
-    P: AsRef<Path>,
-{
-    match std::fs::File::open(path.as_ref()) {
-        Ok(mut f) => {
-            let mut data = vec![0u8; 128 * 1024];
-            if let Ok(hash_size) = f.read(&mut data) {
-                data.resize(hash_size, 0u8);
-                let hash = fxhash::hash64(&data);
-                return Some(hash);
-            }
-        }
-        Err(e) => log::error!("failed opening file: {}", e),
-    };
-    None
-}
-
 // A database with per file info, stored on disk via SQL, but also with manual in-memory cache.
 // On disk we store it in a sqlite table:
-// [file size] [first 128kb file hash] [data]
+// [file size] [first 128kb file hash] [hash v2] [data] [last known path] [last played unix time]
+//
+// Rows are identified by (size, hash_v2), rows with NULL hash_v2 are legacy ones, they get hash_v2 assigned when the
+// file is played for the first time (see FileKey and preload_played_file).
+//
+// Path and last played time are optional and informative only, file identity is (size, hash_v2).
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileData {
-    // Which portions of the file you saw? Each bit corresponds to 1/100th of a file (1 percent if you wish)
-    // This feature is enabled for files which are longer than 500 seconds. Technically I could enable it for smaller
-    // files, but what's the point. Maybe I will figure out a way to calculate it reliably later. Right now it's
-    // recorded when player receives position update, it rounds the position to the closest chunk (out of 100 chunks)
-    // and writes the bit. With 500 second requirement you have 5 seconds (and 5 updates) to actually hit the bit.
+    // Which portions of the file you saw? Each bit corresponds to 1/128th of a file. It's recorded on every "time-pos"
+    // update, and continuous playback marks all the chunks between two updates, so short clips get all of their
+    // chunks as well, see mark_time_span_as_seen.
     //
     // Also I could use u128, but decided not to. Hard to say why. It's not like I plan to port things to wasm, where
     // u128 are not supported. Just don't feel comfortable using u128. Also "ron" serde encoder/decoder supports u128,
     // but never the less.
     pub seen0: u64,
     pub seen1: u64,
+    // duration in seconds the seen bitmap is mapped to, set by the first recorded position, so that a slightly
+    // different duration reported later doesn't shift the chunks
+    #[serde(default)]
+    pub seen_duration: Option<f64>,
 
     pub projection: Projection,
     pub mode: Mode,
@@ -73,28 +101,159 @@ pub struct FileData {
     #[serde(default = "default_stereo_convergence_flat")]
     pub stereo_convergence_flat: f32,
 
-    #[serde(default = "default_flat_distance")]
+    #[serde(default = "crate::config::default_flat_distance")]
     pub flat_distance: f32,
 
-    #[serde(default = "default_flat_scale")]
+    #[serde(default = "crate::config::default_flat_scale")]
     pub flat_scale: f32,
+
+    // sideways offset of the flat screen in meters, positive is to the right
+    #[serde(default)]
+    pub flat_offset: f32,
+
+    // yaw and pitch (radians) of world origin applied on load, None keeps the live origin, see
+    // Action::SaveFileOrientation
+    #[serde(default)]
+    pub saved_orientation: Option<Vec2>,
+
+    // ER 180 vertical coverage and vertical offset of the picture's center, both in degrees, for footage which
+    // doesn't span the full 180 degrees vertically or was shot with a tilted camera
+    #[serde(default = "default_er180_v_fov")]
+    pub er180_v_fov: f32,
+    #[serde(default)]
+    pub er180_pitch_offset: f32,
+
+    // track selections, restored when file is loaded
+    #[serde(default)]
+    pub vid: Option<i64>,
+    #[serde(default)]
+    pub aid: Option<i64>,
+    #[serde(default)]
+    pub sid: Option<i64>,
+
+    // mpv color adjustments, each is in -100..100 range
+    #[serde(default)]
+    pub brightness: i64,
+    #[serde(default)]
+    pub contrast: i64,
+    #[serde(default)]
+    pub gamma: i64,
+    #[serde(default)]
+    pub saturation: i64,
+
+    #[serde(default)]
+    pub deinterlace: bool,
+
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+
+    // mpv "loop-file" is set to "inf" on load
+    #[serde(default)]
+    pub loop_file: bool,
+
+    // set once volume is changed while the file plays, None plays at Config::default_volume
+    #[serde(default)]
+    pub volume: Option<f64>,
+
+    // mirrored footage correction, applied to each eye's picture in all projections
+    #[serde(default)]
+    pub flip_horizontal: bool,
+    #[serde(default)]
+    pub flip_vertical: bool,
+
+    // brightness multipliers for mismatched stereo footage, per viewer's eye
+    #[serde(default = "default_eye_gain")]
+    pub left_eye_gain: f32,
+    #[serde(default = "default_eye_gain")]
+    pub right_eye_gain: f32,
+
+    // flat projection crop, zoom >= 1 magnifies the center of the frame, pan is an offset of the visible region in
+    // texture coordinates (y goes down), see set_flat_zoom_pan
+    #[serde(default = "default_flat_zoom")]
+    pub flat_zoom: f32,
+    #[serde(default)]
+    pub flat_pan: Vec2,
+
+    // mpv subtitle scale and vertical position (0 - top, 100 - bottom, up to 150), subtitles are rendered by mpv
+    // into the video frame itself, None keeps mpv's own value (e.g. from mpv.conf)
+    #[serde(default)]
+    pub sub_scale: Option<f64>,
+    #[serde(default)]
+    pub sub_pos: Option<i64>,
 }
 
 fn default_stereo_convergence_flat() -> f32 {
     0.0
 }
 
-fn default_flat_distance() -> f32 {
-    5.0
+pub fn default_er180_v_fov() -> f32 {
+    180.0
 }
 
-fn default_flat_scale() -> f32 {
-    4.0
+fn default_speed() -> f64 {
+    1.0
+}
+
+fn default_eye_gain() -> f32 {
+    1.0
+}
+
+fn default_flat_zoom() -> f32 {
+    1.0
 }
 
 impl FileData {
-    pub fn mark_as_seen(&mut self, percentage: f64) {
-        let mut p = ((percentage * 1.28).floor() as u8).clamp(0, 127);
+    pub fn new(cfg: &Config) -> FileData {
+        FileData {
+            mode: Mode::Mono,
+            projection: Projection::Flat,
+            seen0: 0,
+            seen1: 0,
+            seen_duration: None,
+            stereo_convergence: cfg.default_stereo_convergence,
+            aspect_ratio: AspectRatio::One,
+            stereo_convergence_flat: default_stereo_convergence_flat(),
+            flat_distance: cfg.flat_distance,
+            flat_scale: cfg.flat_scale,
+            flat_offset: 0.0,
+            saved_orientation: None,
+            er180_v_fov: default_er180_v_fov(),
+            er180_pitch_offset: 0.0,
+            vid: None,
+            aid: None,
+            sid: None,
+            brightness: 0,
+            contrast: 0,
+            gamma: 0,
+            saturation: 0,
+            deinterlace: false,
+            speed: default_speed(),
+            loop_file: false,
+            volume: None,
+            flip_horizontal: false,
+            flip_vertical: false,
+            left_eye_gain: default_eye_gain(),
+            right_eye_gain: default_eye_gain(),
+            flat_zoom: default_flat_zoom(),
+            flat_pan: Vec2::ZERO,
+            sub_scale: None,
+            sub_pos: None,
+        }
+    }
+
+    // marks chunks played through from `from` to `to`, both in seconds
+    pub fn mark_time_span_as_seen(&mut self, from: f64, to: f64, duration: f64) {
+        let duration = *self.seen_duration.get_or_insert(duration);
+        if duration <= 0.0 {
+            return;
+        }
+        let chunk = |t: f64| ((t / duration * 128.0).floor() as i64).clamp(0, 127) as u8;
+        for p in chunk(from.min(to))..=chunk(from.max(to)) {
+            self.mark_chunk_as_seen(p);
+        }
+    }
+
+    fn mark_chunk_as_seen(&mut self, mut p: u8) {
         if p >= 64 {
             p -= 64;
             let bit = 1u64 << p;
@@ -105,6 +264,24 @@ impl FileData {
         }
     }
 
+    // fraction of the file seen so far, in 0..1 range
+    pub fn seen_fraction(&self) -> f32 {
+        (self.seen0.count_ones() + self.seen1.count_ones()) as f32 / 128.0
+    }
+
+    pub fn apply_preset(&mut self, preset: &ProjectionPreset) {
+        self.projection = preset.projection;
+        self.mode = preset.mode;
+        if preset.projection == Projection::Flat {
+            self.stereo_convergence_flat = preset.stereo_convergence;
+        } else {
+            self.stereo_convergence = preset.stereo_convergence;
+        }
+        if let Some(fov) = preset.er180_v_fov {
+            self.er180_v_fov = fov.clamp(90.0, 180.0);
+        }
+    }
+
     pub fn flip_eyes(&mut self) {
         self.mode = match self.mode {
             Mode::TopBottom => Mode::BottomTop,
@@ -114,37 +291,49 @@ impl FileData {
             _ => self.mode,
         };
     }
+
+    // keeps the visible region within the video frame
+    pub fn set_flat_zoom_pan(&mut self, zoom: f32, pan: Vec2) {
+        self.flat_zoom = zoom.clamp(1.0, 10.0);
+        let max_pan = 0.5 - 0.5 / self.flat_zoom;
+        self.flat_pan = pan.clamp(Vec2::splat(-max_pan), Vec2::splat(max_pan));
+    }
 }
 
 pub struct CachedFileData {
     // if file was saved to DB, this is how data looked
     saved_data: Option<FileData>,
     dirty: bool,
-    size: u64,
-    hash: u64,
+    key: FileKey,
+    // set when file gets played, saved together with data, stays untouched in DB otherwise
+    path: Option<String>,
+    last_played: Option<i64>,
+    played: bool,
     // data as it is now
     pub data: FileData,
 }
 
 impl CachedFileData {
-    pub fn new_dirty(key: (u64, u64), data: FileData) -> CachedFileData {
+    pub fn new_dirty(key: FileKey, data: FileData) -> CachedFileData {
         CachedFileData {
             dirty: true,
             ..CachedFileData::new(key, data)
         }
     }
-    pub fn new(key: (u64, u64), data: FileData) -> CachedFileData {
+    pub fn new(key: FileKey, data: FileData) -> CachedFileData {
         CachedFileData {
-            size: key.0,
-            hash: key.1,
+            key,
             saved_data: Some(data.clone()),
             data,
             dirty: false,
+            path: None,
+            last_played: None,
+            played: false,
         }
     }
 }
 
-fn load_sqlite() -> Result<rusqlite::Connection, anyhow::Error> {
+fn load_sqlite(cfg: &Config) -> Result<rusqlite::Connection, anyhow::Error> {
     let dirs = xdg::BaseDirectories::with_prefix("vrmp")?;
     let conn = if let Some(sqlfile) = dirs.find_data_file("files.sqlite") {
         let conn = rusqlite::Connection::open(sqlfile)?;
@@ -155,6 +344,15 @@ fn load_sqlite() -> Result<rusqlite::Connection, anyhow::Error> {
         conn
     };
 
+    conn.busy_timeout(Duration::from_millis(cfg.sqlite_busy_timeout_ms))?;
+    if cfg.sqlite_wal {
+        // WAL lets readers proceed while a write is in progress and makes commits cheaper, the mode is persistent
+        let mode: String = conn.pragma_update_and_check(None, "journal_mode", &"WAL", |row| row.get(0))?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            log::error!("failed enabling sqlite WAL mode, journal mode is {}", mode);
+        }
+    }
+
     conn.execute(
         r#"
             CREATE TABLE IF NOT EXISTS files (
@@ -166,19 +364,138 @@ fn load_sqlite() -> Result<rusqlite::Connection, anyhow::Error> {
         "#,
         [],
     )?;
+    migrate_sqlite(&conn)?;
 
     Ok(conn)
 }
 
+// Schema changes on top of the initial "files" table, tracked via sqlite's user_version pragma.
+fn migrate_sqlite(conn: &rusqlite::Connection) -> Result<(), anyhow::Error> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < 1 {
+        log::info!("migrating files db to version 1");
+        conn.execute_batch(
+            r#"
+                BEGIN;
+                ALTER TABLE files ADD COLUMN path TEXT;
+                ALTER TABLE files ADD COLUMN last_played INT;
+                PRAGMA user_version = 1;
+                COMMIT;
+            "#,
+        )?;
+    }
+    if version < 2 {
+        log::info!("migrating files db to version 2");
+        conn.execute_batch(
+            r#"
+                BEGIN;
+                CREATE INDEX IF NOT EXISTS files_path ON files (path);
+                PRAGMA user_version = 2;
+                COMMIT;
+            "#,
+        )?;
+    }
+    if version < 3 {
+        // primary key changes, so the table has to be recreated
+        log::info!("migrating files db to version 3");
+        conn.execute_batch(
+            r#"
+                BEGIN;
+                CREATE TABLE files_v3 (
+                    size INT NOT NULL,
+                    hash BLOB NOT NULL,
+                    hash_v2 BLOB,
+                    data BLOB NOT NULL,
+                    path TEXT,
+                    last_played INT
+                );
+                INSERT INTO files_v3 (size, hash, data, path, last_played)
+                    SELECT size, hash, data, path, last_played FROM files;
+                DROP TABLE files;
+                ALTER TABLE files_v3 RENAME TO files;
+                CREATE UNIQUE INDEX files_key ON files (size, hash_v2);
+                CREATE INDEX files_legacy_key ON files (size, hash);
+                CREATE INDEX files_path ON files (path);
+                PRAGMA user_version = 3;
+                COMMIT;
+            "#,
+        )?;
+    }
+    Ok(())
+}
+
+// Single row of the "files" table, as it's written in export files.
+#[derive(Serialize, Deserialize)]
+struct ExportedFile {
+    size: u64,
+    hash: u64,
+    #[serde(default)]
+    hash_v2: Option<u64>,
+    data: FileData,
+    path: Option<String>,
+    last_played: Option<i64>,
+}
+
+// Default location of FileDB export file, it's the same file for export and import.
+pub fn default_export_path() -> Result<PathBuf, anyhow::Error> {
+    let dirs = xdg::BaseDirectories::with_prefix("vrmp")?;
+    Ok(dirs.place_data_file("files-export.ron")?)
+}
+
+fn hash_from_bytes(bytes: &[u8]) -> Option<u64> {
+    // hash is stored as native endian bytes, see bytemuck::bytes_of usage
+    <[u8; 8]>::try_from(bytes).ok().map(u64::from_ne_bytes)
+}
+
+fn file_key_from_row(size: u64, hash: &[u8], hash_v2: &[u8]) -> Option<FileKey> {
+    Some(FileKey {
+        size,
+        hash: hash_from_bytes(hash)?,
+        hash_v2: hash_from_bytes(hash_v2)?,
+    })
+}
+
+fn unix_time_now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// Legacy rows (made before hash_v2) are matched by old hash.
+fn select_legacy_entry(conn: &rusqlite::Connection, key: FileKey) -> Result<Option<Vec<u8>>, anyhow::Error> {
+    let mut select_stmt =
+        conn.prepare_cached("SELECT data FROM files WHERE size = ? AND hash = ? AND hash_v2 IS NULL")?;
+    let data = select_stmt
+        .query_row(params![key.size, bytemuck::bytes_of(&key.hash)], |row| {
+            let data: Vec<u8> = row.get(0)?;
+            Ok(data)
+        })
+        .optional()?;
+    Ok(data)
+}
+
+// Assigns hash_v2 to a legacy row, so that other files sharing the old hash don't match it anymore.
+fn upgrade_legacy_entry(conn: &rusqlite::Connection, key: FileKey) -> Result<(), anyhow::Error> {
+    let mut update_stmt =
+        conn.prepare_cached("UPDATE files SET hash_v2 = ? WHERE size = ? AND hash = ? AND hash_v2 IS NULL")?;
+    update_stmt.execute(params![
+        bytemuck::bytes_of(&key.hash_v2),
+        key.size,
+        bytemuck::bytes_of(&key.hash)
+    ])?;
+    Ok(())
+}
+
 pub struct FileDB {
     // files loaded/written from/to DB
-    pub local_file_cache: HashMap<(u64, u64), CachedFileData>,
+    pub local_file_cache: HashMap<FileKey, CachedFileData>,
     conn: Option<rusqlite::Connection>,
 }
 
 impl FileDB {
-    pub fn load() -> FileDB {
-        let conn = match load_sqlite() {
+    pub fn load(cfg: &Config) -> FileDB {
+        let conn = match load_sqlite(cfg) {
             Ok(conn) => Some(conn),
             Err(e) => {
                 log::error!("failed opening sqlite db: {}", e);
@@ -191,63 +508,179 @@ impl FileDB {
         }
     }
 
-    pub fn preload_file(&mut self, size: u64, hash: u64) -> Result<(), anyhow::Error> {
+    // Read-only, a matching legacy entry is loaded but stays legacy, browsing must not claim it for a file which
+    // merely shares the old hash.
+    pub fn preload_file(&mut self, key: FileKey) -> Result<(), anyhow::Error> {
+        self.load_file(key, false)
+    }
+
+    // Same as preload_file, but for the file which is being played, a matching legacy entry is upgraded.
+    pub fn preload_played_file(&mut self, key: FileKey) -> Result<(), anyhow::Error> {
+        self.load_file(key, true)
+    }
+
+    fn load_file(&mut self, key: FileKey, upgrade_legacy: bool) -> Result<(), anyhow::Error> {
         if let Some(conn) = self.conn.as_ref() {
-            let mut select_stmt = conn.prepare_cached("SELECT data FROM files WHERE size = ? AND hash = ?")?;
-            let data = select_stmt
-                .query_row(params![size, bytemuck::bytes_of(&hash)], |row| {
+            let mut select_stmt = conn.prepare_cached("SELECT data FROM files WHERE size = ? AND hash_v2 = ?")?;
+            let mut data = select_stmt
+                .query_row(params![key.size, bytemuck::bytes_of(&key.hash_v2)], |row| {
                     let data: Vec<u8> = row.get(0)?;
                     Ok(data)
                 })
                 .optional()?;
+            if data.is_none() {
+                data = select_legacy_entry(conn, key)?;
+                if data.is_some() && upgrade_legacy {
+                    upgrade_legacy_entry(conn, key)?;
+                }
+            }
             if let Some(data) = data {
                 let fdata: FileData = ron::from_str(&String::from_utf8(data)?)?;
-                self.local_file_cache
-                    .insert((size, hash), CachedFileData::new((size, hash), fdata));
+                self.local_file_cache.insert(key, CachedFileData::new(key, fdata));
             }
         }
         Ok(())
     }
 
-    pub fn get_file_mut(&mut self, key: (u64, u64)) -> &mut FileData {
+    pub fn get_file_mut(&mut self, key: FileKey, cfg: &Config) -> &mut FileData {
         if !self.local_file_cache.contains_key(&key) {
-            self.local_file_cache.insert(
-                key.clone(),
-                CachedFileData::new_dirty(
-                    key,
-                    FileData {
-                        mode: Mode::Mono,
-                        projection: Projection::Flat,
-                        seen0: 0,
-                        seen1: 0,
-                        stereo_convergence: 0.0,
-                        aspect_ratio: AspectRatio::One,
-                        stereo_convergence_flat: default_stereo_convergence_flat(),
-                        flat_distance: default_flat_distance(),
-                        flat_scale: default_flat_scale(),
-                    },
-                ),
-            );
+            self.local_file_cache
+                .insert(key, CachedFileData::new_dirty(key, FileData::new(cfg)));
         }
         let v = self.local_file_cache.get_mut(&key).unwrap();
         v.dirty = true;
         &mut v.data
     }
 
-    pub fn get_file(&mut self, key: (u64, u64)) -> Option<&FileData> {
+    pub fn get_file(&self, key: FileKey) -> Option<&FileData> {
         self.local_file_cache.get(&key).map(|v| &v.data)
     }
 
+    // Removes stored settings of the file. In-memory entry is replaced with defaults which are considered saved, so
+    // they don't get written back to disk unless modified.
+    pub fn forget_file(&mut self, key: FileKey, cfg: &Config) -> Result<(), anyhow::Error> {
+        if let Some(conn) = self.conn.as_ref() {
+            let mut delete_stmt = conn.prepare_cached("DELETE FROM files WHERE size = ? AND hash_v2 = ?")?;
+            delete_stmt.execute(params![key.size, bytemuck::bytes_of(&key.hash_v2)])?;
+        }
+        self.local_file_cache
+            .insert(key, CachedFileData::new(key, FileData::new(cfg)));
+        Ok(())
+    }
+
+    // Removes stored settings of all files.
+    pub fn forget_all_files(&mut self) -> Result<(), anyhow::Error> {
+        if let Some(conn) = self.conn.as_ref() {
+            conn.execute("DELETE FROM files", [])?;
+        }
+        self.local_file_cache.clear();
+        Ok(())
+    }
+
+    // Looks up a file by its last known path and preloads it, use get_file() with returned key to access the data.
+    // Path is only a hint, if file was replaced the key might refer to a different content.
+    // Legacy entries (without hash_v2) are not found by path.
+    pub fn find_file_by_path(&mut self, path: &str) -> Result<Option<FileKey>, anyhow::Error> {
+        let row = {
+            let conn = match &self.conn {
+                Some(conn) => conn,
+                None => return Ok(None),
+            };
+            let mut select_stmt = conn.prepare_cached(
+                r#"
+                    SELECT size, hash, hash_v2 FROM files
+                    WHERE path = ? AND hash_v2 IS NOT NULL
+                    ORDER BY last_played DESC LIMIT 1
+                "#,
+            )?;
+            select_stmt
+                .query_row(params![path], |row| {
+                    let size: u64 = row.get(0)?;
+                    let hash: Vec<u8> = row.get(1)?;
+                    let hash_v2: Vec<u8> = row.get(2)?;
+                    Ok((size, hash, hash_v2))
+                })
+                .optional()?
+        };
+        let key = match row.and_then(|(size, hash, hash_v2)| file_key_from_row(size, &hash, &hash_v2)) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        if !self.local_file_cache.contains_key(&key) {
+            self.preload_file(key)?;
+        }
+        Ok(Some(key))
+    }
+
+    // Remembers where the file was played from and when, creates an entry if there is none.
+    pub fn mark_file_played(&mut self, key: FileKey, path: &str, cfg: &Config) {
+        self.get_file_mut(key, cfg);
+        let v = self.local_file_cache.get_mut(&key).unwrap();
+        v.path = Some(path.to_owned());
+        v.last_played = Some(unix_time_now());
+        v.played = true;
+    }
+
+    // Returns paths of files which were seen partially (seen fraction within [min, max]), most recently played first.
+    pub fn partially_seen_files(&self, min: f32, max: f32, limit: usize) -> Result<Vec<(String, f32)>, anyhow::Error> {
+        let conn = match &self.conn {
+            Some(conn) => conn,
+            None => return Ok(Vec::new()),
+        };
+        let mut select_stmt = conn.prepare_cached(
+            r#"
+                SELECT size, hash, hash_v2, path, data FROM files
+                WHERE path IS NOT NULL
+                ORDER BY last_played DESC
+            "#,
+        )?;
+        let mut rows = select_stmt.query([])?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next()? {
+            let size: u64 = row.get(0)?;
+            let hash: Vec<u8> = row.get(1)?;
+            let hash_v2: Option<Vec<u8>> = row.get(2)?;
+            let path: String = row.get(3)?;
+            let data: Vec<u8> = row.get(4)?;
+            // in-memory cache might be ahead of what's on disk
+            let key = hash_v2.and_then(|hash_v2| file_key_from_row(size, &hash, &hash_v2));
+            let fraction = match key.and_then(|key| self.local_file_cache.get(&key)) {
+                Some(v) => v.data.seen_fraction(),
+                None => ron::from_str::<FileData>(&String::from_utf8(data)?)?.seen_fraction(),
+            };
+            if fraction >= min && fraction <= max {
+                out.push((path, fraction));
+                if out.len() >= limit {
+                    break;
+                }
+            }
+        }
+        Ok(out)
+    }
+
     pub fn save_to_disk_maybe(&mut self) {
         let conn = match &self.conn {
             Some(conn) => conn,
             None => return,
         };
+        // when file is moved, its row gets the new path on next save, but a different file might end up at the old
+        // location, so path is always unique to the most recently played file
+        let mut clear_path_stmt = match conn
+            .prepare_cached("UPDATE files SET path = NULL WHERE path = ? AND NOT (size = ? AND hash_v2 IS ?)")
+        {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                log::error!("failed preparing update stmt: {}", e);
+                return;
+            }
+        };
         let mut select_stmt = match conn.prepare_cached(
             r#"
-                INSERT INTO files VALUES (?, ?, ?)
-                ON CONFLICT (size, hash) DO UPDATE SET
-                    data = ?
+                INSERT INTO files (size, hash, hash_v2, data, path, last_played) VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT (size, hash_v2) DO UPDATE SET
+                    data = excluded.data,
+                    path = COALESCE(excluded.path, path),
+                    last_played = COALESCE(excluded.last_played, last_played)
             "#,
         ) {
             Ok(stmt) => stmt,
@@ -262,12 +695,26 @@ impl FileDB {
             }
 
             v.dirty = false;
-            if Some(v.data.clone()) != v.saved_data {
+            if Some(v.data.clone()) != v.saved_data || v.played {
+                if let (true, Some(path)) = (v.played, &v.path) {
+                    if let Err(e) =
+                        clear_path_stmt.execute(params![path, v.key.size, bytemuck::bytes_of(&v.key.hash_v2)])
+                    {
+                        log::error!("failed clearing stale file path: {}", e);
+                    }
+                }
                 v.saved_data = Some(v.data.clone());
+                v.played = false;
                 match ron::to_string(&v.data).map(|v| Vec::from(v)) {
                     Ok(data) => {
-                        if let Err(e) = select_stmt.execute(params![v.size, bytemuck::bytes_of(&v.hash), &data, &data])
-                        {
+                        if let Err(e) = select_stmt.execute(params![
+                            v.key.size,
+                            bytemuck::bytes_of(&v.key.hash),
+                            bytemuck::bytes_of(&v.key.hash_v2),
+                            &data,
+                            &v.path,
+                            &v.last_played
+                        ]) {
                             log::error!("failed saving file: {}", e);
                         }
                     }
@@ -278,4 +725,101 @@ impl FileDB {
             }
         }
     }
+
+    // Dumps all rows into a single RON file, unsaved in-memory changes are flushed first.
+    pub fn export_to_path(&mut self, path: &Path) -> Result<usize, anyhow::Error> {
+        self.save_to_disk_maybe();
+        let conn = match &self.conn {
+            Some(conn) => conn,
+            None => return Err(anyhow::anyhow!("file db is not available")),
+        };
+        let mut select_stmt = conn.prepare_cached("SELECT size, hash, hash_v2, data, path, last_played FROM files")?;
+        let mut rows = select_stmt.query([])?;
+        let mut files = Vec::new();
+        while let Some(row) = rows.next()? {
+            let hash_bytes: Vec<u8> = row.get(1)?;
+            let hash = match hash_from_bytes(&hash_bytes) {
+                Some(hash) => hash,
+                None => {
+                    log::error!("skipping file db row with malformed hash");
+                    continue;
+                }
+            };
+            let hash_v2_bytes: Option<Vec<u8>> = row.get(2)?;
+            let data: Vec<u8> = row.get(3)?;
+            files.push(ExportedFile {
+                size: row.get(0)?,
+                hash,
+                hash_v2: hash_v2_bytes.and_then(|b| hash_from_bytes(&b)),
+                data: ron::from_str(&String::from_utf8(data)?)?,
+                path: row.get(4)?,
+                last_played: row.get(5)?,
+            });
+        }
+        let s = ron::ser::to_string_pretty(&files, ron::ser::PrettyConfig::new())?;
+        std::fs::write(path, s)?;
+        Ok(files.len())
+    }
+
+    // Upserts rows from a RON file made by export_to_path. On conflict the most recently played version wins, rows
+    // without play time never overwrite existing ones. Legacy rows (without hash_v2) are only inserted if there is no
+    // legacy row for the same file.
+    pub fn import_from_path(&mut self, path: &Path) -> Result<usize, anyhow::Error> {
+        self.save_to_disk_maybe();
+        let s = std::fs::read_to_string(path)?;
+        let files: Vec<ExportedFile> = ron::from_str(&s)?;
+        let conn = match &mut self.conn {
+            Some(conn) => conn,
+            None => return Err(anyhow::anyhow!("file db is not available")),
+        };
+        let tx = conn.transaction()?;
+        let mut num_imported = 0;
+        {
+            let mut insert_stmt = tx.prepare(
+                r#"
+                    INSERT INTO files (size, hash, hash_v2, data, path, last_played) VALUES (?, ?, ?, ?, ?, ?)
+                    ON CONFLICT (size, hash_v2) DO UPDATE SET
+                        data = excluded.data,
+                        path = COALESCE(excluded.path, path),
+                        last_played = excluded.last_played
+                    WHERE excluded.last_played > COALESCE(last_played, 0)
+                "#,
+            )?;
+            let mut insert_legacy_stmt = tx.prepare(
+                r#"
+                    INSERT INTO files (size, hash, data, path, last_played)
+                    SELECT ?1, ?2, ?3, ?4, ?5
+                    WHERE NOT EXISTS (SELECT 1 FROM files WHERE size = ?1 AND hash = ?2 AND hash_v2 IS NULL)
+                "#,
+            )?;
+            for f in &files {
+                let data = ron::to_string(&f.data)?;
+                num_imported += match f.hash_v2 {
+                    Some(hash_v2) => insert_stmt.execute(params![
+                        f.size,
+                        bytemuck::bytes_of(&f.hash),
+                        bytemuck::bytes_of(&hash_v2),
+                        data.as_bytes(),
+                        &f.path,
+                        &f.last_played
+                    ])?,
+                    None => insert_legacy_stmt.execute(params![
+                        f.size,
+                        bytemuck::bytes_of(&f.hash),
+                        data.as_bytes(),
+                        &f.path,
+                        &f.last_played
+                    ])?,
+                };
+            }
+        }
+        tx.commit()?;
+
+        // cached entries might be outdated now, reload them
+        let keys = self.local_file_cache.keys().copied().collect::<Vec<_>>();
+        for key in keys {
+            self.preload_file(key)?;
+        }
+        Ok(num_imported)
+    }
 }
@@ -1,6 +1,10 @@
 use std::{
     borrow::Cow,
-    mem,
+    collections::HashSet,
+    f32::consts::PI,
+    ffi::OsStr,
+    mem::{self, ManuallyDrop},
+    path::{Path, PathBuf},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -9,9 +13,11 @@ use argh::FromArgs;
 use bytemuck_derive::{Pod, Zeroable};
 use glam::{Mat4, Quat, Vec2, Vec3};
 use sdl2::{
+    controller::{Axis, Button, GameController},
     event::{Event, WindowEvent},
-    keyboard::Keycode,
+    keyboard::{Keycode, Mod},
     mouse::MouseButton,
+    video::FullscreenType,
 };
 use wgpu_hal::InstanceFlags;
 
@@ -19,17 +25,73 @@ use crate::{
     action::{Action, ActionBin},
     camera_state::CameraState,
     config::{Config, ConfigSyncer},
-    danger::{self, egl_bridge::EGLContext},
-    enums::{AspectRatio, Projection},
-    filedb::FileDB,
+    controls::Controls,
+    danger::{self, egl_bridge::EGLContext, vulkan::SharedTextureFormat},
+    device_models::DeviceModels,
+    enums::{AspectRatio, Mode, Projection, SharedOutputSource},
+    filedb::{default_export_path, FileDB, FileData, FileKey},
     imgui::font_awesome,
-    imgui::{file_browser::ImguiFileBrowser, general::General},
-    pipeline::{fullscreen_triangle::FullscreenTriangle, textured_quad::TexturedQuad},
-    scene::{render_scene, Scene, VideoRenderer},
-    vrinfo::VRInfo,
+    imgui::{
+        file_browser::{adjacent_video_file, ImguiFileBrowser},
+        general::{General, MAX_SPEED, MIN_SPEED},
+        stats::FrameStats,
+        toasts::{expire_toasts, push_toast, render_buffering, render_toasts},
+    },
+    pipeline::{
+        curved_quad::CurvedQuad, fullscreen_triangle::FullscreenTriangle, render_model::RenderModelPipeline,
+        sphere_mesh::SphereMesh, textured_quad::TexturedQuad,
+    },
+    scene::{render_scene, ui_curvature_radius, ui_panel_hit, ui_panel_size, ui_panel_transform, Scene, VideoRenderer},
+    status::{Status, StatusWriter},
+    timeline::Timeline,
+    vrinfo::{scale_eye_to_head_mat, VRInfo},
     vscreen::VScreen,
 };
-use crate::{filedb::load_file_size_and_hash, tracks::Tracks};
+use crate::{
+    filedb::load_file_key,
+    tracks::Tracks,
+    undo::{UndoHistory, ViewSettings},
+};
+
+// stick deflection below this is ignored, cheap sticks don't return to zero exactly
+const GAMEPAD_DEAD_ZONE: f32 = 0.25;
+const GAMEPAD_LOOK_DEGREES_PER_SEC: f32 = 120.0;
+// gamepad buttons feeding imgui navigation while UI is shown, they don't trigger control map actions then
+const GAMEPAD_NAV_BUTTONS: &[(Button, imgui::NavInput)] = &[
+    (Button::DPadUp, imgui::NavInput::DpadUp),
+    (Button::DPadDown, imgui::NavInput::DpadDown),
+    (Button::DPadLeft, imgui::NavInput::DpadLeft),
+    (Button::DPadRight, imgui::NavInput::DpadRight),
+    (Button::A, imgui::NavInput::Activate),
+    (Button::B, imgui::NavInput::Cancel),
+    (Button::X, imgui::NavInput::Menu),
+    (Button::Y, imgui::NavInput::Input),
+    (Button::LeftShoulder, imgui::NavInput::FocusPrev),
+    (Button::RightShoulder, imgui::NavInput::FocusNext),
+];
+
+// eye supersample factor multiplier in idle power saving mode, see update_power_saving
+const IDLE_SUPERSAMPLE_SCALE: f32 = 0.5;
+// head turn which ends idle power saving mode
+const IDLE_WAKE_UP_DEGREES: f32 = 20.0;
+// pose changes below these are ignored when deciding whether eye frames can be reused, see can_reuse_eye_frames
+const STATIC_FRAME_MAX_DEGREES: f32 = 0.05;
+const STATIC_FRAME_MAX_METERS: f32 = 0.0005;
+// main loop throttling while nothing is rendered, see is_render_suspended
+const SUSPENDED_FRAME_SLEEP: Duration = Duration::from_millis(50);
+
+// per-file mpv properties which are saved only when changed from UI or controls, see save_user_property
+const USER_SAVED_PROPERTIES: &[&str] = &[
+    "brightness",
+    "contrast",
+    "gamma",
+    "saturation",
+    "deinterlace",
+    "speed",
+    "loop-file",
+    "sub-scale",
+    "sub-pos",
+];
 
 fn reset_origin(cam_mat: Mat4) -> Mat4 {
     let (_, rot, tr) = cam_mat.inverse().to_scale_rotation_translation();
@@ -37,7 +99,72 @@ fn reset_origin(cam_mat: Mat4) -> Mat4 {
     return Mat4::from_translation(tr) * Mat4::from_rotation_y(y);
 }
 
-fn create_depth_texture(device: &wgpu::Device, w: u32, h: u32) -> wgpu::TextureView {
+// yaw and pitch of the view direction, see FileData::saved_orientation
+fn view_yaw_pitch(cam_mat: Mat4) -> Vec2 {
+    let (_, rot, _) = cam_mat.inverse().to_scale_rotation_translation();
+    let (y, x, _) = rot.to_euler(glam::EulerRot::YXZ);
+    Vec2::new(y, x)
+}
+
+// same as reset_origin, but facing the given direction instead of the current one
+fn oriented_origin(cam_mat: Mat4, yaw_pitch: Vec2) -> Mat4 {
+    let (_, _, tr) = cam_mat.inverse().to_scale_rotation_translation();
+    Mat4::from_translation(tr) * Mat4::from_rotation_y(yaw_pitch.x) * Mat4::from_rotation_x(yaw_pitch.y)
+}
+
+fn companion_proj_mat(config: &Config, w: u32, h: u32) -> Mat4 {
+    let (near, far) = config.clip_planes();
+    let fov = config.companion_fov_degrees.clamp(30.0, 120.0).to_radians();
+    Mat4::perspective_lh(fov, w as f32 / h as f32, near, far)
+}
+
+// configure() falls back to Fifo with a warning when the requested mode is not supported, so the effective mode is
+// logged, see VulkanWGPU::present_modes
+fn log_present_mode(requested: wgpu::PresentMode, supported: &[wgpu::PresentMode]) {
+    if supported.is_empty() {
+        log::info!("desktop window present mode: {:?} (support unknown)", requested);
+    } else if supported.contains(&requested) {
+        log::info!("desktop window present mode: {:?}", requested);
+    } else {
+        log::info!(
+            "desktop window present mode: Fifo ({:?} is not supported, supported: {:?})",
+            requested,
+            supported
+        );
+    }
+}
+
+// Some compositors report 0x0 drawable size until the window is mapped, while surface and attachments can't be
+// zero-sized. Events are pumped for a while waiting for a real size, then requested window size is used instead.
+fn initial_drawable_size(sdl_context: &sdl2::Sdl, window: &sdl2::video::Window) -> (u32, u32) {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    loop {
+        let (w, h) = window.drawable_size();
+        if w > 0 && h > 0 {
+            return (w, h);
+        }
+        if Instant::now() >= deadline {
+            let (ww, wh) = window.size();
+            let size = (ww.max(1), wh.max(1));
+            log::error!(
+                "window drawable size is {}x{}, using {}x{} instead",
+                w,
+                h,
+                size.0,
+                size.1
+            );
+            return size;
+        }
+        // events stay queued, they are handled by the main loop later
+        match sdl_context.event_pump() {
+            Ok(mut pump) => pump.pump_events(),
+            Err(e) => log::error!("failed pumping window events: {}", e),
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn create_depth_texture(device: &wgpu::Device, w: u32, h: u32, sample_count: u32) -> wgpu::TextureView {
     let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
         label: None,
         size: wgpu::Extent3d {
@@ -46,7 +173,7 @@ fn create_depth_texture(device: &wgpu::Device, w: u32, h: u32) -> wgpu::TextureV
             ..Default::default()
         },
         mip_level_count: 1,
-        sample_count: 1,
+        sample_count,
         dimension: wgpu::TextureDimension::D2,
         format: wgpu::TextureFormat::Depth32Float,
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -54,6 +181,41 @@ fn create_depth_texture(device: &wgpu::Device, w: u32, h: u32) -> wgpu::TextureV
     depth_texture.create_view(&wgpu::TextureViewDescriptor::default())
 }
 
+// multisampled color texture to render into before resolving into swapchain texture, not needed without MSAA
+fn create_msaa_texture(
+    device: &wgpu::Device,
+    w: u32,
+    h: u32,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    (sample_count > 1).then(|| {
+        let msaa_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: w,
+                height: h,
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        msaa_texture.create_view(&wgpu::TextureViewDescriptor::default())
+    })
+}
+
+// Render targets and pipelines used together in render_scene must have the same sample count, otherwise it's a wgpu
+// validation error in the middle of a frame. Checked once after creation, names are for the error message.
+fn check_sample_counts(sample_count: u32, used: &[(&str, u32)]) -> Result<(), String> {
+    match used.iter().find(|(_, c)| *c != sample_count) {
+        Some((name, c)) => Err(format!("{} sample count is {}, expected {}", name, c, sample_count)),
+        None => Ok(()),
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 struct LineVertex {
@@ -61,6 +223,27 @@ struct LineVertex {
     color: glam::Vec3,
 }
 
+// what the current eye frames were rendered with, see Global::can_reuse_eye_frames
+struct EyeFrameState {
+    hmd_mat: Mat4,
+    device_poses: Vec<Mat4>,
+    eye_width: u32,
+}
+
+fn is_same_pose(a: Mat4, b: Mat4) -> bool {
+    let (_, rot, tr) = (a * b.inverse()).to_scale_rotation_translation();
+    rot.angle_between(Quat::IDENTITY).to_degrees() <= STATIC_FRAME_MAX_DEGREES && tr.length() <= STATIC_FRAME_MAX_METERS
+}
+
+// see Global::update_volume_fade
+struct VolumeFade {
+    start: Instant,
+    // volume before the fade started
+    target: f64,
+    // last volume value sent to mpv
+    last: i64,
+}
+
 #[derive(FromArgs)]
 /// VR media player
 pub struct Arguments {
@@ -71,19 +254,21 @@ pub struct Arguments {
     #[argh(switch)]
     /// enable vulkan debug and validation layers
     pub validation_layers: bool,
+
+    #[argh(option)]
+    /// force vulkan physical device by index (all devices are listed in the log)
+    pub gpu: Option<usize>,
+
+    #[argh(positional)]
+    /// video file or URL to open at startup
+    pub path: Option<String>,
 }
 
 pub struct Global {
-    // rust assumes there are no dependencies between struct members and it runs destructors in declaration order
-    // (but my code is unsafe here and there are dependencies between struct members, I should probably do something
-    // about it)
-    //                   ##
-    //                   ##
-    //                   ##
-    //                   ##
-    //               ##  ##  ##
-    //                 ######
-    //                   ##
+    // Rust runs destructors in declaration order and assumes there are no dependencies between struct members. Members
+    // with unsafe parts (GPU, shared textures, mpv render context, VR, EGL) do depend on each other, they are wrapped
+    // in ManuallyDrop/Option and torn down by shutdown in explicit order, the rest is dropped as usual afterwards.
+    // See run, it guarantees shutdown is called.
 
     // various stuff that is trivially destructable, no need to order it
     time: Instant,
@@ -96,88 +281,202 @@ pub struct Global {
     camera_state: CameraState,
     swap_z: Mat4,
     ui_origin: Mat4,
+    // ui_origin is kept as is when UI is shown, see Action::TogglePinUI
+    ui_pinned: bool,
     world_origin: Mat4,
+    // set by Action::RecenterScreen, cleared when world origin is reset
+    screen_origin: Option<Mat4>,
+    // when VR playback got paused, None while playing, see update_power_saving
+    idle_since: Option<Instant>,
+    // head pose at the moment idle power saving kicked in, None when it's off
+    idle_power_saving_view: Option<Mat4>,
     proj_mat: Mat4,
     view_mat: Mat4,
     move_forward: bool,
     move_backward: bool,
     move_right: bool,
     move_left: bool,
+    // middle mouse button drag pans flat screen crop (shift + drag if middle click has an action), see
+    // FileData::flat_pan
+    flat_panning: bool,
+    // drag without motion is a regular middle click
+    flat_pan_moved: bool,
+    // shift + left mouse button drag moves flat screen, see FileData::flat_offset
+    flat_placing: bool,
     cam_pos: Vec3,
     cam_quat: Quat,
     is_running: bool,
     is_gui: bool,
+    is_mini_ui: bool,
+    is_quick_projection: bool,
+    // controller laser pointer model matrix when it points at the UI, see update_laser_pointer
+    laser: Option<Mat4>,
+    laser_trigger: bool,
+    paused_by_focus_loss: bool,
+    window_minimized: bool,
+    hmd_tracking_lost: bool,
+    // eyes are not rendered/submitted while SteamVR dashboard is shown or HMD is not worn, see handle_vr_events
+    should_render_eyes: bool,
+    // mpv drew a new frame in the last gl_render
+    video_frame_changed: bool,
+    // None forces eye rendering on the next frame, see can_reuse_eye_frames
+    last_eye_frame: Option<EyeFrameState>,
+    // shows current file as mono regardless of its stored mode, reset on file change
+    force_mono: bool,
+    vr_dashboard_active: bool,
+    vr_hmd_idle: bool,
     suboptimal: bool,
+    sample_count: u32,
     surface_config: wgpu::SurfaceConfiguration,
     config_syncer: ConfigSyncer,
+    controls: Controls,
     async_size: (Option<u32>, Option<u32>),
+    // size of the "@vrmp-downscale" video filter if it's active, see fit_video_size
+    video_downscale: Option<(u32, u32)>,
     current_file_path: Option<String>,
     current_file_duration: Option<u32>,
-    current_file_key: Option<(u64, u64)>,
+    timeline: Timeline,
+    current_file_key: Option<FileKey>,
     current_file_tracks: Option<Tracks>,
+    volume_fade: Option<VolumeFade>,
+    // mpv's "volume-max" as observed, restored volumes are clamped to it
+    volume_max: f64,
+    // volume command from UI or controls is in flight, the next observed volume is saved to the current file
+    volume_changed_by_user: bool,
+    // USER_SAVED_PROPERTIES with a command from UI or controls in flight, the next observed value is saved
+    properties_changed_by_user: HashSet<&'static str>,
+    // UI edits of the current file, cleared on file change
+    undo_history: UndoHistory,
+    // track selections are saved per file only after saved ones were restored, see restore_track_selections
+    current_file_tracks_restored: bool,
+    // stereo mode is detected from video-params once per file and only if the file has no stored settings
+    current_file_detect_stereo_mode: bool,
     filedb: FileDB,
     action_bin: ActionBin,
+    status_writer: StatusWriter,
+    // short messages shown on top of the UI, see notify
+    toasts: Vec<(String, Instant)>,
+    frame_stats: FrameStats,
 
-    // wgpu resources, generally it's safe to destroy them in arbitrary order
-    vr_info: Option<VRInfo>,
-    vscreen: VScreen,
-    tquad_shared_tex: TexturedQuad,
-    tquad_imgui: TexturedQuad,
-
-    ftri_equirectangular_360: FullscreenTriangle,
-    ftri_equirectangular_180: FullscreenTriangle,
-    ftri_fisheye_180: FullscreenTriangle,
-    ftri_equiangular_cubemap: FullscreenTriangle,
+    // current comfort vignette intensity and the view it was computed against, see update_comfort_vignette
+    comfort_vignette: f32,
+    comfort_vignette_prev_view: Mat4,
+    // tunnel shown after world origin changes, goes from 1 to 0, drawn with the comfort vignette pipeline
+    fov_reduction: f32,
 
-    camera_state_uniform_buf: wgpu::Buffer,
-    lines_buf: wgpu::Buffer,
-    camera_bgrp: wgpu::BindGroup,
-    lines_pipeline: wgpu::RenderPipeline,
-    depth_view: wgpu::TextureView,
-    black_texture_bgrp: wgpu::BindGroup,
+    // wgpu resources, destroyed in shutdown before the device they were created on
+    gpu_res: ManuallyDrop<GpuResources>,
 
-    // I destroy these manually in shutdown function, at least their unsafe part
-    shared_tex: danger::shared_texture::SharedTexture,
-    gpu: danger::vulkan::VulkanWGPU,
+    // destroyed in shutdown
+    shared_tex: ManuallyDrop<danger::shared_texture::SharedTexture>,
+    shared_output: Option<danger::shared_output::SharedOutput>,
+    gpu: ManuallyDrop<danger::vulkan::VulkanWGPU>,
 
     // mpv
-    mpv_render: Box<libmpv::RenderContext>,
+    mpv_render: ManuallyDrop<Box<libmpv::RenderContext>>, // destroyed in shutdown
     mpv: Box<libmpv::Context>,
 
     // imgui
     imgui_general: General,
     imgui_file_browser: ImguiFileBrowser,
-    imgui_renderer: imgui_wgpu::Renderer,
     imgui: imgui::Context,
 
     // sdl2
     sdl_event_pump: sdl2::EventPump,
     sdl_window: sdl2::video::Window,
     sdl_video_subsystem: sdl2::VideoSubsystem,
+    sdl_controller_subsystem: Option<sdl2::GameControllerSubsystem>,
     sdl_context: sdl2::Sdl,
+    // opened on ControllerDeviceAdded, SDL sends it for already connected ones at startup as well
+    gamepads: Vec<GameController>,
+    // left stick x/y, right stick x/y in -1..1 range, with dead zone applied
+    gamepad_axes: [f32; 4],
 
     // vr
-    vr: Option<Box<libopenvr::Context>>, // destroyed manually
+    vr: Option<Box<libopenvr::Context>>, // destroyed in shutdown
 
     // egl
-    egl: Box<EGLContext>,
+    egl: ManuallyDrop<Box<EGLContext>>, // destroyed in shutdown
+}
+
+// Members of Global which own wgpu objects. They must not outlive the device, so they are grouped to be dropped as a
+// whole at the start of Global::shutdown, generally it's safe to destroy them in arbitrary order.
+struct GpuResources {
+    vr_info: Option<VRInfo>,
+    vscreen: VScreen,
+    tquad_shared_tex: TexturedQuad,
+    tquad_imgui: TexturedQuad,
+    ui_mesh: CurvedQuad,
+    render_model_pipeline: RenderModelPipeline,
+    device_models: DeviceModels,
+    imgui_renderer: imgui_wgpu::Renderer,
+
+    ftri_equirectangular_360: FullscreenTriangle,
+    ftri_equirectangular_180: FullscreenTriangle,
+    ftri_fisheye_180: FullscreenTriangle,
+    ftri_equiangular_cubemap: FullscreenTriangle,
+    ftri_comfort_vignette: FullscreenTriangle,
+    // used instead of ftri_equirectangular_* when Config::sphere_mesh is on
+    sphere_equirectangular_360: SphereMesh,
+    sphere_equirectangular_180: SphereMesh,
+
+    camera_state_uniform_buf: wgpu::Buffer,
+    lines_buf: wgpu::Buffer,
+    laser_buf: wgpu::Buffer,
+    camera_bgrp: wgpu::BindGroup,
+    lines_pipeline: wgpu::RenderPipeline,
+    depth_view: wgpu::TextureView,
+    msaa_view: Option<wgpu::TextureView>,
+    black_texture_bgrp: wgpu::BindGroup,
+}
+
+// Calls Global::shutdown when dropped, which happens on regular exit and when unwinding from a panic alike.
+struct ShutdownGuard(Global);
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        // global is dropped right after the guard, nothing else touches it in between
+        unsafe { self.0.shutdown() };
+    }
 }
 
 impl Global {
     pub fn init() -> Global {
         let config_syncer = ConfigSyncer::new(Config::load().expect("failed loading config"));
+        let controls = Controls::load().unwrap_or_else(|e| {
+            log::error!("failed loading controls, using defaults: {}", e);
+            Controls::defaults()
+        });
         let args: Arguments = argh::from_env();
         let egl = danger::egl_bridge::load_egl();
         log::info!("loading app");
 
-        let vr = args
+        // missing VR runtime is not fatal, the app continues in desktop mode
+        let mut toasts = Vec::new();
+        let vr = match args
             .vr
-            .then(|| libopenvr::Context::create(libopenvr::ApplicationType::Scene));
+            .then(|| libopenvr::Context::create(libopenvr::ApplicationType::Scene))
+        {
+            Some(Ok(vr)) => Some(vr),
+            Some(Err(e)) => {
+                log::warn!("failed initializing VR, continuing without VR: {}", e);
+                push_toast(&mut toasts, "VR is not available, is SteamVR running?".to_owned());
+                None
+            }
+            None => None,
+        };
 
         sdl2::hint::set("SDL_VIDEO_X11_FORCE_EGL", "1");
 
         let sdl_context = sdl2::init().unwrap();
         let sdl_video_subsystem = sdl_context.video().unwrap();
+        let sdl_controller_subsystem = match sdl_context.game_controller() {
+            Ok(s) => Some(s),
+            Err(e) => {
+                log::error!("failed initializing game controller subsystem: {}", e);
+                None
+            }
+        };
 
         let sdl_window = sdl_video_subsystem
             .window("vrmp", 1920, 1080)
@@ -192,8 +491,18 @@ impl Global {
             .make_current(egl.display, Some(egl.surface), Some(egl.surface), Some(egl.context))
             .unwrap();
 
-        let mpv = libmpv::Context::create();
+        let mpv = libmpv::Context::create(&config_syncer.get().mpv_hwdec, &config_syncer.get().mpv_profile);
         mpv.initialize();
+        let shared_texture_format = cond!(
+            config_syncer.get().hdr_shared_texture,
+            SharedTextureFormat::Rgba16Float,
+            SharedTextureFormat::Srgb8
+        );
+        // explicit, default "auto" picks the transfer function based on display which mpv doesn't know about
+        mpv.command_async(&["set", "target-trc", shared_texture_format.mpv_target_trc()]);
+        if shared_texture_format == SharedTextureFormat::Rgba16Float {
+            log::info!("using half-float shared texture");
+        }
 
         mpv.observe_property("sid");
         mpv.observe_property("vid");
@@ -201,13 +510,60 @@ impl Global {
         mpv.observe_property("pause");
         mpv.observe_property("hwdec");
         mpv.observe_property("hwdec-current");
+        mpv.observe_property("brightness");
+        mpv.observe_property("contrast");
+        mpv.observe_property("gamma");
+        mpv.observe_property("saturation");
+        mpv.observe_property("deinterlace");
+        mpv.observe_property("speed");
+        mpv.observe_property("sub-scale");
+        mpv.observe_property("sub-pos");
+        mpv.observe_property("volume");
+        mpv.observe_property("volume-max");
+        mpv.observe_property("loop-file");
+        mpv.observe_property("idle-active");
+        mpv.observe_property("estimated-vf-fps");
+        mpv.observe_property("video-bitrate");
+        mpv.observe_property("paused-for-cache");
+        mpv.observe_property("cache-buffering-state");
+        mpv.observe_property("demuxer-cache-duration");
+        mpv.observe_property("frame-drop-count");
+        mpv.observe_property("time-pos");
+        mpv.observe_property("duration");
+
+        // file passed via command line is opened the same way as if it was clicked in the file browser, anything
+        // which is not on disk (URLs, mpv protocols like av://) goes to mpv as is
+        let mut action_bin = ActionBin::create();
+        if let Some(path) = &args.path {
+            let path = if Path::new(path).exists() {
+                match std::fs::canonicalize(path) {
+                    Ok(p) => p.to_string_lossy().to_string(),
+                    Err(e) => {
+                        log::error!("failed resolving path {}: {}", path, e);
+                        path.clone()
+                    }
+                }
+            } else {
+                path.clone()
+            };
+            action_bin.put(Action::Command(vec!["loadfile".to_owned(), path]));
+        }
 
         // NOTE: mpv uses references to egl here in its event callbacks, please make sure it's kept in a Box<_>,
         // otherwise pointer will be invalidated after move out of init() function we're in
-        let mpv_render = unsafe { mpv.create_render_context(&egl.egl, &sdl_window) };
+        let mpv_render = match unsafe { mpv.create_render_context(&egl.egl, &sdl_window) } {
+            Ok(r) => r,
+            Err(e) => {
+                log::error!("failed creating mpv render context: {}", e);
+                if let Some(vr_ctx) = &vr {
+                    vr_ctx.shutdown();
+                }
+                std::process::exit(1);
+            }
+        };
 
-        let (w, h) = sdl_window.drawable_size();
-        let gpu = unsafe {
+        let (w, h) = initial_drawable_size(&sdl_context, &sdl_window);
+        let mut gpu = unsafe {
             danger::vulkan::VulkanWGPU::create(&danger::vulkan::LoadVulkanWGPUParams {
                 vr_ctx: vr.as_ref().map(|v| v.as_ref()),
                 window: &sdl_window,
@@ -217,6 +573,7 @@ impl Global {
                     ..Default::default()
                 },
                 flags: cond!(args.validation_layers, InstanceFlags::all(), InstanceFlags::empty()),
+                gpu_index: args.gpu,
             })
         };
 
@@ -273,9 +630,20 @@ impl Global {
             gpu.vk_physical_device,
             &gpu.device,
             shared_texture_bind_group_layout.clone(),
+            &mut gpu.cmd_pool,
+            gpu.vk_queue,
             512,
             512,
+            shared_texture_format,
+            config_syncer.get().gl_vk_semaphore_sync,
         );
+        if !config_syncer.get().gl_vk_semaphore_sync {
+            log::info!("GL<->VK semaphore sync is disabled, using glFinish fallback");
+        }
+        let shared_output = match config_syncer.get().shared_output {
+            SharedOutputSource::Disabled => None,
+            _ => danger::shared_output::SharedOutput::create(),
+        };
 
         // Load the shaders from disk
         let lines_shader = gpu.device.create_shader_module(&wgpu::ShaderModuleDescriptor {
@@ -316,13 +684,32 @@ impl Global {
 
         let swapchain_format = gpu.surface.get_preferred_format(&gpu.adapter).unwrap();
 
-        let vr_info = vr.as_ref().map(|vr_ctx| VRInfo::create(&vr_ctx, &gpu.device));
+        // every render target and pipeline used by render_scene shares this sample count
+        let sample_count = match config_syncer.get().msaa_samples {
+            v @ (1 | 4) => v,
+            v => {
+                log::warn!("unsupported MSAA sample count: {}, MSAA is disabled", v);
+                1
+            }
+        };
+
+        let supersample_factor = config_syncer.get().supersample_factor;
+        let vr_info = vr.as_ref().map(|vr_ctx| {
+            VRInfo::create(
+                &vr_ctx,
+                &gpu.device,
+                supersample_factor,
+                sample_count,
+                config_syncer.get().clip_planes(),
+            )
+        });
         if let Some(vr_info) = &vr_info {
             log::info!(
                 "Recommended Eye Resolution: {}x{}",
                 vr_info.recommended_eye_size.0,
                 vr_info.recommended_eye_size.1
             );
+            log::info!("Supersample Factor: {}", vr_info.supersample_factor);
             log::info!("Eye Resolution: {}x{}", vr_info.eye_w, vr_info.eye_h);
             log::info!("IPD: {}", vr_info.ipd);
         }
@@ -356,11 +743,15 @@ impl Global {
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             }),
-            multisample: wgpu::MultisampleState::default(),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
             multiview: None,
         });
 
-        let depth_view = create_depth_texture(&gpu.device, w, h);
+        let depth_view = create_depth_texture(&gpu.device, w, h, sample_count);
+        let msaa_view = create_msaa_texture(&gpu.device, w, h, swapchain_format, sample_count);
 
         //---------------------------------------------------------------------------------
 
@@ -369,10 +760,11 @@ impl Global {
             format: swapchain_format,
             width: w,
             height: h,
-            present_mode: wgpu::PresentMode::Mailbox,
+            present_mode: config_syncer.get().present_mode.wgpu(),
         };
 
         gpu.surface.configure(&gpu.device, &surface_config);
+        log_present_mode(surface_config.present_mode, &gpu.present_modes);
 
         //---------------------------------------------------------------------------------
         let lines_buf = gpu.device.create_buffer(&wgpu::BufferDescriptor {
@@ -413,6 +805,29 @@ impl Global {
             ]),
         );
 
+        let laser_buf = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (mem::size_of::<LineVertex>() * 2) as _,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // openvr controllers point along -Z
+        gpu.queue.write_buffer(
+            &laser_buf,
+            0,
+            bytemuck::cast_slice(&[
+                LineVertex {
+                    position: Vec3::new(0.0, 0.0, 0.0),
+                    color: Vec3::new(0.0, 0.5, 1.0),
+                },
+                LineVertex {
+                    position: Vec3::new(0.0, 0.0, -1.0),
+                    color: Vec3::new(0.0, 0.5, 1.0),
+                },
+            ]),
+        );
+
         let camera_state_uniform_buf = gpu.device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
             size: mem::size_of::<CameraState>() as _,
@@ -485,6 +900,7 @@ impl Global {
             &gpu.queue,
             swapchain_format.into(),
             &pipeline_layout,
+            sample_count,
             include_shader!("proj_flat.wgsl"),
         );
         let tquad_imgui = TexturedQuad::create(
@@ -496,88 +912,203 @@ impl Global {
                 write_mask: wgpu::ColorWrites::ALL,
             },
             &pipeline_layout,
+            sample_count,
             include_shader!("textured_quad.wgsl"),
         );
 
+        let ui_mesh = CurvedQuad::create(&gpu.device);
+
+        let render_model_pipeline = RenderModelPipeline::create(
+            &gpu.device,
+            swapchain_format.into(),
+            &pipeline_layout,
+            sample_count,
+            include_shader!("render_model.wgsl"),
+        );
+        let device_models = DeviceModels::new(shared_texture_bind_group_layout.clone());
+
         let ftri_equirectangular_360 = FullscreenTriangle::create(
             &gpu.device,
             swapchain_format.into(),
             &pipeline_layout,
+            sample_count,
             include_shader!("proj_equirectangular_360.wgsl"),
         );
         let ftri_equirectangular_180 = FullscreenTriangle::create(
             &gpu.device,
             swapchain_format.into(),
             &pipeline_layout,
+            sample_count,
             include_shader!("proj_equirectangular_180.wgsl"),
         );
         let ftri_fisheye_180 = FullscreenTriangle::create(
             &gpu.device,
             swapchain_format.into(),
             &pipeline_layout,
+            sample_count,
             include_shader!("proj_fisheye_180.wgsl"),
         );
+        let sphere_equirectangular_360 = SphereMesh::create(
+            &gpu.device,
+            swapchain_format.into(),
+            &pipeline_layout,
+            sample_count,
+            include_shader!("mesh_equirectangular_360.wgsl"),
+            2.0 * PI,
+        );
+        let sphere_equirectangular_180 = SphereMesh::create(
+            &gpu.device,
+            swapchain_format.into(),
+            &pipeline_layout,
+            sample_count,
+            include_shader!("mesh_equirectangular_180.wgsl"),
+            PI,
+        );
         let ftri_cubemap = FullscreenTriangle::create(
             &gpu.device,
             swapchain_format.into(),
             &pipeline_layout,
+            sample_count,
             include_shader!("proj_equiangular_cubemap.wgsl"),
         );
+        let ftri_comfort_vignette = FullscreenTriangle::create(
+            &gpu.device,
+            wgpu::ColorTargetState {
+                format: swapchain_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            &pipeline_layout,
+            sample_count,
+            include_shader!("comfort_vignette.wgsl"),
+        );
+        let mut sample_counts = vec![
+            ("flat video pipeline", tquad_shared_tex.sample_count),
+            ("imgui pipeline", tquad_imgui.sample_count),
+            ("render model pipeline", render_model_pipeline.sample_count),
+            ("equirectangular 360 pipeline", ftri_equirectangular_360.sample_count),
+            ("equirectangular 180 pipeline", ftri_equirectangular_180.sample_count),
+            ("fisheye 180 pipeline", ftri_fisheye_180.sample_count),
+            (
+                "equirectangular 360 sphere pipeline",
+                sphere_equirectangular_360.sample_count,
+            ),
+            (
+                "equirectangular 180 sphere pipeline",
+                sphere_equirectangular_180.sample_count,
+            ),
+            ("equiangular cubemap pipeline", ftri_cubemap.sample_count),
+            ("comfort vignette pipeline", ftri_comfort_vignette.sample_count),
+        ];
+        if let Some(vr_info) = &vr_info {
+            sample_counts.push(("left eye target", vr_info.left_eye.sample_count));
+            sample_counts.push(("right eye target", vr_info.right_eye.sample_count));
+        }
+        if let Err(e) = check_sample_counts(sample_count, &sample_counts) {
+            panic!("{}", e);
+        }
         //---------------------------------------------------------------------------------
 
-        let mut filedb = FileDB::load();
-        let imgui_file_browser = ImguiFileBrowser::new(&mut filedb);
+        let filedb = FileDB::load(config_syncer.get());
+        let imgui_file_browser = ImguiFileBrowser::new(config_syncer.get());
         let imgui_general = General::new();
         let cam_quat = Quat::IDENTITY;
         let cam_pos = Vec3::new(0.0, 0.0, 0.0);
-        let proj_mat = Mat4::perspective_lh(90f32.to_radians(), w as f32 / h as f32, 0.01, 100.0);
+        let proj_mat = companion_proj_mat(config_syncer.get(), w, h);
         let view_mat = Mat4::from_rotation_translation(cam_quat.inverse(), -cam_pos);
-        let camera_state = CameraState::from_proj_and_view(proj_mat, view_mat, Mat4::IDENTITY, 0, None, &imgui_general);
+        let camera_state =
+            CameraState::from_proj_and_view(proj_mat, view_mat, Mat4::IDENTITY, 0, Mode::Mono, None, &imgui_general);
         let time = Instant::now();
         let sdl_event_pump = sdl_context.event_pump().unwrap();
         let swap_z = Mat4::from_scale(Vec3::new(1.0, 1.0, -1.0));
 
         Global {
-            action_bin: ActionBin::create(),
+            action_bin,
+            status_writer: StatusWriter::new(),
             suboptimal: false,
+            sample_count,
             filedb,
             async_size: (None, None),
+            video_downscale: None,
             current_file_path: None,
             current_file_duration: None,
+            timeline: Timeline::default(),
             current_file_key: None,
             current_file_tracks: None,
+            volume_fade: None,
+            volume_max: 130.0,
+            volume_changed_by_user: false,
+            properties_changed_by_user: HashSet::new(),
+            undo_history: UndoHistory::new(),
+            current_file_tracks_restored: false,
+            current_file_detect_stereo_mode: false,
+            toasts,
+            frame_stats: FrameStats::new(),
             config_syncer,
-            egl,
+            controls,
+            egl: ManuallyDrop::new(egl),
             vr,
             sdl_context,
             sdl_video_subsystem,
+            sdl_controller_subsystem,
+            gamepads: Vec::new(),
+            gamepad_axes: [0.0; 4],
             sdl_window,
             sdl_event_pump,
             imgui,
-            imgui_renderer,
             imgui_file_browser,
             imgui_general,
             mpv,
-            mpv_render,
-            gpu,
-            shared_tex,
-            depth_view,
-            lines_pipeline,
-            black_texture_bgrp,
-            camera_bgrp,
-            lines_buf,
-            camera_state_uniform_buf,
-            vscreen,
-            tquad_shared_tex,
-            tquad_imgui,
-            ftri_equirectangular_360,
-            ftri_equirectangular_180,
-            ftri_fisheye_180,
-            ftri_equiangular_cubemap: ftri_cubemap,
-            vr_info,
+            mpv_render: ManuallyDrop::new(mpv_render),
+            gpu: ManuallyDrop::new(gpu),
+            shared_tex: ManuallyDrop::new(shared_tex),
+            shared_output,
+            gpu_res: ManuallyDrop::new(GpuResources {
+                vr_info,
+                vscreen,
+                tquad_shared_tex,
+                tquad_imgui,
+                ui_mesh,
+                render_model_pipeline,
+                device_models,
+                imgui_renderer,
+                ftri_equirectangular_360,
+                ftri_equirectangular_180,
+                ftri_fisheye_180,
+                ftri_equiangular_cubemap: ftri_cubemap,
+                ftri_comfort_vignette,
+                sphere_equirectangular_360,
+                sphere_equirectangular_180,
+                camera_state_uniform_buf,
+                lines_buf,
+                laser_buf,
+                camera_bgrp,
+                lines_pipeline,
+                depth_view,
+                msaa_view,
+                black_texture_bgrp,
+            }),
+            laser: None,
+            laser_trigger: false,
+            paused_by_focus_loss: false,
+            window_minimized: false,
+            hmd_tracking_lost: false,
+            should_render_eyes: true,
+            video_frame_changed: true,
+            last_eye_frame: None,
+            force_mono: false,
+            vr_dashboard_active: false,
+            vr_hmd_idle: false,
+            comfort_vignette: 0.0,
+            comfort_vignette_prev_view: Mat4::IDENTITY,
+            fov_reduction: 0.0,
             camera_state,
             swap_z,
             ui_origin: Mat4::IDENTITY,
+            ui_pinned: false,
+            screen_origin: None,
+            idle_since: None,
+            idle_power_saving_view: None,
             world_origin: Mat4::IDENTITY,
             proj_mat,
             view_mat,
@@ -587,6 +1118,9 @@ impl Global {
             move_forward: false,
             move_backward: false,
             move_right: false,
+            flat_panning: false,
+            flat_pan_moved: false,
+            flat_placing: false,
             move_left: false,
             cam_pos,
             cam_quat,
@@ -597,11 +1131,28 @@ impl Global {
             time,
             is_running: true,
             is_gui: false,
+            is_mini_ui: false,
+            is_quick_projection: false,
+        }
+    }
+
+    fn is_mirroring_vr(&self) -> bool {
+        self.gpu_res.vr_info.is_some() && self.config_syncer.get().companion_mirror_vr
+    }
+
+    // mirror uses the same predicted HMD pose as the left eye, companion camera is not involved at all
+    fn companion_view_mat(&self) -> Mat4 {
+        match &self.gpu_res.vr_info {
+            Some(vr_info) if self.config_syncer.get().companion_mirror_vr => {
+                let world_scale = self.config_syncer.get().world_scale;
+                scale_eye_to_head_mat(vr_info.left_eye_to_head_mat, world_scale) * self.swap_z * vr_info.hmd_mat
+            }
+            _ => self.view_mat,
         }
     }
 
     fn current_camera_mat(&self) -> Mat4 {
-        if let Some(vr_info) = &self.vr_info {
+        if let Some(vr_info) = &self.gpu_res.vr_info {
             vr_info.hmd_mat
         } else {
             Mat4::from_quat(self.cam_quat.inverse()) * Mat4::from_translation(-self.cam_pos)
@@ -611,6 +1162,7 @@ impl Global {
     pub fn main_loop(&mut self) {
         self.update_delta();
         self.update_mpv();
+        self.update_volume_fade();
         self.update_imgui();
         if self.is_per_sec_update {
             self.per_second_update();
@@ -619,24 +1171,36 @@ impl Global {
             self.fast_update();
         }
 
-        self.before_vk_render();
+        if self.is_render_suspended() {
+            std::thread::sleep(SUSPENDED_FRAME_SLEEP);
+        } else {
+            self.update_comfort_vignette();
+            self.update_power_saving();
+            self.before_vk_render();
 
-        let frame = self.vk_render();
+            let frame = self.vk_render();
 
-        self.after_vk_render();
-        self.vr_present();
-        self.vk_present(frame);
-        self.gl_render();
+            self.after_vk_render();
+            self.vr_present();
+            self.vk_present(frame);
+            self.gl_render();
+        }
 
         self.handle_sdl2_events();
+        self.handle_vr_events();
         self.handle_action_bin();
 
-        if self.suboptimal {
+        let present_mode = self.config_syncer.get().present_mode.wgpu();
+        if self.suboptimal || present_mode != self.surface_config.present_mode {
+            if present_mode != self.surface_config.present_mode {
+                self.surface_config.present_mode = present_mode;
+                log_present_mode(present_mode, &self.gpu.present_modes);
+            }
             self.suboptimal = false;
             self.gpu.surface.configure(&self.gpu.device, &self.surface_config);
         }
 
-        self.wait_get_hmd_pose();
+        self.wait_get_poses();
         self.is_per_sec_update = false;
         self.is_fast_update = false;
     }
@@ -645,6 +1209,7 @@ impl Global {
         let now = Instant::now();
         self.delta = now - self.time;
         self.time = now;
+        self.frame_stats.push(self.delta);
 
         self.delta_accum_secs += self.delta.as_secs_f32();
         if self.delta_accum_secs > 1.0 {
@@ -660,13 +1225,70 @@ impl Global {
     }
 
     pub fn update_imgui(&mut self) {
-        self.vscreen.imgui_prepare_frame(&mut self.imgui);
+        self.gpu_res.vscreen.imgui_prepare_frame(&mut self.imgui);
+        self.update_gamepad_nav();
+    }
+
+    fn is_gamepad_nav_active(&self) -> bool {
+        self.config_syncer.get().gamepad_ui_navigation && self.is_ui_visible() && !self.gamepads.is_empty()
+    }
+
+    // nav inputs are levels rather than events, so they are polled every frame
+    fn update_gamepad_nav(&mut self) {
+        let active = self.is_gamepad_nav_active();
+        let io = self.imgui.io_mut();
+        io.config_flags.set(imgui::ConfigFlags::NAV_ENABLE_GAMEPAD, active);
+        io.backend_flags.set(imgui::BackendFlags::HAS_GAMEPAD, active);
+        for input in imgui::NavInput::VARIANTS {
+            io[input] = 0.0;
+        }
+        if !active {
+            return;
+        }
+        for gamepad in &self.gamepads {
+            for (button, input) in GAMEPAD_NAV_BUTTONS {
+                if gamepad.button(*button) {
+                    io[*input] = 1.0;
+                }
+            }
+        }
     }
 
     fn reset_current_file(&mut self) {
         self.current_file_path = None;
         self.current_file_duration = None;
+        self.timeline = Timeline::default();
+        self.volume_changed_by_user = false;
+        self.properties_changed_by_user.clear();
         self.current_file_tracks = None;
+        self.undo_history.clear();
+        self.current_file_tracks_restored = false;
+        self.current_file_detect_stereo_mode = false;
+        self.force_mono = false;
+        self.imgui_general.force_mono = false;
+        self.imgui_general.video_bitrate = None;
+        self.imgui_general.paused_for_cache = false;
+        self.imgui_general.cache_buffering_state = 0;
+        self.imgui_general.demuxer_cache_duration = None;
+        self.frame_stats.reset_mpv_stats();
+        self.update_window_title();
+    }
+
+    fn update_window_title(&mut self) {
+        let title = match &self.current_file_path {
+            Some(path) => {
+                let name = Path::new(path)
+                    .file_name()
+                    .unwrap_or(OsStr::new(path))
+                    .to_string_lossy();
+                let state = cond!(self.imgui_general.playing, "playing", "paused");
+                format!("{} [{}] - vrmp", name, state)
+            }
+            None => "vrmp".to_owned(),
+        };
+        if let Err(e) = self.sdl_window.set_title(&title) {
+            log::error!("failed setting window title: {}", e);
+        }
     }
 
     pub fn update_mpv(&mut self) {
@@ -674,13 +1296,23 @@ impl Global {
             match ev {
                 libmpv::Event::VideoReconfig => {
                     self.async_size = (None, None);
-                    self.mpv.get_size_async();
+                    self.mpv.get_video_params_async();
                 }
                 libmpv::Event::EndFile => {
                     self.reset_current_file();
                 }
+                libmpv::Event::EndFileError(e) => {
+                    log::error!("mpv failed playing file: {}", e);
+                    self.notify(format!("Failed playing file: {}", e));
+                }
                 libmpv::Event::FileLoaded => {
                     self.reset_current_file();
+                    if self.config_syncer.get().reset_origin_on_file_load {
+                        self.dispatch_action(Action::ResetWorldOrigin);
+                    }
+                    if self.is_fullscreen_on_play() {
+                        self.set_fullscreen(true);
+                    }
                     self.mpv.get_path_async();
                     self.mpv.get_video_params_async();
                     self.mpv.get_track_list_async();
@@ -692,122 +1324,588 @@ impl Global {
                     "sid" => self.mpv.get_sid_async(),
                     "hwdec" => self.mpv.get_hwdec_async(),
                     "hwdec-current" => self.mpv.get_hwdec_current_async(),
+                    "brightness" => self.mpv.get_brightness_async(),
+                    "contrast" => self.mpv.get_contrast_async(),
+                    "gamma" => self.mpv.get_gamma_async(),
+                    "saturation" => self.mpv.get_saturation_async(),
+                    "deinterlace" => self.mpv.get_deinterlace_async(),
+                    "sub-scale" => self.mpv.get_sub_scale_async(),
+                    "sub-pos" => self.mpv.get_sub_pos_async(),
+                    "speed" => self.mpv.get_speed_async(),
+                    "volume" => self.mpv.get_volume_async(),
+                    "volume-max" => self.mpv.get_volume_max_async(),
+                    "loop-file" => self.mpv.get_loop_file_async(),
+                    "idle-active" => self.mpv.get_idle_active_async(),
+                    "estimated-vf-fps" => self.mpv.get_estimated_vf_fps_async(),
+                    "video-bitrate" => self.mpv.get_video_bitrate_async(),
+                    "paused-for-cache" => self.mpv.get_paused_for_cache_async(),
+                    "cache-buffering-state" => self.mpv.get_cache_buffering_state_async(),
+                    "demuxer-cache-duration" => self.mpv.get_demuxer_cache_duration_async(),
+                    "frame-drop-count" => self.mpv.get_frame_drop_count_async(),
+                    "time-pos" => self.mpv.get_time_pos_async(),
+                    "duration" => self.mpv.get_duration_async(),
                     _ => {}
                 },
                 libmpv::Event::Property(p) => match (p.name.as_ref(), p.value) {
                     ("hwdec-current", libmpv::PropertyValue::String(v)) => self.imgui_general.hwdec_current = v,
                     ("hwdec", libmpv::PropertyValue::String(v)) => self.imgui_general.hwdec = v,
+                    // mpv is idle when playback stops and there is nothing else to play
+                    ("idle-active", libmpv::PropertyValue::Bool(true)) => {
+                        if self.is_fullscreen_on_play() {
+                            self.set_fullscreen(false);
+                        }
+                    }
+                    // "inf", "no" or a number of remaining repeats
+                    ("loop-file", libmpv::PropertyValue::String(v)) => {
+                        let loop_file = v != "no";
+                        self.imgui_general.loop_file = loop_file;
+                        self.save_user_property("loop-file", |d| d.loop_file = loop_file);
+                    }
                     ("path", libmpv::PropertyValue::String(v)) => self.on_mpv_file_loaded(v),
-                    ("width", libmpv::PropertyValue::I64(v)) => self.async_size.0 = Some(v as u32),
-                    ("height", libmpv::PropertyValue::I64(v)) => self.async_size.1 = Some(v as u32),
-                    ("duration", libmpv::PropertyValue::I64(v)) => self.on_mpv_duration_changed(v as u32),
+                    ("duration", libmpv::PropertyValue::F64(v)) => self.on_mpv_duration_changed(v),
                     ("percent-pos", libmpv::PropertyValue::F64(v)) => self.on_mpv_percent_pos_change(v),
+                    ("time-pos", libmpv::PropertyValue::F64(v)) => self.on_mpv_time_pos_change(v),
                     ("vid", libmpv::PropertyValue::I64(v)) => {
                         if let Some(t) = &mut self.current_file_tracks {
                             t.vid = v;
                         }
+                        self.save_track_selection(|d| d.vid = Some(v));
                     }
                     ("aid", libmpv::PropertyValue::I64(v)) => {
                         if let Some(t) = &mut self.current_file_tracks {
                             t.aid = v;
                         }
+                        self.save_track_selection(|d| d.aid = Some(v));
                     }
                     ("sid", libmpv::PropertyValue::I64(v)) => {
                         if let Some(t) = &mut self.current_file_tracks {
                             t.sid = v;
                         }
+                        self.save_track_selection(|d| d.sid = Some(v));
+                    }
+                    ("brightness", libmpv::PropertyValue::I64(v)) => {
+                        self.imgui_general.brightness = v;
+                        self.save_user_property("brightness", |d| d.brightness = v);
+                    }
+                    ("contrast", libmpv::PropertyValue::I64(v)) => {
+                        self.imgui_general.contrast = v;
+                        self.save_user_property("contrast", |d| d.contrast = v);
+                    }
+                    ("gamma", libmpv::PropertyValue::I64(v)) => {
+                        self.imgui_general.gamma = v;
+                        self.save_user_property("gamma", |d| d.gamma = v);
+                    }
+                    ("saturation", libmpv::PropertyValue::I64(v)) => {
+                        self.imgui_general.saturation = v;
+                        self.save_user_property("saturation", |d| d.saturation = v);
+                    }
+                    ("deinterlace", libmpv::PropertyValue::Bool(v)) => {
+                        self.imgui_general.deinterlace = v;
+                        self.save_user_property("deinterlace", |d| d.deinterlace = v);
+                    }
+                    ("video-params", libmpv::PropertyValue::Node(n)) => self.on_mpv_video_params(&n),
+                    ("speed", libmpv::PropertyValue::F64(v)) => {
+                        self.imgui_general.speed = v;
+                        self.save_user_property("speed", |d| d.speed = v);
+                    }
+                    ("sub-scale", libmpv::PropertyValue::F64(v)) => {
+                        self.imgui_general.sub_scale = v;
+                        self.save_user_property("sub-scale", |d| d.sub_scale = Some(v));
+                    }
+                    ("sub-pos", libmpv::PropertyValue::I64(v)) => {
+                        self.imgui_general.sub_pos = v;
+                        self.save_user_property("sub-pos", |d| d.sub_pos = Some(v));
+                    }
+                    ("volume", libmpv::PropertyValue::F64(v)) => {
+                        self.imgui_general.volume = v;
+                        // fade in and per-file restore change volume as well, those are not saved
+                        if self.volume_changed_by_user {
+                            self.volume_changed_by_user = false;
+                            self.save_video_adjustment(|d| d.volume = Some(v));
+                        }
                     }
+                    ("volume-max", libmpv::PropertyValue::F64(v)) => self.volume_max = v,
+                    ("estimated-vf-fps", libmpv::PropertyValue::F64(v)) => self.frame_stats.estimated_vf_fps = Some(v),
+                    ("video-bitrate", libmpv::PropertyValue::F64(v)) => self.imgui_general.video_bitrate = Some(v),
+                    ("paused-for-cache", libmpv::PropertyValue::Bool(v)) => self.imgui_general.paused_for_cache = v,
+                    ("cache-buffering-state", libmpv::PropertyValue::I64(v)) => {
+                        self.imgui_general.cache_buffering_state = v
+                    }
+                    ("demuxer-cache-duration", libmpv::PropertyValue::F64(v)) => {
+                        self.imgui_general.demuxer_cache_duration = Some(v)
+                    }
+                    ("frame-drop-count", libmpv::PropertyValue::I64(v)) => self.frame_stats.frame_drop_count = Some(v),
                     ("track-list", libmpv::PropertyValue::Node(n)) => {
                         self.current_file_tracks = Some(Tracks::parse(&n));
+                        self.restore_track_selections();
                         self.mpv.get_vid_async();
                         self.mpv.get_aid_async();
                         self.mpv.get_sid_async();
                     }
-                    ("pause", libmpv::PropertyValue::Bool(v)) => self.imgui_general.playing = !v, // this one is purely visual
+                    ("pause", libmpv::PropertyValue::Bool(v)) => {
+                        self.imgui_general.playing = !v; // this one is purely visual
+                        self.update_window_title();
+                    }
                     _ => {}
                 },
             }
         }
         if let (Some(w), Some(h)) = self.async_size {
+            let (w, h) = self.fit_video_size(w, h);
             self.shared_tex.request_resize(w, h);
             self.async_size = (None, None);
         }
         self.mpv_render.update_maybe();
     }
 
+    // Video bigger than max_video_size is downscaled by mpv filter, so that shared texture and the frames after the
+    // filter fit into VRAM of low-end GPUs. The filter is labeled and replaced or removed as the video size changes.
+    // It's a software filter, mpv copies hardware decoded frames back to system memory to feed it, see
+    // Config::max_video_size. Returns the size of the shared texture.
+    fn fit_video_size(&mut self, w: u32, h: u32) -> (u32, u32) {
+        let max = self.config_syncer.get().max_video_size;
+        let fitted = if max != 0 && w.max(h) > max {
+            let scale = max as f64 / w.max(h) as f64;
+            // even dimensions keep chroma subsampled formats happy
+            let even = |v: u32| ((v as f64 * scale / 2.0).round() as u32 * 2).max(2);
+            Some((even(w), even(h)))
+        } else {
+            None
+        };
+        if fitted != self.video_downscale {
+            match fitted {
+                Some((fw, fh)) => {
+                    log::info!("downscaling {}x{} video to {}x{}", w, h, fw, fh);
+                    let filter = format!("@vrmp-downscale:scale=w={}:h={}", fw, fh);
+                    if self.video_downscale.is_some() {
+                        self.mpv.command_async(&["vf", "remove", "@vrmp-downscale"]);
+                    }
+                    self.mpv.command_async(&["vf", "add", filter.as_str()]);
+                }
+                None => self.mpv.command_async(&["vf", "remove", "@vrmp-downscale"]),
+            }
+            self.video_downscale = fitted;
+        }
+        fitted.unwrap_or((w, h))
+    }
+
+    // Applies track selections saved for the current file, ids which are not in the track list are ignored.
+    fn restore_track_selections(&mut self) {
+        if self.current_file_tracks_restored {
+            return;
+        }
+        self.current_file_tracks_restored = true;
+        let (tracks, fdata) = match (
+            &self.current_file_tracks,
+            self.current_file_key.and_then(|k| self.filedb.get_file(k)),
+        ) {
+            (Some(tracks), Some(fdata)) => (tracks, fdata),
+            _ => return,
+        };
+        let selections = [
+            ("vid", fdata.vid, &tracks.video),
+            ("aid", fdata.aid, &tracks.audio),
+            ("sid", fdata.sid, &tracks.sub),
+        ];
+        for (name, id, list) in selections {
+            let id = match id {
+                Some(id) => id,
+                None => continue,
+            };
+            if list.iter().any(|t| t.id == id) {
+                let id = id.to_string();
+                self.mpv.command_async(&["set", name, id.as_str()]);
+            } else {
+                log::info!("saved {} {} is not in the track list, ignoring", name, id);
+            }
+        }
+    }
+
+    fn save_track_selection<F: FnOnce(&mut FileData)>(&mut self, f: F) {
+        if !self.current_file_tracks_restored {
+            return;
+        }
+        if let Some(key) = self.current_file_key {
+            f(self.filedb.get_file_mut(key, self.config_syncer.get()));
+        }
+    }
+
+    fn save_video_adjustment<F: FnOnce(&mut FileData)>(&mut self, f: F) {
+        if let Some(key) = self.current_file_key {
+            f(self.filedb.get_file_mut(key, self.config_syncer.get()));
+        }
+    }
+
+    // Values set by restore_video_adjustments and late replies for the previous file are observed as well, those
+    // are not saved.
+    fn save_user_property<F: FnOnce(&mut FileData)>(&mut self, name: &str, f: F) {
+        if self.properties_changed_by_user.remove(name) {
+            self.save_video_adjustment(f);
+        }
+    }
+
+    // Color adjustments, deinterlacing, speed, looping and volume are global in mpv, so they are set on every file
+    // load, to defaults if nothing is saved. Subtitle placement is set only if saved.
+    fn restore_video_adjustments(&mut self) {
+        let (values, deinterlace, speed, sub_scale, sub_pos, loop_file) =
+            match self.current_file_key.and_then(|k| self.filedb.get_file(k)) {
+                Some(d) => (
+                    [d.brightness, d.contrast, d.gamma, d.saturation],
+                    d.deinterlace,
+                    d.speed,
+                    d.sub_scale,
+                    d.sub_pos,
+                    d.loop_file,
+                ),
+                None => ([0; 4], false, 1.0, None, None, false),
+            };
+        for (name, v) in ["brightness", "contrast", "gamma", "saturation"]
+            .into_iter()
+            .zip(values)
+        {
+            let v = v.clamp(-100, 100).to_string();
+            self.mpv.command_async(&["set", name, v.as_str()]);
+        }
+        self.mpv
+            .command_async(&["set", "deinterlace", cond!(deinterlace, "yes", "no")]);
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED).to_string();
+        self.mpv.command_async(&["set", "speed", speed.as_str()]);
+        // file local options go back to mpv's own values (e.g. from mpv.conf) once the file ends
+        if let Some(sub_scale) = sub_scale {
+            let sub_scale = sub_scale.clamp(0.5, 4.0).to_string();
+            self.mpv
+                .command_async(&["set", "file-local-options/sub-scale", sub_scale.as_str()]);
+        }
+        if let Some(sub_pos) = sub_pos {
+            let sub_pos = sub_pos.clamp(0, 150).to_string();
+            self.mpv
+                .command_async(&["set", "file-local-options/sub-pos", sub_pos.as_str()]);
+        }
+        self.mpv
+            .command_async(&["set", "loop-file", cond!(loop_file, "inf", "no")]);
+        let volume = self.current_file_volume().to_string();
+        self.mpv.command_async(&["set", "volume", volume.as_str()]);
+    }
+
+    // saved volume of the current file or the default one
+    fn current_file_volume(&self) -> f64 {
+        self.current_file_key
+            .and_then(|k| self.filedb.get_file(k))
+            .and_then(|d| d.volume)
+            .unwrap_or(self.config_syncer.get().default_volume)
+            .clamp(0.0, self.volume_max)
+    }
+
     pub fn on_mpv_file_loaded(&mut self, v: String) {
-        if let Some(key) = load_file_size_and_hash(&v) {
-            if let Err(e) = self.filedb.preload_file(key.0, key.1) {
+        // streams, generated sources (e.g. A/V sync test) and other non-local paths have no per-file data
+        let is_file = Path::new(&v).is_file();
+        self.current_file_key = if is_file { load_file_key(&v) } else { None };
+        if let Some(key) = self.current_file_key {
+            if let Err(e) = self.filedb.preload_played_file(key) {
                 log::error!("failed preloading file: {}", e);
             }
-            self.current_file_key = Some(key);
+            self.current_file_detect_stereo_mode =
+                self.config_syncer.get().auto_detect_stereo_mode && self.filedb.get_file(key).is_none();
+            self.filedb.mark_file_played(key, &v, self.config_syncer.get());
+            if let Some(yaw_pitch) = self.filedb.get_file(key).and_then(|d| d.saved_orientation) {
+                self.world_origin = oriented_origin(self.current_camera_mat(), yaw_pitch);
+                self.screen_origin = None;
+            }
+        }
+        self.restore_video_adjustments();
+        self.start_volume_fade();
+        if is_file {
+            self.config_syncer.get_mut().push_recent_file(PathBuf::from(&v));
         }
         self.current_file_path = Some(v);
+        self.update_window_title();
+    }
+
+    fn start_volume_fade(&mut self) {
+        if self.config_syncer.get().volume_fade_in_secs <= 0.0 {
+            return;
+        }
+        let target = self.current_file_volume();
+        self.mpv.command_async(&["set", "volume", "0"]);
+        self.volume_fade = Some(VolumeFade {
+            start: Instant::now(),
+            target,
+            last: 0,
+        });
     }
 
-    pub fn on_mpv_duration_changed(&mut self, v: u32) {
-        self.imgui_general.duration = v;
-        self.current_file_duration = Some(v);
+    // volume is set only when its integer value changes, not to spam mpv with commands every frame
+    pub fn update_volume_fade(&mut self) {
+        let fade = match &mut self.volume_fade {
+            Some(v) => v,
+            None => return,
+        };
+        let secs = self.config_syncer.get().volume_fade_in_secs;
+        let t = cond!(secs > 0.0, fade.start.elapsed().as_secs_f32() / secs, 1.0);
+        let volume = if t >= 1.0 {
+            fade.target
+        } else {
+            (fade.target * t as f64).floor()
+        };
+        if volume as i64 != fade.last || t >= 1.0 {
+            fade.last = volume as i64;
+            let volume = volume.to_string();
+            self.mpv.command_async(&["set", "volume", volume.as_str()]);
+        }
+        if t >= 1.0 {
+            self.volume_fade = None;
+        }
+    }
+
+    // video-params are requested on file load and on every video reconfig, stereo-in may show up only after the first
+    // frame is decoded
+    pub fn on_mpv_video_params(&mut self, n: &libmpv::Node) {
+        // Shared texture gets display size (dw/dh, sample aspect applied), not the storage size. mpv keeps aspect
+        // when it renders into the texture and the flat screen takes its aspect from the texture, so anamorphic video
+        // is not stretched and doesn't get black bars baked in. These are decoder params, filters (see
+        // fit_video_size) don't affect them.
+        let param = |name: &str| n.as_map().and_then(|m| m.get(name)).and_then(|v| v.as_i64()).copied();
+        if let (Some(dw), Some(dh)) = (param("dw"), param("dh")) {
+            self.async_size = (Some(dw as u32), Some(dh as u32));
+        }
+
+        if !self.current_file_detect_stereo_mode {
+            return;
+        }
+        let stereo_in = match n.as_map().and_then(|m| m.get("stereo-in")).and_then(|v| v.as_string()) {
+            Some(v) => v,
+            None => return,
+        };
+        self.current_file_detect_stereo_mode = false;
+        if let (Some(key), Some(mode)) = (self.current_file_key, Mode::from_mpv_stereo_in(stereo_in)) {
+            log::info!("detected stereo mode from video metadata: {}", stereo_in);
+            self.filedb.get_file_mut(key, self.config_syncer.get()).mode = mode;
+        }
+    }
+
+    pub fn on_mpv_duration_changed(&mut self, v: f64) {
+        self.timeline.duration_secs = v;
+        self.current_file_duration = Some(v as u32);
     }
 
     pub fn on_mpv_percent_pos_change(&mut self, v: f64) {
         self.imgui_general.percent_pos = v;
-        if let Some(key) = self.current_file_key {
-            let e = self.filedb.get_file_mut(key);
-            e.mark_as_seen(v);
+    }
+
+    pub fn on_mpv_time_pos_change(&mut self, v: f64) {
+        let prev = self.timeline.pos_secs;
+        self.timeline.pos_secs = v;
+        let duration = self.timeline.duration_secs;
+        if let (Some(key), true) = (self.current_file_key, duration > 0.0) {
+            // small forward step is regular playback, gap between the updates was watched too, seeks mark only the
+            // new position
+            let from = cond!(v >= prev && v - prev <= 2.0, prev, v);
+            let e = self.filedb.get_file_mut(key, self.config_syncer.get());
+            e.mark_time_span_as_seen(from, v, duration);
         }
     }
 
     pub fn per_second_update(&mut self) {
-        // I'm not sure if duration is available right after "FILE_LOADED", I should probably experiment with this
-        self.mpv.get_duration_async();
+        if let Some(vr_info) = &mut self.gpu_res.vr_info {
+            let scale = cond!(self.idle_power_saving_view.is_some(), IDLE_SUPERSAMPLE_SCALE, 1.0);
+            vr_info.set_supersample_factor(&self.gpu.device, self.config_syncer.get().supersample_factor * scale);
+        }
 
         self.config_syncer.save_maybe();
         self.filedb.save_to_disk_maybe();
+        self.status_writer.write_maybe(Status {
+            path: self.current_file_path.clone(),
+            percent_pos: self.imgui_general.percent_pos,
+            duration: self.current_file_duration,
+            playing: self.imgui_general.playing,
+        });
     }
 
     pub fn fast_update(&mut self) {
         self.mpv.get_percent_pos_async();
+        self.imgui_file_browser.update(&mut self.filedb);
+    }
+
+    // vignette intensity follows how fast the view moves relative to the world: head motion and world origin changes
+    pub fn update_comfort_vignette(&mut self) {
+        let view = self.current_camera_mat() * self.world_origin;
+        let prev_view = mem::replace(&mut self.comfort_vignette_prev_view, view);
+        let config = self.config_syncer.get();
+        let dt = self.delta.as_secs_f32();
+        self.fov_reduction = (self.fov_reduction - dt / config.comfort_fov_reduction_secs.max(0.01)).max(0.0);
+        if !config.comfort_vignette || self.vr.is_none() || dt <= 0.0 {
+            self.comfort_vignette = 0.0;
+            return;
+        }
+        let (_, rot, tr) = (view * prev_view.inverse()).to_scale_rotation_translation();
+        // moving 1 m/s is treated as turning 90°/s
+        let speed = (rot.angle_between(Quat::IDENTITY).to_degrees() / dt).max(tr.length() / dt * 90.0);
+        // slow motion (below a quarter of full speed) doesn't trigger vignette at all
+        let full_speed = config.comfort_vignette_speed;
+        let target = ((speed - full_speed * 0.25) / (full_speed * 0.75)).clamp(0.0, 1.0)
+            * config.comfort_vignette_strength.clamp(0.0, 1.0);
+        // kicks in immediately, fades out in about half a second
+        self.comfort_vignette = target.max(self.comfort_vignette - dt * 2.0);
+    }
+
+    // Paused VR playback lowers eye resolution after a while, the picture is static, so it's mostly the UI and the
+    // edges which get blurrier. Restored on resume, when UI is shown or once the head turns away.
+    pub fn update_power_saving(&mut self) {
+        let config = self.config_syncer.get();
+        let idle =
+            config.idle_power_saving && self.vr.is_some() && !self.imgui_general.playing && !self.is_ui_visible();
+        let was_saving = self.idle_power_saving_view.is_some();
+        if !idle {
+            self.idle_since = None;
+            self.idle_power_saving_view = None;
+        } else {
+            let since = *self.idle_since.get_or_insert_with(Instant::now);
+            let view = self.current_camera_mat();
+            match self.idle_power_saving_view {
+                None if since.elapsed().as_secs_f32() >= config.idle_power_saving_secs => {
+                    self.idle_power_saving_view = Some(view);
+                }
+                Some(saving_view) => {
+                    let (_, rot, _) = (view * saving_view.inverse()).to_scale_rotation_translation();
+                    if rot.angle_between(Quat::IDENTITY).to_degrees() > IDLE_WAKE_UP_DEGREES {
+                        // looking around while paused, wait for another idle period
+                        self.idle_power_saving_view = None;
+                        self.idle_since = Some(Instant::now());
+                    }
+                }
+                None => {}
+            }
+        }
+        let saving = self.idle_power_saving_view.is_some();
+        if saving != was_saving {
+            log::info!("idle power saving {}", cond!(saving, "on", "off"));
+            // applied right away instead of waiting for per_second_update
+            if let Some(vr_info) = &mut self.gpu_res.vr_info {
+                let scale = cond!(saving, IDLE_SUPERSAMPLE_SCALE, 1.0);
+                vr_info.set_supersample_factor(&self.gpu.device, self.config_syncer.get().supersample_factor * scale);
+            }
+        }
     }
 
     pub fn before_vk_render(&mut self) {
+        // cheap enough to do every frame, this way config changes apply immediately
+        self.proj_mat = companion_proj_mat(
+            self.config_syncer.get(),
+            self.surface_config.width,
+            self.surface_config.height,
+        );
+        if let (Some(vr_info), Some(vr)) = (&mut self.gpu_res.vr_info, &self.vr) {
+            vr_info.set_clip_planes(vr, self.config_syncer.get().clip_planes());
+        }
+        let gpu_res = &mut *self.gpu_res;
+        gpu_res.ui_mesh.update(
+            &self.gpu.queue,
+            ui_panel_size(self.config_syncer.get(), &gpu_res.vscreen).x,
+            ui_curvature_radius(self.config_syncer.get()),
+        );
         self.shared_tex.resize_maybe(
             &self.gpu.ash_instance,
             &self.gpu.ash_device,
             self.gpu.vk_physical_device,
             &self.gpu.device,
+            &mut self.gpu.cmd_pool,
+            self.gpu.vk_queue,
         );
         self.shared_tex.before_vk(&self.gpu.ash_device, self.gpu.vk_queue);
+
+        if let Some(output) = &mut self.shared_output {
+            // companion output is rendered with companion window depth/msaa attachments, so it has the same size
+            let (w, h) = match (self.config_syncer.get().shared_output, &self.gpu_res.vr_info) {
+                (SharedOutputSource::LeftEye, Some(vr_info)) => (vr_info.left_eye.width, vr_info.left_eye.height),
+                _ => (self.surface_config.width, self.surface_config.height),
+            };
+            output.resize_maybe(
+                &self.gpu.ash_instance,
+                &self.gpu.ash_device,
+                self.gpu.vk_physical_device,
+                &self.gpu.device,
+                w,
+                h,
+            );
+        }
+    }
+
+    // Paused scene with hidden UI and a still head looks exactly the same as the last frame, previous eye textures are
+    // submitted again instead. Anything which might change the picture (new video frame, actions, UI, vignette,
+    // moving head or controllers) renders eyes as usual.
+    fn can_reuse_eye_frames(&self) -> bool {
+        if !self.config_syncer.get().static_frame_skip
+            || self.imgui_general.playing
+            || self.video_frame_changed
+            || self.is_vscreen_visible()
+            || self.laser.is_some()
+            || self.comfort_vignette.max(self.fov_reduction) > 0.0
+        {
+            return false;
+        }
+        match (&self.gpu_res.vr_info, &self.last_eye_frame) {
+            (Some(vr_info), Some(last)) => {
+                let device_poses = self.gpu_res.device_models.drawn_poses();
+                last.eye_width == vr_info.left_eye.width
+                    && is_same_pose(vr_info.hmd_mat, last.hmd_mat)
+                    && device_poses.len() == last.device_poses.len()
+                    && device_poses
+                        .iter()
+                        .zip(&last.device_poses)
+                        .all(|(a, b)| is_same_pose(*a, *b))
+            }
+            _ => false,
+        }
     }
 
     pub fn vk_render(&mut self) -> wgpu::SurfaceTexture {
+        // computed before fdata, which keeps filedb borrowed for the rest of the function
+        let is_vscreen_visible = self.is_vscreen_visible();
+        let companion_view_mat = self.companion_view_mat();
+        let reuse_eye_frames = self.can_reuse_eye_frames();
         let fdata = self.current_file_key.and_then(|k| self.filedb.get_file(k));
         let frame = self.gpu.surface.get_current_texture().unwrap();
         self.suboptimal = frame.suboptimal;
         let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
         let projection = fdata.map(|d| d.projection).unwrap_or(Projection::Flat);
+        let mode = cond!(self.force_mono, Mode::Mono, fdata.map(|d| d.mode).unwrap_or(Mode::Mono));
         let aspect_ratio = fdata.map(|d| d.aspect_ratio).unwrap_or(AspectRatio::One);
-        let flat_distnace = fdata.map(|d| d.flat_distance).unwrap_or(3.0);
-        let flat_scale = fdata.map(|d| d.flat_scale).unwrap_or(3.0);
+        let flat_distnace = fdata
+            .map(|d| d.flat_distance)
+            .unwrap_or(self.config_syncer.get().flat_distance);
+        let flat_scale = fdata
+            .map(|d| d.flat_scale)
+            .unwrap_or(self.config_syncer.get().flat_scale);
+        let flat_offset = fdata.map(|d| d.flat_offset).unwrap_or(0.0);
+        let sphere_mesh = self.config_syncer.get().sphere_mesh;
         let scene = Scene {
             queue: &self.gpu.queue,
             device: &self.gpu.device,
             color: &view,
-            depth: &self.depth_view,
-            camera_bgrp: &self.camera_bgrp,
+            msaa_color: self.gpu_res.msaa_view.as_ref(),
+            depth: &self.gpu_res.depth_view,
+            camera_bgrp: &self.gpu_res.camera_bgrp,
             video_bgrp: cond!(
                 self.shared_tex.is_ready(),
                 &self.shared_tex.vk.bind_group,
-                &self.black_texture_bgrp
+                &self.gpu_res.black_texture_bgrp
             ),
             video: match projection {
-                Projection::Er180 => VideoRenderer::FTri(&self.ftri_equirectangular_180),
-                Projection::Fisheye => VideoRenderer::FTri(&self.ftri_fisheye_180),
-                Projection::Eac => VideoRenderer::FTri(&self.ftri_equiangular_cubemap),
-                Projection::Er360 => VideoRenderer::FTri(&self.ftri_equirectangular_360),
+                Projection::Er180 => cond!(
+                    sphere_mesh,
+                    VideoRenderer::Sphere(&self.gpu_res.sphere_equirectangular_180),
+                    VideoRenderer::FTri(&self.gpu_res.ftri_equirectangular_180)
+                ),
+                Projection::Fisheye => VideoRenderer::FTri(&self.gpu_res.ftri_fisheye_180),
+                Projection::Eac => VideoRenderer::FTri(&self.gpu_res.ftri_equiangular_cubemap),
+                Projection::Er360 => cond!(
+                    sphere_mesh,
+                    VideoRenderer::Sphere(&self.gpu_res.sphere_equirectangular_360),
+                    VideoRenderer::FTri(&self.gpu_res.ftri_equirectangular_360)
+                ),
                 Projection::Flat => VideoRenderer::TQuad(
-                    &self.tquad_shared_tex,
-                    Mat4::from_translation(Vec3::new(0.0, 0.0, flat_distnace))
+                    &self.gpu_res.tquad_shared_tex,
+                    Mat4::from_translation(Vec3::new(flat_offset, 0.0, flat_distnace))
                         * TexturedQuad::scale_for_wh(
                             self.shared_tex.vk.width,
                             self.shared_tex.vk.height,
@@ -816,80 +1914,147 @@ impl Global {
                         ),
                 ),
             },
-            lines_pipeline: &self.lines_pipeline,
-            lines_buf: &self.lines_buf,
-            tquad_imgui: &self.tquad_imgui,
-            vscreen: cond!(self.is_gui, Some(&self.vscreen), None),
+            lines_pipeline: &self.gpu_res.lines_pipeline,
+            lines_buf: &self.gpu_res.lines_buf,
+            laser: self.laser,
+            laser_buf: &self.gpu_res.laser_buf,
+            tquad_imgui: &self.gpu_res.tquad_imgui,
+            ui_mesh: &self.gpu_res.ui_mesh,
+            vscreen: cond!(is_vscreen_visible, Some(&self.gpu_res.vscreen), None),
+            device_models: self
+                .vr
+                .is_some()
+                .then(|| (&self.gpu_res.device_models, &self.gpu_res.render_model_pipeline)),
+            comfort_vignette: None,
+            swap_z: self.swap_z,
             config: self.config_syncer.get(),
             world_origin: self.world_origin,
             debug_matrices: &[
                 self.world_origin,
                 Mat4::IDENTITY,
-                self.vr_info
+                self.gpu_res
+                    .vr_info
                     .as_ref()
                     .map(|i| i.hmd_mat.inverse())
                     .unwrap_or(Mat4::IDENTITY),
             ],
+            screen_origin: self.screen_origin.unwrap_or(self.world_origin),
             ui_origin: self.ui_origin,
         };
 
+        let eye_filter = self.imgui_general.eye_filter;
+
+        let render_eyes = self.should_render_eyes && !reuse_eye_frames;
+        let world_scale = self.config_syncer.get().world_scale;
+        let vignette = self.comfort_vignette.max(self.fov_reduction);
+        let background = self.config_syncer.get().background_color_linear();
+
         // left eye
-        if let Some(vr_info) = &self.vr_info {
+        if let Some(vr_info) = self
+            .gpu_res
+            .vr_info
+            .as_ref()
+            .filter(|_| render_eyes && eye_filter.left())
+        {
             self.camera_state = CameraState::from_proj_and_view(
                 vr_info.left_eye_proj_mat,
-                vr_info.left_eye_to_head_mat * self.swap_z * vr_info.hmd_mat,
+                scale_eye_to_head_mat(vr_info.left_eye_to_head_mat, world_scale) * self.swap_z * vr_info.hmd_mat,
                 self.world_origin,
                 0,
+                mode,
                 fdata,
                 &self.imgui_general,
-            );
+            )
+            .with_vignette(vignette)
+            .with_background(background);
             self.gpu.queue.write_buffer(
-                &self.camera_state_uniform_buf,
+                &self.gpu_res.camera_state_uniform_buf,
                 0,
                 bytemuck::bytes_of(&self.camera_state),
             );
 
             render_scene(&Scene {
+                comfort_vignette: (vignette > 0.0).then(|| &self.gpu_res.ftri_comfort_vignette),
                 color: &vr_info.left_eye.texture_view,
+                msaa_color: vr_info.left_eye.msaa_texture_view.as_ref(),
                 depth: &vr_info.left_eye.depth_texture_view,
                 ..scene
             });
+
+            if self.config_syncer.get().shared_output == SharedOutputSource::LeftEye {
+                if let Some(output) = self.shared_output.as_ref().and_then(|o| o.texture()) {
+                    let mut encoder = self
+                        .gpu
+                        .device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                    encoder.copy_texture_to_texture(
+                        vr_info.left_eye.texture.as_image_copy(),
+                        output.as_image_copy(),
+                        wgpu::Extent3d {
+                            width: vr_info.left_eye.width,
+                            height: vr_info.left_eye.height,
+                            depth_or_array_layers: 1,
+                        },
+                    );
+                    self.gpu.queue.submit(Some(encoder.finish()));
+                }
+            }
         }
 
         // right eye
-        if let Some(vr_info) = &self.vr_info {
+        if let Some(vr_info) = self
+            .gpu_res
+            .vr_info
+            .as_ref()
+            .filter(|_| render_eyes && eye_filter.right())
+        {
             self.camera_state = CameraState::from_proj_and_view(
                 vr_info.right_eye_proj_mat,
-                vr_info.right_eye_to_head_mat * self.swap_z * vr_info.hmd_mat,
+                scale_eye_to_head_mat(vr_info.right_eye_to_head_mat, world_scale) * self.swap_z * vr_info.hmd_mat,
                 self.world_origin,
                 1,
+                mode,
                 fdata,
                 &self.imgui_general,
-            );
+            )
+            .with_vignette(vignette)
+            .with_background(background);
             self.gpu.queue.write_buffer(
-                &self.camera_state_uniform_buf,
+                &self.gpu_res.camera_state_uniform_buf,
                 0,
                 bytemuck::bytes_of(&self.camera_state),
             );
 
             render_scene(&Scene {
+                comfort_vignette: (vignette > 0.0).then(|| &self.gpu_res.ftri_comfort_vignette),
                 color: &vr_info.right_eye.texture_view,
+                msaa_color: vr_info.right_eye.msaa_texture_view.as_ref(),
                 depth: &vr_info.right_eye.depth_texture_view,
                 ..scene
             });
         }
 
+        if render_eyes {
+            self.last_eye_frame = self.gpu_res.vr_info.as_ref().map(|vr_info| EyeFrameState {
+                hmd_mat: vr_info.hmd_mat,
+                device_poses: self.gpu_res.device_models.drawn_poses(),
+                eye_width: vr_info.left_eye.width,
+            });
+        }
+
         // companion window
         self.camera_state = CameraState::from_proj_and_view(
             self.proj_mat,
-            self.view_mat,
+            companion_view_mat,
             self.world_origin,
             0,
+            mode,
             fdata,
             &self.imgui_general,
-        );
+        )
+        .with_background(background);
         self.gpu.queue.write_buffer(
-            &self.camera_state_uniform_buf,
+            &self.gpu_res.camera_state_uniform_buf,
             0,
             bytemuck::bytes_of(&self.camera_state),
         );
@@ -899,7 +2064,23 @@ impl Global {
             ..scene
         });
 
-        if self.is_gui {
+        // without VR left eye output falls back to companion window
+        let companion_output = match self.config_syncer.get().shared_output {
+            SharedOutputSource::Companion => true,
+            SharedOutputSource::LeftEye => self.gpu_res.vr_info.is_none(),
+            SharedOutputSource::Disabled => false,
+        };
+        if companion_output {
+            if let Some(output_view) = self.shared_output.as_ref().and_then(|o| o.texture_view()) {
+                render_scene(&Scene {
+                    color: output_view,
+                    ..scene
+                });
+            }
+        }
+
+        if is_vscreen_visible {
+            let is_ui_visible = self.is_ui_visible();
             let imgui = &mut self.imgui;
             let ui = imgui.frame();
             let gap = 20.0;
@@ -907,25 +2088,79 @@ impl Global {
             let hw = (w - (3.0 * gap)) / 2.0;
             let x0 = gap;
             let x1 = gap + hw + gap;
-            self.imgui_file_browser.render(
-                &mut self.action_bin,
-                &mut self.config_syncer,
-                &mut self.filedb,
-                &ui,
-                [x0, gap],
-                [hw, h - 2.0 * gap],
-            );
-            {
-                let fdata = self.current_file_key.map(|k| self.filedb.get_file_mut(k));
-                self.imgui_general.render(
+            if !is_ui_visible {
+                // only stats window, buffering indicator or toasts, see is_vscreen_visible
+            } else if self.is_mini_ui {
+                let bar_h = 80.0;
+                self.imgui_general.render_mini_bar(
+                    &mut self.action_bin,
+                    &self.timeline,
+                    &ui,
+                    [gap, h - gap - bar_h],
+                    [w - 2.0 * gap, bar_h],
+                );
+            } else if self.is_quick_projection {
+                let size = [240.0, 300.0];
+                let fdata = self
+                    .current_file_key
+                    .map(|k| self.filedb.get_file_mut(k, self.config_syncer.get()));
+                let before = fdata.as_deref().map(ViewSettings::from_file_data);
+                self.imgui_general.render_quick_projection(
                     &mut self.action_bin,
-                    &mut self.config_syncer,
-                    self.current_file_tracks.as_ref(),
                     fdata,
                     &ui,
-                    [x1, gap],
+                    [(w - size[0]) / 2.0, (h - size[1]) / 2.0],
+                    size,
+                );
+                let after = self
+                    .current_file_key
+                    .and_then(|k| self.filedb.get_file(k))
+                    .map(ViewSettings::from_file_data);
+                self.undo_history.track(before, after, ui.is_any_item_active());
+            } else {
+                self.imgui_file_browser.render(
+                    &mut self.action_bin,
+                    &mut self.config_syncer,
+                    &mut self.filedb,
+                    &ui,
+                    [x0, gap],
                     [hw, h - 2.0 * gap],
                 );
+                {
+                    let fdata = self
+                        .current_file_key
+                        .map(|k| self.filedb.get_file_mut(k, self.config_syncer.get()));
+                    let before = fdata.as_deref().map(ViewSettings::from_file_data);
+                    self.imgui_general.render(
+                        &mut self.action_bin,
+                        &mut self.config_syncer,
+                        &self.timeline,
+                        self.current_file_tracks.as_ref(),
+                        fdata,
+                        &ui,
+                        [x1, gap],
+                        [hw, h - 2.0 * gap],
+                    );
+                    let after = self
+                        .current_file_key
+                        .and_then(|k| self.filedb.get_file(k))
+                        .map(ViewSettings::from_file_data);
+                    self.undo_history.track(before, after, ui.is_any_item_active());
+                }
+            }
+            if self.imgui_general.show_stats {
+                let eye_size = self
+                    .gpu_res
+                    .vr_info
+                    .as_ref()
+                    .map(|i| (i.left_eye.width, i.left_eye.height));
+                self.frame_stats
+                    .render(&ui, eye_size, &mut self.imgui_general.show_stats);
+            }
+            expire_toasts(&mut self.toasts);
+            render_toasts(&ui, &self.toasts, gap);
+            if self.imgui_general.paused_for_cache {
+                render_buffering(&ui, self.imgui_general.cache_buffering_state, gap);
             }
 
             let mut encoder: wgpu::CommandEncoder = self
@@ -934,10 +2169,11 @@ impl Global {
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
             {
+                let gpu_res = &mut *self.gpu_res;
                 let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                     label: None,
                     color_attachments: &[wgpu::RenderPassColorAttachment {
-                        view: &self.vscreen.texture_view,
+                        view: &gpu_res.vscreen.texture_view,
                         resolve_target: None,
                         ops: wgpu::Operations {
                             load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -952,7 +2188,8 @@ impl Global {
                     depth_stencil_attachment: None,
                 });
 
-                self.imgui_renderer
+                gpu_res
+                    .imgui_renderer
                     .render(ui.render(), &self.gpu.queue, &self.gpu.device, &mut rpass)
                     .expect("imgui rendering failed");
             }
@@ -964,13 +2201,23 @@ impl Global {
 
     pub fn after_vk_render(&mut self) {
         self.shared_tex.after_vk(&self.gpu.ash_device, self.gpu.vk_queue);
+        if let Some(output) = &mut self.shared_output {
+            output.update(&self.gpu.ash_device);
+        }
     }
 
     pub fn vr_present(&mut self) {
-        if let (Some(vr_ctx), Some(vr_info)) = (&self.vr, &self.vr_info) {
+        if !self.should_render_eyes {
+            return;
+        }
+        if let (Some(vr_ctx), Some(vr_info)) = (&self.vr, &self.gpu_res.vr_info) {
             unsafe {
-                self.gpu
-                    .submit_eye_textures(&vr_ctx, &vr_info.left_eye, &vr_info.right_eye);
+                let eye_filter = self.imgui_general.eye_filter;
+                self.gpu.submit_eye_textures(
+                    &vr_ctx,
+                    eye_filter.left().then(|| &vr_info.left_eye),
+                    eye_filter.right().then(|| &vr_info.right_eye),
+                );
             }
         }
     }
@@ -992,79 +2239,180 @@ impl Global {
                 )
                 .unwrap();
 
+            // mpv output is already encoded as the texture expects it, see SharedTextureFormat
+            unsafe { gl::Disable(gl::FRAMEBUFFER_SRGB) };
+
             let fbo = self.shared_tex.gl.gl_fbo as i32;
             let width = self.shared_tex.vk.width as i32;
             let height = self.shared_tex.vk.height as i32;
-            let fmt = gl::SRGB8 as i32;
-            self.shared_tex
-                .draw_gl(|| self.mpv_render.render_maybe(fbo, width, height, fmt));
+            let fmt = self.shared_tex.vk.format.gl_internal_format() as i32;
+            self.video_frame_changed = self.shared_tex.draw_gl(&self.gpu.ash_device, || {
+                self.mpv_render.render_maybe(fbo, width, height, fmt)
+            });
         }
     }
 
     pub fn handle_sdl2_events(&mut self) {
         let mut xrel_accum = 0i32;
         let mut yrel_accum = 0i32;
-        for event in self.sdl_event_pump.poll_iter() {
+        // collected upfront, so that event handling can use &mut self methods
+        let events: Vec<Event> = self.sdl_event_pump.poll_iter().collect();
+        for event in events {
+            // keyboard navigation of the UI is in progress, key presses drive the nav cursor instead of hotkeys,
+            // releases still go through, so that movement keys don't get stuck
+            let is_ui_keyboard_nav =
+                self.is_ui_visible() && self.imgui.io().nav_visible && matches!(event, Event::KeyDown { .. });
             // some events we always handle
-            if let Event::Quit { .. }
-            | Event::KeyDown {
+            if let Event::Quit { .. } = event {
+                self.action_bin.put(Action::Quit);
+            } else if let Event::KeyDown {
                 keycode: Some(Keycode::Escape),
                 ..
             } = event
             {
-                self.action_bin.put(Action::Quit);
+                // Escape leaves keyboard navigation first
+                if !is_ui_keyboard_nav {
+                    self.action_bin.put(Action::Quit);
+                }
+            } else if let Event::Window {
+                win_event: WindowEvent::SizeChanged(w, h),
+                ..
+            } = event
+            {
+                // SizeChanged comes for any size change, Resized only for those not caused by the app itself (e.g.
+                // fullscreen toggle), stale surface size would stretch the picture. Zero size shows up on some
+                // compositors while the window is not mapped, the old size is kept then.
+                if w > 0 && h > 0 {
+                    self.resize_surface(w as u32, h as u32);
+                }
+            } else if let Event::Window {
+                win_event: win_event @ (WindowEvent::FocusLost | WindowEvent::FocusGained),
+                ..
+            } = event
+            {
+                self.on_window_focus_changed(win_event == WindowEvent::FocusGained);
             } else if let Event::Window {
-                win_event: WindowEvent::Resized(w, h),
+                win_event: win_event @ (WindowEvent::Minimized | WindowEvent::Restored | WindowEvent::Maximized),
                 ..
             } = event
             {
-                assert!(w > 0 && h > 0);
-                let w = w as u32;
-                let h = h as u32;
-                self.surface_config.width = w;
-                self.surface_config.height = h;
-                self.gpu.surface.configure(&self.gpu.device, &self.surface_config);
-                self.depth_view = create_depth_texture(&self.gpu.device, w, h);
-                self.proj_mat = Mat4::perspective_lh(90f32.to_radians(), w as f32 / h as f32, 0.01, 100.0);
+                self.window_minimized = win_event == WindowEvent::Minimized;
             } else if let Event::MouseButtonDown {
                 mouse_btn: MouseButton::Right,
                 ..
             } = event
             {
                 self.action_bin.put(Action::ToggleUI);
+            } else if let Event::MouseButtonDown {
+                mouse_btn: MouseButton::Middle,
+                ..
+            } = event
+            {
+                // configured middle click action wins over plain drag, shift + drag pans regardless
+                let pan = self.controls.middle_click == Action::None || self.is_shift_held();
+                if pan && !self.is_ui_visible() && self.is_flat_file() {
+                    self.flat_panning = true;
+                    self.flat_pan_moved = false;
+                } else {
+                    self.action_bin.put(self.controls.middle_click.clone());
+                }
+            } else if let Event::MouseButtonUp {
+                mouse_btn: MouseButton::Middle,
+                ..
+            } = event
+            {
+                if mem::take(&mut self.flat_panning) {
+                    if self.flat_pan_moved {
+                        self.finish_flat_edit();
+                    } else {
+                        self.action_bin.put(self.controls.middle_click.clone());
+                    }
+                }
+            } else if let Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                ..
+            } = event
+            {
+                self.flat_placing = self.is_shift_held() && !self.is_ui_visible() && self.is_flat_file();
+            } else if let Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } = event
+            {
+                if mem::take(&mut self.flat_placing) {
+                    self.finish_flat_edit();
+                }
+            } else if let Event::MouseButtonDown { mouse_btn, .. } = event {
+                let action = match mouse_btn {
+                    MouseButton::X1 => Some(&self.controls.x1_click),
+                    MouseButton::X2 => Some(&self.controls.x2_click),
+                    _ => None,
+                };
+                if let Some(action) = action {
+                    self.action_bin.put(action.clone());
+                }
             }
             if let Event::KeyDown {
                 keycode: Some(Keycode::Space),
                 ..
             } = event
             {
-                self.action_bin.put(Action::ResetWorldOrigin);
+                if !is_ui_keyboard_nav {
+                    self.action_bin.put(Action::ResetWorldOrigin);
+                }
             }
+            self.handle_gamepad_event(&event);
 
-            if self.is_gui {
+            if self.is_ui_visible() {
                 // gui only events
-                self.vscreen
+                self.gpu_res
+                    .vscreen
                     .imgui_handle_event(&mut self.imgui, &event, &self.config_syncer.get());
             } else {
                 // non-gui only events
                 match event {
+                    Event::MouseMotion { xrel, yrel, .. } if self.flat_panning => {
+                        self.flat_pan_moved = true;
+                        self.pan_flat(xrel, yrel);
+                    }
+                    Event::MouseMotion { xrel, yrel, .. } if self.flat_placing => self.place_flat(xrel, yrel),
                     Event::MouseMotion { xrel, yrel, .. } => {
                         xrel_accum += xrel;
                         yrel_accum += yrel;
                     }
+                    Event::MouseWheel { y, .. } if self.is_flat_file() => self.zoom_flat(y),
                     _ => {}
                 }
             }
 
-            if !self.imgui.io().want_capture_keyboard {
+            if !self.imgui.io().want_capture_keyboard && !is_ui_keyboard_nav {
                 // it's ok to handle keyboard events if imgui doesn't need keyboard input
                 match event {
-                    Event::KeyDown { keycode, .. } => match keycode {
+                    Event::KeyDown { keycode, keymod, .. } => match keycode {
                         Some(Keycode::W) => self.move_forward = true,
                         Some(Keycode::S) => self.move_backward = true,
                         Some(Keycode::A) => self.move_left = true,
                         Some(Keycode::D) => self.move_right = true,
-                        _ => {}
+                        Some(Keycode::R) => self.action_bin.put(Action::ResetCameraRoll),
+                        Some(Keycode::Z) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                            self.action_bin.put(Action::Undo)
+                        }
+                        Some(Keycode::C) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                            self.action_bin.put(Action::CopyTimestamp)
+                        }
+                        Some(Keycode::C) => self.action_bin.put(Action::RecenterScreen),
+                        Some(Keycode::PageDown) => self.action_bin.put(Action::NextFile),
+                        Some(Keycode::PageUp) => self.action_bin.put(Action::PreviousFile),
+                        Some(Keycode::F3) => self.action_bin.put(Action::ToggleStats),
+                        Some(Keycode::F11) => self.action_bin.put(Action::ToggleFullscreen),
+                        Some(Keycode::Left) => self.seek_relative(-1.0, keymod),
+                        Some(Keycode::Right) => self.seek_relative(1.0, keymod),
+                        Some(k) => {
+                            if let Some(action) = self.controls.key_action(k) {
+                                self.action_bin.put(action.clone());
+                            }
+                        }
+                        None => {}
                     },
                     Event::KeyUp { keycode, .. } => match keycode {
                         Some(Keycode::W) => self.move_forward = false,
@@ -1079,22 +2427,25 @@ impl Global {
         }
 
         // UPDATE COMPANION WINDOW CAMERA
-        {
-            if self.move_forward | self.move_backward | self.move_left | self.move_right {
-                let mut motion = Vec2::new(0.0, 0.0);
-                if self.move_forward {
-                    motion.y += 1.0;
-                }
-                if self.move_backward {
-                    motion.y -= 1.0;
-                }
-                if self.move_right {
-                    motion.x += 1.0;
-                }
-                if self.move_left {
-                    motion.x -= 1.0;
-                }
-                motion = motion.normalize();
+        if !self.is_mirroring_vr() {
+            let mut motion = Vec2::new(0.0, 0.0);
+            if self.move_forward {
+                motion.y += 1.0;
+            }
+            if self.move_backward {
+                motion.y -= 1.0;
+            }
+            if self.move_right {
+                motion.x += 1.0;
+            }
+            if self.move_left {
+                motion.x -= 1.0;
+            }
+            motion = motion.normalize_or_zero();
+            // sdl stick y axis goes down
+            let [lx, ly, rx, ry] = self.gamepad_axes;
+            motion = (motion + Vec2::new(lx, -ly)).clamp_length_max(1.0);
+            if motion != Vec2::ZERO {
                 let cam_mat = Mat4::from_quat(self.cam_quat);
                 let forward_vec = cam_mat.z_axis.truncate();
                 let right_vec = cam_mat.x_axis.truncate();
@@ -1103,6 +2454,10 @@ impl Global {
                 self.cam_pos += forward_vec * Vec3::splat(self.delta.as_secs_f32() * speed) * motion.y;
                 self.cam_pos += right_vec * Vec3::splat(self.delta.as_secs_f32() * speed) * motion.x;
             }
+            if self.config_syncer.get().snap_turn {
+                // turning is done by Action::SnapTurnLeft/Right
+                xrel_accum = 0;
+            }
             if xrel_accum != 0 || yrel_accum != 0 {
                 let sens = self.config_syncer.get().camera_sensitivity;
                 let vrot = Quat::from_rotation_x((yrel_accum as f32 * sens).to_radians());
@@ -1110,17 +2465,180 @@ impl Global {
                 // let hrot = Quat::IDENTITY;
                 self.cam_quat = (hrot * (self.cam_quat * vrot)).normalize();
             }
+            if rx != 0.0 || ry != 0.0 {
+                let step = GAMEPAD_LOOK_DEGREES_PER_SEC * self.delta.as_secs_f32();
+                let vrot = Quat::from_rotation_x((ry * step).to_radians());
+                let hrot = Quat::from_rotation_y((rx * step).to_radians());
+                self.cam_quat = (hrot * (self.cam_quat * vrot)).normalize();
+            }
             self.view_mat = Mat4::from_quat(self.cam_quat.inverse()) * Mat4::from_translation(-self.cam_pos);
         }
     }
 
+    fn resize_surface(&mut self, w: u32, h: u32) {
+        self.surface_config.width = w;
+        self.surface_config.height = h;
+        self.gpu.surface.configure(&self.gpu.device, &self.surface_config);
+        self.gpu_res.depth_view = create_depth_texture(&self.gpu.device, w, h, self.sample_count);
+        self.gpu_res.msaa_view =
+            create_msaa_texture(&self.gpu.device, w, h, self.surface_config.format, self.sample_count);
+        self.proj_mat = companion_proj_mat(self.config_syncer.get(), w, h);
+    }
+
+    fn handle_gamepad_event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                if let Some(subsystem) = &self.sdl_controller_subsystem {
+                    match subsystem.open(which) {
+                        Ok(c) => {
+                            log::info!("game controller connected: {}", c.name());
+                            self.gamepads.push(c);
+                        }
+                        Err(e) => log::error!("failed opening game controller: {}", e),
+                    }
+                }
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.gamepads.retain(|c| c.instance_id() != which);
+                if self.gamepads.is_empty() {
+                    self.gamepad_axes = [0.0; 4];
+                }
+            }
+            Event::ControllerButtonDown { button, .. } => {
+                if self.is_gamepad_nav_active() && GAMEPAD_NAV_BUTTONS.iter().any(|(b, _)| *b == button) {
+                    return;
+                }
+                if let Some(action) = self.controls.button_action(button) {
+                    self.action_bin.put(action.clone());
+                }
+            }
+            Event::ControllerAxisMotion { axis, value, .. } => {
+                let index = match axis {
+                    Axis::LeftX => 0,
+                    Axis::LeftY => 1,
+                    Axis::RightX => 2,
+                    Axis::RightY => 3,
+                    _ => return,
+                };
+                let v = value as f32 / i16::MAX as f32;
+                // rescaled, so that stick motion starts smoothly at the dead zone edge
+                self.gamepad_axes[index] = if v.abs() < GAMEPAD_DEAD_ZONE {
+                    0.0
+                } else {
+                    v.signum() * ((v.abs() - GAMEPAD_DEAD_ZONE) / (1.0 - GAMEPAD_DEAD_ZONE)).min(1.0)
+                };
+            }
+            _ => {}
+        }
+    }
+
+    // around world up axis, so that snap turns don't accumulate roll
+    fn snap_turn(&mut self, direction: f32) {
+        if !self.config_syncer.get().snap_turn || self.is_mirroring_vr() {
+            return;
+        }
+        let angle = self.config_syncer.get().snap_turn_degrees.to_radians() * direction;
+        self.cam_quat = (Quat::from_rotation_y(angle) * self.cam_quat).normalize();
+    }
+
+    fn is_flat_file(&self) -> bool {
+        self.current_file_key
+            .and_then(|k| self.filedb.get_file(k))
+            .map(|d| d.projection == Projection::Flat)
+            .unwrap_or(false)
+    }
+
+    fn is_shift_held(&self) -> bool {
+        self.sdl_context
+            .keyboard()
+            .mod_state()
+            .intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD)
+    }
+
+    // Mouse edits of the flat screen. Only files with stored settings are edited (see is_flat_file), an entry is
+    // written back only if something actually changed, e.g. wheel zoom at its limit doesn't dirty it. A drag is a
+    // single undo step, see finish_flat_edit.
+    fn edit_flat_file<F: FnOnce(&mut FileData)>(&mut self, f: F) {
+        let key = match self.current_file_key {
+            Some(key) => key,
+            None => return,
+        };
+        let mut fdata = match self.filedb.get_file(key) {
+            Some(d) => d.clone(),
+            None => return,
+        };
+        let before = ViewSettings::from_file_data(&fdata);
+        f(&mut fdata);
+        if self.filedb.get_file(key) != Some(&fdata) {
+            let after = ViewSettings::from_file_data(&fdata);
+            *self.filedb.get_file_mut(key, self.config_syncer.get()) = fdata;
+            let dragging = self.flat_panning || self.flat_placing;
+            self.undo_history.track(Some(before), Some(after), dragging);
+        }
+    }
+
+    fn finish_flat_edit(&mut self) {
+        let current = self
+            .current_file_key
+            .and_then(|k| self.filedb.get_file(k))
+            .map(ViewSettings::from_file_data);
+        self.undo_history.track(current, current, false);
+    }
+
+    fn zoom_flat(&mut self, steps: i32) {
+        self.edit_flat_file(|d| d.set_flat_zoom_pan(d.flat_zoom * 1.1f32.powi(steps), d.flat_pan));
+    }
+
+    // moves the picture along with the mouse, roughly as if the whole window showed the zoomed-in region
+    fn pan_flat(&mut self, xrel: i32, yrel: i32) {
+        let (w, h) = (self.surface_config.width as f32, self.surface_config.height as f32);
+        self.edit_flat_file(|d| {
+            let delta = Vec2::new(xrel as f32 / w, yrel as f32 / h) / d.flat_zoom;
+            d.set_flat_zoom_pan(d.flat_zoom, d.flat_pan - delta);
+        });
+    }
+
+    // sideways drag moves the screen sideways, forward/backward drag pushes it away/pulls it closer, speed is
+    // proportional to the distance, so that it feels the same regardless of how far the screen is
+    fn place_flat(&mut self, xrel: i32, yrel: i32) {
+        self.edit_flat_file(|d| {
+            let speed = 0.002 * d.flat_distance;
+            d.flat_offset = (d.flat_offset + xrel as f32 * speed).clamp(-20.0, 20.0);
+            d.flat_distance = (d.flat_distance - yrel as f32 * speed).clamp(0.2, 20.0);
+        });
+    }
+
+    pub fn handle_vr_events(&mut self) {
+        let events = match &self.vr {
+            Some(vr) => vr.system.poll_events(),
+            None => return,
+        };
+        for ev in events {
+            match ev {
+                libopenvr::VrEvent::DashboardActivated => self.vr_dashboard_active = true,
+                libopenvr::VrEvent::DashboardDeactivated => self.vr_dashboard_active = false,
+                libopenvr::VrEvent::UserInteractionStarted(libopenvr::HMD_DEVICE_INDEX) => self.vr_hmd_idle = false,
+                libopenvr::VrEvent::UserInteractionEnded(libopenvr::HMD_DEVICE_INDEX) => self.vr_hmd_idle = true,
+                _ => {}
+            }
+        }
+        // poses are still waited for every frame, it keeps the app in compositor's frame loop
+        let should_render_eyes = !self.vr_dashboard_active && !self.vr_hmd_idle;
+        if should_render_eyes != self.should_render_eyes {
+            log::info!("VR eye rendering is {}", cond!(should_render_eyes, "resumed", "paused"));
+            self.should_render_eyes = should_render_eyes;
+        }
+    }
+
     pub fn handle_action_bin(&mut self) {
-        if let Some(action) = self.action_bin.dispatch() {
+        while let Some(action) = self.action_bin.dispatch() {
             self.dispatch_action(action);
         }
     }
 
     pub fn dispatch_action(&mut self, action: Action) {
+        // most actions change what's on screen one way or another
+        self.last_eye_frame = None;
         match action {
             Action::None => {}
             Action::Quit => {
@@ -1129,45 +2647,409 @@ impl Global {
             Action::ToggleUI => {
                 self.is_gui = !self.is_gui;
                 if self.is_gui {
-                    // reset ui origin when gui is turned on
-                    self.ui_origin = reset_origin(self.current_camera_mat());
-                    // cancel movement if switched to gui
-                    self.move_forward = false;
-                    self.move_backward = false;
-                    self.move_left = false;
-                    self.move_right = false;
+                    self.is_mini_ui = false;
+                    self.is_quick_projection = false;
+                    self.on_ui_shown();
+                }
+            }
+            Action::ToggleForceMono => {
+                self.force_mono = !self.force_mono;
+                self.imgui_general.force_mono = self.force_mono;
+            }
+            Action::ToggleStats => {
+                self.imgui_general.show_stats = !self.imgui_general.show_stats;
+            }
+            Action::ToggleFullscreen => {
+                let is_fullscreen = self.sdl_window.fullscreen_state() != FullscreenType::Off;
+                self.set_fullscreen(!is_fullscreen);
+            }
+            Action::ToggleMiniUI => {
+                self.is_mini_ui = !self.is_mini_ui;
+                if self.is_mini_ui {
+                    self.is_gui = false;
+                    self.is_quick_projection = false;
+                    self.on_ui_shown();
+                }
+            }
+            Action::ToggleQuickProjection => {
+                self.is_quick_projection = !self.is_quick_projection;
+                if self.is_quick_projection {
+                    self.is_gui = false;
+                    self.is_mini_ui = false;
+                    self.on_ui_shown();
                 }
             }
             Action::ResetWorldOrigin => {
                 self.world_origin = reset_origin(self.current_camera_mat());
+                self.screen_origin = None;
+                if self.vr.is_some() && self.config_syncer.get().comfort_fov_reduction {
+                    self.fov_reduction = 1.0;
+                }
+            }
+            Action::SaveFileOrientation => {
+                if let Some(key) = self.current_file_key {
+                    let yaw_pitch = view_yaw_pitch(self.current_camera_mat());
+                    self.filedb
+                        .get_file_mut(key, self.config_syncer.get())
+                        .saved_orientation = Some(yaw_pitch);
+                    self.world_origin = oriented_origin(self.current_camera_mat(), yaw_pitch);
+                    self.screen_origin = None;
+                    self.notify("Saved orientation of the current file");
+                }
+            }
+            Action::RecenterScreen => {
+                let origin = reset_origin(self.current_camera_mat());
+                self.screen_origin = Some(origin);
+                if !self.ui_pinned {
+                    self.ui_origin = origin;
+                }
+            }
+            Action::TogglePinUI => {
+                self.ui_pinned = !self.ui_pinned;
+                self.imgui_general.ui_pinned = self.ui_pinned;
+                if !self.ui_pinned {
+                    // follow-me behavior, bring UI back in front of the viewer
+                    self.ui_origin = reset_origin(self.current_camera_mat());
+                }
+                self.notify(cond!(self.ui_pinned, "UI pinned", "UI unpinned"));
+            }
+            Action::SnapTurnLeft => self.snap_turn(-1.0),
+            Action::SnapTurnRight => self.snap_turn(1.0),
+            Action::ResetCameraRoll => {
+                let forward = Mat4::from_quat(self.cam_quat).z_axis;
+                let yaw = forward.x.atan2(forward.z);
+                self.cam_quat = Quat::from_rotation_y(yaw);
+            }
+            Action::CycleAspectRatio => {
+                if let Some(key) = self.current_file_key {
+                    let fdata = self.filedb.get_file_mut(key, self.config_syncer.get());
+                    if fdata.projection == Projection::Flat {
+                        let before = ViewSettings::from_file_data(fdata);
+                        fdata.aspect_ratio = fdata.aspect_ratio.next();
+                        let after = ViewSettings::from_file_data(fdata);
+                        let msg = format!("Aspect Ratio: {}", fdata.aspect_ratio.label());
+                        self.undo_history.track(Some(before), Some(after), false);
+                        self.notify(msg);
+                    }
+                }
+            }
+            Action::NextFile => self.load_adjacent_file(1),
+            Action::PreviousFile => self.load_adjacent_file(-1),
+            Action::Undo => match (self.current_file_key, self.undo_history.undo()) {
+                (Some(key), Some(settings)) => {
+                    settings.apply(self.filedb.get_file_mut(key, self.config_syncer.get()));
+                    self.notify("Undone");
+                }
+                _ => self.notify("Nothing to undo"),
+            },
+            Action::CopyTimestamp => {
+                let mut timestamp = String::new();
+                self.timeline.write_pos(&mut timestamp);
+                if self.current_file_path.is_none() {
+                    self.notify("Nothing is playing");
+                } else if let Err(e) = self.sdl_video_subsystem.clipboard().set_clipboard_text(&timestamp) {
+                    log::error!("failed copying timestamp to clipboard: {}", e);
+                    self.notify(format!("Failed copying timestamp: {}", e));
+                } else {
+                    self.notify(format!("Copied {}", timestamp));
+                }
+            }
+            Action::ForgetCurrentFile => {
+                if let Some(key) = self.current_file_key {
+                    match self.filedb.forget_file(key, self.config_syncer.get()) {
+                        Ok(_) => {
+                            log::info!("forgot settings of the current file");
+                            self.notify("Forgot settings of the current file");
+                        }
+                        Err(e) => {
+                            log::error!("failed forgetting file: {}", e);
+                            self.notify(format!("Failed forgetting file: {}", e));
+                        }
+                    }
+                }
+            }
+            Action::ResetAllSettings { forget_files } => {
+                log::info!("resetting all settings (forget files: {})", forget_files);
+                *self.config_syncer.get_mut() = Config::defaults();
+                self.imgui_file_browser.reload_config(self.config_syncer.get());
+                if forget_files {
+                    if let Err(e) = self.filedb.forget_all_files() {
+                        log::error!("failed forgetting all files: {}", e);
+                        self.notify(format!("Failed forgetting all files: {}", e));
+                    }
+                }
+                self.notify("Settings were reset");
+            }
+            Action::ExportFileDB => {
+                match default_export_path().and_then(|p| self.filedb.export_to_path(&p).map(|n| (p, n))) {
+                    Ok((p, n)) => {
+                        log::info!("exported {} files to {}", n, p.display());
+                        self.notify(format!("Exported {} files to {}", n, p.display()));
+                    }
+                    Err(e) => {
+                        log::error!("failed exporting file db: {}", e);
+                        self.notify(format!("Failed exporting file DB: {}", e));
+                    }
+                }
+            }
+            Action::ImportFileDB => {
+                match default_export_path().and_then(|p| self.filedb.import_from_path(&p).map(|n| (p, n))) {
+                    Ok((p, n)) => {
+                        log::info!("imported {} files from {}", n, p.display());
+                        self.notify(format!("Imported {} files from {}", n, p.display()));
+                    }
+                    Err(e) => {
+                        log::error!("failed importing file db: {}", e);
+                        self.notify(format!("Failed importing file DB: {}", e));
+                    }
+                }
             }
             Action::Command(cmd) => {
+                // e.g. "set volume 50" or "add volume -2", user's choice wins over the fade in
+                if cmd.get(1).map(|v| v == "volume").unwrap_or(false) {
+                    self.volume_changed_by_user = true;
+                    self.volume_fade = None;
+                }
+                if let Some(name) = cmd
+                    .get(1)
+                    .and_then(|v| USER_SAVED_PROPERTIES.iter().find(|n| **n == v.as_str()))
+                {
+                    self.properties_changed_by_user.insert(*name);
+                }
                 let s = cmd.iter().map(|v| v.as_str()).collect::<Vec<_>>();
                 self.mpv.command_async(&s);
             }
         }
     }
 
-    pub fn wait_get_hmd_pose(&mut self) {
-        if let (Some(vr), Some(vr_info)) = (&self.vr, &mut self.vr_info) {
-            let m = vr.compositor.wait_get_hmd_pose();
+    // desktop fullscreen, i.e. borderless window of desktop size without a video mode switch
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        let typ = cond!(fullscreen, FullscreenType::Desktop, FullscreenType::Off);
+        if let Err(e) = self.sdl_window.set_fullscreen(typ) {
+            log::error!("failed changing fullscreen state: {}", e);
+        }
+    }
+
+    fn is_fullscreen_on_play(&self) -> bool {
+        self.vr.is_none() && self.config_syncer.get().fullscreen_on_play
+    }
+
+    // Only resumes playback if it was paused by focus loss, so that user's own pause is kept.
+    fn on_window_focus_changed(&mut self, focused: bool) {
+        if self.vr.is_some() || !self.config_syncer.get().pause_on_focus_loss {
+            return;
+        }
+        if !focused && self.imgui_general.playing {
+            self.paused_by_focus_loss = true;
+            self.mpv.command_async(&["set", "pause", "yes"]);
+        } else if focused && self.paused_by_focus_loss {
+            self.paused_by_focus_loss = false;
+            self.mpv.command_async(&["set", "pause", "no"]);
+        }
+    }
+
+    // small step by default, large step with Shift held
+    fn seek_relative(&mut self, direction: f64, keymod: Mod) {
+        let cfg = self.config_syncer.get();
+        let large = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+        let secs = direction * cond!(large, cfg.seek_large_secs, cfg.seek_small_secs);
+        self.action_bin.put(Action::Command(vec![
+            "seek".to_owned(),
+            format!("{}", secs),
+            "relative".to_owned(),
+        ]));
+    }
+
+    // Nothing is visible while the desktop window is minimized, so GPU work and mpv frame rendering are skipped
+    // until it's restored. In VR mode the headset is the output and rendering goes on.
+    fn is_render_suspended(&self) -> bool {
+        self.window_minimized && self.vr.is_none()
+    }
+
+    fn is_ui_visible(&self) -> bool {
+        self.is_gui || self.is_mini_ui || self.is_quick_projection
+    }
+
+    // stats window is shown even when UI is hidden, it doesn't take input though
+    fn is_vscreen_visible(&self) -> bool {
+        // toasts keep it visible until they expire, otherwise they'd expire unseen with the UI hidden
+        self.is_ui_visible()
+            || self.imgui_general.show_stats
+            || self.imgui_general.paused_for_cache
+            || !self.toasts.is_empty()
+    }
+
+    fn on_ui_shown(&mut self) {
+        // reset ui origin when gui is turned on, unless it's pinned
+        if !self.ui_pinned {
+            self.ui_origin = reset_origin(self.current_camera_mat());
+        }
+        // cancel movement if switched to gui
+        self.move_forward = false;
+        self.move_backward = false;
+        self.move_left = false;
+        self.move_right = false;
+    }
+
+    // Shows a short message on top of the UI for a few seconds, it's not logged, callers do that.
+    pub fn notify<S: Into<String>>(&mut self, msg: S) {
+        push_toast(&mut self.toasts, msg.into());
+    }
+
+    fn load_adjacent_file(&mut self, offset: isize) {
+        let path = match &self.current_file_path {
+            Some(p) => PathBuf::from(p),
+            None => return,
+        };
+        match adjacent_video_file(&path, offset, &self.config_syncer.get().video_extensions) {
+            // goes through the regular file loading path, so per-file settings get picked up as usual
+            Some(p) => self.dispatch_action(Action::Command(vec![
+                "loadfile".to_owned(),
+                p.to_string_lossy().to_string(),
+            ])),
+            None => {
+                log::info!(
+                    "no adjacent file for offset {} in directory of {}",
+                    offset,
+                    path.display()
+                );
+                self.notify(cond!(offset > 0, "No next file", "No previous file"));
+            }
+        }
+    }
+
+    pub fn wait_get_poses(&mut self) {
+        let poses = match &self.vr {
+            Some(vr) => vr.compositor.wait_get_poses(),
+            None => return,
+        };
+        // last known HMD pose is kept while tracking is lost
+        let hmd_pose = poses[libopenvr::HMD_DEVICE_INDEX].filter(|p| p.is_valid);
+        if let (Some(vr_info), Some(pose)) = (&mut self.gpu_res.vr_info, hmd_pose) {
+            let m = pose.device_to_absolute;
             vr_info.orig_hmd_mat = m;
             vr_info.hmd_mat = (self.swap_z * m * self.swap_z).inverse();
         }
+        if hmd_pose.is_none() != self.hmd_tracking_lost {
+            self.hmd_tracking_lost = hmd_pose.is_none();
+            self.notify(cond!(
+                self.hmd_tracking_lost,
+                "HMD tracking lost",
+                "HMD tracking restored"
+            ));
+        }
+        self.update_laser_pointer(&poses);
+        if let Some(vr) = &self.vr {
+            self.gpu_res
+                .device_models
+                .update(vr, poses, &self.gpu.device, &self.gpu.queue);
+        }
     }
 
-    pub fn shutdown(&mut self) {
-        self.gpu.shutdown();
-        self.shared_tex.shutdown(&self.gpu.ash_device);
-        if let Some(vr_ctx) = &self.vr {
+    // The first controller pointing at the UI panel drives the UI cursor, its trigger is the left mouse button.
+    fn update_laser_pointer(&mut self, poses: &[Option<libopenvr::TrackedPose>]) {
+        self.laser = None;
+        let hit = self.vr.as_ref().filter(|_| self.is_ui_visible()).and_then(|vr| {
+            let config = self.config_syncer.get();
+            let inv_panel_mat = ui_panel_transform(self.ui_origin, config).inverse();
+            let size = ui_panel_size(config, &self.gpu_res.vscreen);
+            let radius = ui_curvature_radius(config);
+            poses.iter().enumerate().find_map(|(index, pose)| {
+                let pose =
+                    pose.filter(|p| p.is_valid && p.device_class == libopenvr::TrackedDeviceClass::Controller)?;
+                // ray in UI panel space, in meters
+                let m = self.swap_z * pose.device_to_absolute;
+                let origin = inv_panel_mat.transform_point3(m.transform_point3(Vec3::ZERO));
+                let dir = inv_panel_mat.transform_vector3(m.transform_vector3(-Vec3::Z));
+                let (t, x) = ui_panel_hit(origin, dir, radius)?;
+                // position on the unit quad, which is [-0.5, 0.5] in XY plane
+                let p = Vec2::new(x / size.x, (origin.y + dir.y * t) / size.y);
+                if t <= 0.0 || p.x.abs() > 0.5 || p.y.abs() > 0.5 {
+                    return None;
+                }
+                let length = (dir * t).length();
+                let trigger = vr
+                    .system
+                    .get_controller_state(index)
+                    .map_or(false, |s| s.is_trigger_pressed());
+                Some((p, m * Mat4::from_scale(Vec3::splat(length)), trigger))
+            })
+        });
+
+        let trigger = match hit {
+            Some((p, laser, trigger)) => {
+                // texture v is flipped in the shader
+                let x = (p.x + 0.5) * self.gpu_res.vscreen.width as f32;
+                let y = (0.5 - p.y) * self.gpu_res.vscreen.height as f32;
+                self.gpu_res.vscreen.set_mouse_position(x, y);
+                self.laser = Some(laser);
+                trigger
+            }
+            None => false,
+        };
+        if trigger != self.laser_trigger {
+            self.laser_trigger = trigger;
+            self.gpu_res.vscreen.set_left_mouse_button(trigger);
+        }
+    }
+
+    // Tears down members with unsafe parts, order matters:
+    //  1. GPU goes idle, nothing in flight references images destroyed below
+    //  2. wgpu resources (pipelines, buffers, eye textures, vscreen, etc), they must not outlive the device
+    //  3. VR, openvr compositor holds the device and submitted eye images, so it goes while the device is alive
+    //  4. shared images (VK and GL halves), GL context is still current
+    //  5. mpv render context, it uses GL context as well
+    //  6. command pool and GPU handles, nothing created on the device is left at this point
+    //  7. EGL context, last GL user is gone
+    // Safety: must be the last call on Global, ManuallyDrop members are dropped here.
+    unsafe fn shutdown(&mut self) {
+        self.status_writer.remove();
+
+        self.gpu.device.poll(wgpu::Maintain::Wait);
+        if let Err(e) = self.gpu.ash_device.device_wait_idle() {
+            log::error!("failed waiting for device idle: {}", e);
+        }
+
+        ManuallyDrop::drop(&mut self.gpu_res);
+
+        if let Some(vr_ctx) = self.vr.take() {
             vr_ctx.shutdown();
         }
+
+        if let Some(output) = self.shared_output.take() {
+            output.shutdown(&self.gpu.ash_device);
+        }
+        self.shared_tex.shutdown(&self.gpu.ash_device);
+        ManuallyDrop::drop(&mut self.shared_tex);
+
+        ManuallyDrop::drop(&mut self.mpv_render);
+
+        self.gpu.shutdown();
+        ManuallyDrop::drop(&mut self.gpu);
+
+        ManuallyDrop::drop(&mut self.egl);
     }
 
-    pub fn run(&mut self) {
-        while self.is_running {
-            self.main_loop();
+    pub fn run(self) {
+        let mut guard = ShutdownGuard(self);
+        let global = &mut guard.0;
+        while global.is_running {
+            global.main_loop();
         }
-        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_count_mismatch_is_reported() {
+        assert_eq!(check_sample_counts(4, &[]), Ok(()));
+        assert_eq!(check_sample_counts(4, &[("a", 4), ("b", 4)]), Ok(()));
+        assert_eq!(
+            check_sample_counts(4, &[("a", 4), ("b", 1), ("c", 2)]),
+            Err("b sample count is 1, expected 4".to_owned())
+        );
     }
 }
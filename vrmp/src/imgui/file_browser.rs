@@ -1,58 +1,220 @@
 use crate::action::{Action, ActionBin};
-use crate::config::ConfigSyncer;
-use crate::filedb::{load_file_hash, FileDB};
+use crate::config::{Config, ConfigSyncer};
+use crate::enums::SortMode;
+use crate::filedb::{load_file_key, FileDB, FileKey};
 use crate::imgui::font_awesome as fa;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
 use std::{ffi::OsString, fs::Metadata, path::PathBuf};
 
 use super::util::hex;
 
-fn is_video_extension(ext: Option<&OsStr>) -> bool {
+// video_extensions come from config, comparison is ASCII case-insensitive
+pub fn is_video_extension(ext: Option<&OsStr>, video_extensions: &[String]) -> bool {
     if let Some(ext) = ext {
-        ext.eq_ignore_ascii_case("avi")
-            || ext.eq_ignore_ascii_case("flv")
-            || ext.eq_ignore_ascii_case("m4p")
-            || ext.eq_ignore_ascii_case("m4v")
-            || ext.eq_ignore_ascii_case("mkv")
-            || ext.eq_ignore_ascii_case("mov")
-            || ext.eq_ignore_ascii_case("mp2")
-            || ext.eq_ignore_ascii_case("mp4")
-            || ext.eq_ignore_ascii_case("mpe")
-            || ext.eq_ignore_ascii_case("mpeg")
-            || ext.eq_ignore_ascii_case("mpg")
-            || ext.eq_ignore_ascii_case("mpv")
-            || ext.eq_ignore_ascii_case("ogg")
-            || ext.eq_ignore_ascii_case("qt")
-            || ext.eq_ignore_ascii_case("swf")
-            || ext.eq_ignore_ascii_case("webm")
-            || ext.eq_ignore_ascii_case("wmv")
+        video_extensions.iter().any(|v| ext.eq_ignore_ascii_case(v))
     } else {
         false
     }
 }
 
+// Finds a video file next to the given one in its directory (sorted by name), offset is usually 1 or -1.
+pub fn adjacent_video_file(path: &Path, offset: isize, video_extensions: &[String]) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    let name = path.file_name()?;
+    let rd = match std::fs::read_dir(dir) {
+        Ok(rd) => rd,
+        Err(e) => {
+            log::error!("failed reading directory {}: {}", dir.display(), e);
+            return None;
+        }
+    };
+    let mut names = rd
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| !t.is_dir()).unwrap_or(false))
+        .map(|e| e.file_name())
+        .filter(|n| is_video_extension(Path::new(n).extension(), video_extensions))
+        .collect::<Vec<_>>();
+    names.sort();
+    let idx = names.iter().position(|n| n == name)? as isize + offset;
+    if idx < 0 || idx as usize >= names.len() {
+        return None;
+    }
+    Some(dir.join(&names[idx as usize]))
+}
+
+const SORT_MODES: &[(SortMode, &str)] = &[
+    (SortMode::Name, "Name"),
+    (SortMode::DateModified, "Date Modified"),
+    (SortMode::Size, "Size"),
+];
+
+// File key of a directory entry, hashing is done on a background thread, so it might not be known yet.
+#[derive(Clone, Copy)]
+enum EntryKey {
+    Computing,
+    Done(Option<FileKey>),
+}
+
+type HashResult = (u64, OsString, Option<FileKey>);
+
+// entry is considered unchanged if its size and modification time are the same, file key depends on contents only
+fn is_same_file_state(a: &Metadata, b: &Metadata) -> bool {
+    match (a.modified(), b.modified()) {
+        (Ok(ma), Ok(mb)) => a.len() == b.len() && ma == mb,
+        _ => false,
+    }
+}
+
 pub struct ImguiFileBrowser {
     current_path: PathBuf,
-    contents: Vec<(OsString, Metadata, Option<(u64, u64)>)>,
+    contents: Vec<(OsString, Metadata, EntryKey)>,
+    // directory contents were listed from, keys are only reused when the same directory is listed again
+    contents_path: PathBuf,
+    // copied from config, so that rebuild doesn't need it
+    sort_mode: SortMode,
+    sort_descending: bool,
+    video_extensions: Vec<String>,
     tmp_str: String,
     tmp_path: PathBuf,
+    // queried from FileDB when "continue watching" list is opened
+    continue_watching: Vec<(String, f32)>,
+    continue_watching_open: bool,
+    // recent files with seen fraction queried from FileDB when the list is opened
+    recent_files: Vec<(PathBuf, Option<f32>)>,
+    recent_files_open: bool,
+
+    // watches current directory, so that new/removed files show up without manual refresh
+    watcher: Option<RecommendedWatcher>,
+    watcher_rx: Receiver<DebouncedEvent>,
+    watched_path: Option<PathBuf>,
+
+    // file hashing reads head and tail of every video file, which is slow on network drives, so it's done on a
+    // worker thread, results are tagged with generation and ones from previously listed directory are dropped
+    hash_generation: u64,
+    hash_cancel: Arc<AtomicBool>,
+    hash_tx: Sender<HashResult>,
+    hash_rx: Receiver<HashResult>,
 }
 
 impl ImguiFileBrowser {
-    pub fn new(fdb: &mut FileDB) -> ImguiFileBrowser {
+    pub fn new(cfg: &Config) -> ImguiFileBrowser {
+        let (tx, watcher_rx) = channel();
+        let watcher = match notify::watcher(tx, Duration::from_millis(500)) {
+            Ok(w) => Some(w),
+            Err(e) => {
+                log::error!("failed creating directory watcher: {}", e);
+                None
+            }
+        };
+        let (hash_tx, hash_rx) = channel();
         let mut res = ImguiFileBrowser {
             current_path: std::env::current_dir().unwrap(),
             contents: Vec::new(),
+            contents_path: PathBuf::new(),
+            sort_mode: cfg.sort_mode,
+            sort_descending: cfg.sort_descending,
+            video_extensions: cfg.video_extensions.clone(),
             tmp_str: String::new(),
             tmp_path: PathBuf::new(),
+            continue_watching: Vec::new(),
+            continue_watching_open: false,
+            recent_files: Vec::new(),
+            recent_files_open: false,
+            watcher,
+            watcher_rx,
+            watched_path: None,
+            hash_generation: 0,
+            hash_cancel: Arc::new(AtomicBool::new(false)),
+            hash_tx,
+            hash_rx,
         };
-        res.rebuild(fdb);
+        res.rebuild();
         res
     }
 
-    fn rebuild(&mut self, fdb: &mut FileDB) {
-        self.contents.clear();
+    // picks up settings cached from config, used when config is replaced as a whole
+    pub fn reload_config(&mut self, cfg: &Config) {
+        self.sort_mode = cfg.sort_mode;
+        self.sort_descending = cfg.sort_descending;
+        self.video_extensions.clone_from(&cfg.video_extensions);
+        self.sort();
+    }
+
+    fn watch_current_path(&mut self) {
+        let watcher = match &mut self.watcher {
+            Some(w) => w,
+            None => return,
+        };
+        if self.watched_path.as_ref() == Some(&self.current_path) {
+            return;
+        }
+        if let Some(p) = self.watched_path.take() {
+            if let Err(e) = watcher.unwatch(&p) {
+                log::error!("failed unwatching directory {}: {}", p.display(), e);
+            }
+        }
+        match watcher.watch(&self.current_path, RecursiveMode::NonRecursive) {
+            Ok(_) => self.watched_path = Some(self.current_path.clone()),
+            Err(e) => log::error!("failed watching directory {}: {}", self.current_path.display(), e),
+        }
+    }
+
+    // Drains directory watcher events and rebuilds contents if current directory was modified. Events are debounced
+    // by the watcher itself, so a file being downloaded shows up once it's done being written. Also picks up file keys
+    // computed by the hashing thread.
+    pub fn update(&mut self, fdb: &mut FileDB) {
+        while let Ok((generation, name, key)) = self.hash_rx.try_recv() {
+            if generation != self.hash_generation {
+                continue;
+            }
+            if let Some(c) = self.contents.iter_mut().find(|c| c.0 == name) {
+                c.2 = EntryKey::Done(key);
+            }
+            if let Some(key) = key {
+                if let Err(e) = fdb.preload_file(key) {
+                    log::error!("failed preloading file: {}", e);
+                }
+            }
+        }
+
+        let mut needs_rebuild = false;
+        while let Ok(ev) = self.watcher_rx.try_recv() {
+            let in_current_dir = |p: &Path| p.parent() == Some(self.current_path.as_path());
+            needs_rebuild |= match &ev {
+                DebouncedEvent::Create(p) | DebouncedEvent::Write(p) | DebouncedEvent::Remove(p) => in_current_dir(p),
+                DebouncedEvent::Rename(from, to) => in_current_dir(from) || in_current_dir(to),
+                DebouncedEvent::Rescan => true,
+                DebouncedEvent::Error(e, _) => {
+                    log::error!("directory watcher error: {}", e);
+                    false
+                }
+                _ => false,
+            };
+        }
+        if needs_rebuild {
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.watch_current_path();
+        // watcher events list the same directory again, keep keys of unchanged entries, so that only new or modified
+        // files are hashed
+        let mut previous: HashMap<OsString, (Metadata, EntryKey)> = HashMap::new();
+        if self.contents_path == self.current_path {
+            previous.extend(self.contents.drain(..).map(|(name, md, key)| (name, (md, key))));
+        } else {
+            self.contents.clear();
+            self.contents_path.clone_from(&self.current_path);
+        }
         if let Ok(rd) = std::fs::read_dir(&self.current_path) {
             for f in rd {
                 if let Ok(e) = f {
@@ -61,23 +223,63 @@ impl ImguiFileBrowser {
                         let tmp_path = &mut self.tmp_path;
                         tmp_path.clone_from(&self.current_path);
                         tmp_path.push(&file_name);
-                        let is_video = is_video_extension(tmp_path.extension());
-                        let key = if is_video {
-                            load_file_hash(&tmp_path).map(|hash| (md.len(), hash))
-                        } else {
-                            None
+                        let is_video = is_video_extension(tmp_path.extension(), &self.video_extensions);
+                        let key = match previous.remove(&file_name) {
+                            _ if !is_video => EntryKey::Done(None),
+                            Some((prev_md, prev_key)) if is_same_file_state(&prev_md, &md) => prev_key,
+                            _ => EntryKey::Computing,
                         };
                         self.contents.push((file_name, md, key));
-                        if let Some(key) = key {
-                            if let Err(e) = fdb.preload_file(key.0, key.1) {
-                                log::error!("failed preloading file: {}", e);
-                            }
-                        }
                     }
                 }
             }
         }
 
+        self.sort();
+        self.start_hashing();
+    }
+
+    // Cancels previous hashing and starts hashing video files of the current directory which don't have a key yet, in
+    // display order.
+    fn start_hashing(&mut self) {
+        self.hash_cancel.store(true, Ordering::Relaxed);
+        self.hash_cancel = Arc::new(AtomicBool::new(false));
+        self.hash_generation += 1;
+
+        let names: Vec<OsString> = self
+            .contents
+            .iter()
+            .filter(|c| matches!(c.2, EntryKey::Computing))
+            .map(|c| c.0.clone())
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        let dir = self.current_path.clone();
+        let cancel = self.hash_cancel.clone();
+        let tx = self.hash_tx.clone();
+        let generation = self.hash_generation;
+        let res = std::thread::Builder::new()
+            .name("file-hashing".to_owned())
+            .spawn(move || {
+                for name in names {
+                    if cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let key = load_file_key(dir.join(&name));
+                    if tx.send((generation, name, key)).is_err() {
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = res {
+            log::error!("failed spawning file hashing thread: {}", e);
+        }
+    }
+
+    fn sort(&mut self) {
+        let sort_mode = self.sort_mode;
+        let sort_descending = self.sort_descending;
         self.contents.sort_by(|a, b| {
             if a.1.is_dir() != b.1.is_dir() {
                 return if a.1.is_dir() {
@@ -85,9 +287,14 @@ impl ImguiFileBrowser {
                 } else {
                     std::cmp::Ordering::Greater
                 };
-            } else {
-                a.0.cmp(&b.0)
             }
+            let ord = match sort_mode {
+                SortMode::Name => std::cmp::Ordering::Equal,
+                SortMode::DateModified => a.1.modified().ok().cmp(&b.1.modified().ok()),
+                SortMode::Size => a.1.len().cmp(&b.1.len()),
+            }
+            .then_with(|| a.0.cmp(&b.0));
+            cond!(sort_descending, ord.reverse(), ord)
         });
     }
 
@@ -117,6 +324,41 @@ impl ImguiFileBrowser {
                     if ui.checkbox("Hidden Files", &mut show_hidden_files) {
                         config_syncer.get_mut().show_hidden_files = show_hidden_files;
                     }
+                    ui.same_line();
+                    let mut sort_changed = false;
+                    {
+                        let _token = ui.push_item_width(120.0);
+                        let preview = SORT_MODES
+                            .iter()
+                            .find(|(m, _)| *m == self.sort_mode)
+                            .map(|(_, name)| *name)
+                            .unwrap_or("");
+                        imgui::ComboBox::new("##sort-mode")
+                            .preview_value(preview)
+                            .build(ui, || {
+                                for (mode, name) in SORT_MODES {
+                                    let selected = *mode == self.sort_mode;
+                                    if imgui::Selectable::new(name).selected(selected).build(ui) && !selected {
+                                        self.sort_mode = *mode;
+                                        sort_changed = true;
+                                    }
+                                }
+                            });
+                    }
+                    ui.same_line();
+                    if ui.small_button(cond!(self.sort_descending, fa::ARROW_DOWN, fa::ARROW_UP)) {
+                        self.sort_descending = !self.sort_descending;
+                        sort_changed = true;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(cond!(self.sort_descending, "Descending", "Ascending"));
+                    }
+                    if sort_changed {
+                        let cfg_mut = config_syncer.get_mut();
+                        cfg_mut.sort_mode = self.sort_mode;
+                        cfg_mut.sort_descending = self.sort_descending;
+                        self.sort();
+                    }
                 }
                 // current path line
                 {
@@ -149,13 +391,103 @@ impl ImguiFileBrowser {
                             self.current_path.pop();
                             num_elements -= 1;
                         }
-                        self.rebuild(fdb);
+                        self.rebuild();
+                    }
+                }
+
+                // continue watching
+                {
+                    ui.same_line_with_pos(ui.window_content_region_width() - 125.0);
+                    let _token = ui.push_style_var(imgui::StyleVar::FramePadding([0.0, 0.0]));
+                    ui.set_next_item_width(20.0);
+                    let opened = imgui::ComboBox::new("##continue-watching")
+                        .preview_value(fa::HOURGLASS_HALF)
+                        .flags(imgui::ComboBoxFlags::NO_ARROW_BUTTON | imgui::ComboBoxFlags::HEIGHT_LARGE)
+                        .build(ui, || {
+                            if !self.continue_watching_open {
+                                let cfg = config_syncer.get();
+                                let min = cfg.continue_watching_min_percent / 100.0;
+                                let max = cfg.continue_watching_max_percent / 100.0;
+                                self.continue_watching = match fdb.partially_seen_files(min, max, 20) {
+                                    Ok(v) => v,
+                                    Err(e) => {
+                                        log::error!("failed querying partially seen files: {}", e);
+                                        Vec::new()
+                                    }
+                                };
+                            }
+                            if self.continue_watching.is_empty() {
+                                ui.text_disabled("Nothing to continue");
+                            }
+                            for (path, fraction) in &self.continue_watching {
+                                let tmp_str = &mut self.tmp_str;
+                                tmp_str.clear();
+                                write!(tmp_str, "{:3.0}%  {}", fraction * 100.0, path).unwrap();
+                                if imgui::Selectable::new(tmp_str)
+                                    .disabled(!Path::new(path).exists())
+                                    .build(ui)
+                                {
+                                    action_bin.put(Action::Command(vec!["loadfile".to_owned(), path.clone()]));
+                                }
+                            }
+                        });
+                    self.continue_watching_open = opened.is_some();
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Continue watching");
+                    }
+                }
+
+                // recent files
+                {
+                    ui.same_line();
+                    let _token = ui.push_style_var(imgui::StyleVar::FramePadding([0.0, 0.0]));
+                    let cfg = config_syncer.get();
+                    ui.set_next_item_width(20.0);
+                    let opened = imgui::ComboBox::new("##recent")
+                        .preview_value(fa::HISTORY)
+                        .flags(imgui::ComboBoxFlags::NO_ARROW_BUTTON | imgui::ComboBoxFlags::HEIGHT_LARGE)
+                        .build(ui, || {
+                            if !self.recent_files_open {
+                                self.recent_files = cfg
+                                    .recent_files
+                                    .iter()
+                                    .map(|p| {
+                                        let seen = match fdb.find_file_by_path(&p.to_string_lossy()) {
+                                            Ok(key) => key.and_then(|k| fdb.get_file(k)).map(|d| d.seen_fraction()),
+                                            Err(e) => {
+                                                log::error!("failed looking up file by path: {}", e);
+                                                None
+                                            }
+                                        };
+                                        (p.clone(), seen)
+                                    })
+                                    .collect();
+                            }
+                            for (p, seen) in &self.recent_files {
+                                let tmp_str = &mut self.tmp_str;
+                                tmp_str.clear();
+                                if let Some(seen) = seen {
+                                    write!(tmp_str, "{:3.0}%  ", seen * 100.0).unwrap();
+                                }
+                                write!(tmp_str, "{}", p.to_string_lossy()).unwrap();
+                                // stale entries stay in the list, but can't be clicked
+                                if imgui::Selectable::new(tmp_str).disabled(!p.exists()).build(ui) {
+                                    action_bin.put(Action::Command(vec![
+                                        "loadfile".to_owned(),
+                                        p.to_string_lossy().to_string(),
+                                    ]));
+                                }
+                            }
+                        });
+                    self.recent_files_open = opened.is_some();
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Recent files");
                     }
                 }
 
                 // favorites
                 {
-                    ui.same_line_with_pos(ui.window_content_region_width() - 65.0);
+                    ui.same_line();
                     let favidx = {
                         let cfg = config_syncer.get();
                         cfg.favorite_directories.iter().position(|pp| pp == &self.current_path)
@@ -192,7 +524,7 @@ impl ImguiFileBrowser {
                                 for dir in &cfg.favorite_directories {
                                     if imgui::Selectable::new(dir.to_string_lossy()).build(ui) {
                                         self.current_path.clone_from(dir);
-                                        self.rebuild(fdb);
+                                        self.rebuild();
                                     }
                                 }
                             });
@@ -214,7 +546,7 @@ impl ImguiFileBrowser {
 
                         if imgui::Selectable::new(tmp_str).build(ui) {
                             self.current_path.pop();
-                            self.rebuild(fdb);
+                            self.rebuild();
                         }
                     }
 
@@ -225,7 +557,11 @@ impl ImguiFileBrowser {
 
                     // render ui for entries
                     for c in &self.contents {
-                        let is_seen = c.2.and_then(|k| fdb.get_file(k).map(|_| true)).unwrap_or(false);
+                        let is_computing = matches!(c.2, EntryKey::Computing);
+                        let is_seen = match c.2 {
+                            EntryKey::Done(Some(k)) => fdb.get_file(k).is_some(),
+                            _ => false,
+                        };
                         let name = c.0.to_string_lossy();
                         {
                             let tmp_str = &mut self.tmp_str;
@@ -244,7 +580,7 @@ impl ImguiFileBrowser {
                                 None
                             } else {
                                 let p: &std::path::Path = c.0.as_ref();
-                                let is_video = is_video_extension(p.extension());
+                                let is_video = is_video_extension(p.extension(), &self.video_extensions);
                                 if show_video_files_only && !is_video {
                                     continue;
                                 }
@@ -252,6 +588,8 @@ impl ImguiFileBrowser {
                                 write!(tmp_str, "{}  ", icon).unwrap();
                                 if is_seen {
                                     write!(tmp_str, "{} ", fa::EYE).unwrap();
+                                } else if is_computing {
+                                    write!(tmp_str, "{} ", fa::HOURGLASS_HALF).unwrap();
                                 }
                                 write!(tmp_str, "{}", name).unwrap();
                                 is_video.then(|| {
@@ -263,6 +601,9 @@ impl ImguiFileBrowser {
                             };
                             imgui::Selectable::new(tmp_str).build(ui)
                         };
+                        if is_computing && ui.is_item_hovered() {
+                            ui.tooltip_text("Computing file hash...");
+                        }
                         if clicked {
                             if is_dir {
                                 clicked_dir = Some(c.0.clone());
@@ -275,7 +616,7 @@ impl ImguiFileBrowser {
                     // event processing
                     if let Some(clicked_dir) = clicked_dir {
                         self.current_path.push(clicked_dir);
-                        self.rebuild(fdb);
+                        self.rebuild();
                     } else if let Some(clicked_file) = clicked_file {
                         let mut p = self.current_path.clone();
                         p.push(clicked_file);
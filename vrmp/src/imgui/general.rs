@@ -1,12 +1,15 @@
 use std::fmt::Write;
 
+use glam::Vec2;
 use imgui::StyleColor;
 
 use crate::action::{Action, ActionBin};
-use crate::config::ConfigSyncer;
-use crate::enums::{AspectRatio, Mode, Projection};
-use crate::filedb::FileData;
+use crate::config::{default_background_color, default_world_scale, ConfigSyncer};
+use crate::enums::{AspectRatio, DebugMode, EyeFilter, Mode, PresentMode, Projection};
+use crate::filedb::{default_er180_v_fov, FileData};
+use crate::timeline::{write_hms, Timeline};
 use crate::tracks::{Track, Tracks};
+use crate::vrinfo::{MAX_SUPERSAMPLE_FACTOR, MAX_WORLD_SCALE, MIN_SUPERSAMPLE_FACTOR, MIN_WORLD_SCALE};
 
 use super::font_awesome as fa;
 
@@ -14,15 +17,76 @@ use super::util::{hex, iter_bit_spans};
 
 use indoc::indoc;
 
+// mpv defaults, reset buttons of subtitle sliders go back to these
+const DEFAULT_SUB_SCALE: f64 = 1.0;
+const DEFAULT_SUB_POS: i64 = 100;
+
+// Generated source for measuring A/V latency: 100ms white flash together with 1kHz beep once per second.
+const AV_SYNC_TEST_SOURCE: &str = concat!(
+    "av://lavfi:",
+    "color=c=black:s=640x360:r=60,geq=lum='if(lt(mod(T,1),0.1),235,16)':cb=128:cr=128[out0];",
+    "aevalsrc=exprs='if(lt(mod(t,1),0.1),0.5*sin(2*PI*1000*t),0)':s=48000[out1]",
+);
+
+const DEBUG_MODES: &[(DebugMode, &str)] = &[
+    (DebugMode::Off, "Off"),
+    (DebugMode::Uv, "UV"),
+    (DebugMode::EyeIndex, "Eye Index"),
+    (DebugMode::Depth, "Depth"),
+    (DebugMode::RayDirection, "Ray Direction"),
+];
+
+// (projection, button label, tooltip), shared by video settings and quick projection picker
+const PROJECTIONS: &[(Projection, &str, &str)] = &[
+    (Projection::Er360, "ER 360", "Equirectangular 360°"),
+    (Projection::Er180, "ER 180", "Equirectangular 180°"),
+    (Projection::Fisheye, "Fisheye", "Fisheye 180°"),
+    (Projection::Eac, "EAC", "Equi-Angular Cubemap"),
+    (Projection::Flat, "Flat", "Flat Screen"),
+];
+
+const EYE_FILTERS: &[(EyeFilter, &str)] = &[
+    (EyeFilter::Both, "Both"),
+    (EyeFilter::LeftOnly, "Left Only"),
+    (EyeFilter::RightOnly, "Right Only"),
+];
+
+pub const MIN_SPEED: f64 = 0.25;
+pub const MAX_SPEED: f64 = 4.0;
+
 pub struct General {
     pub percent_pos: f64,
-    pub duration: u32,
-    pub shader_debug: f32,
+    pub debug_mode: DebugMode,
+    pub eye_filter: EyeFilter,
     pub show_demo: bool,
+    pub show_stats: bool,
     pub playing: bool,
     pub hwdec: String,
     pub hwdec_current: String,
+    // current video bitrate in bits per second as reported by mpv
+    pub video_bitrate: Option<f64>,
+    // playback is stalled until cache fills up to cache_buffering_state percent, see render_buffering
+    pub paused_for_cache: bool,
+    pub cache_buffering_state: i64,
+    // seconds of media ahead of the playback position which are already downloaded, streams and network files only
+    pub demuxer_cache_duration: Option<f64>,
+    // mpv color adjustments as observed, in -100..100 range
+    pub brightness: i64,
+    pub contrast: i64,
+    pub gamma: i64,
+    pub saturation: i64,
+    pub deinterlace: bool,
+    pub speed: f64,
+    pub sub_scale: f64,
+    pub sub_pos: i64,
+    pub volume: f64,
+    pub loop_file: bool,
+    // mirrors Global::force_mono
+    pub force_mono: bool,
+    // mirrors Global::ui_pinned
+    pub ui_pinned: bool,
 
+    reset_forget_files: bool,
     tmp_str: String,
 }
 
@@ -30,21 +94,164 @@ impl General {
     pub fn new() -> General {
         General {
             percent_pos: 0.0,
-            duration: 0,
-            shader_debug: 0.0,
+            debug_mode: DebugMode::Off,
+            eye_filter: EyeFilter::Both,
             show_demo: false,
+            show_stats: false,
             playing: false,
             hwdec: String::new(),
             hwdec_current: String::new(),
+            video_bitrate: None,
+            paused_for_cache: false,
+            cache_buffering_state: 0,
+            demuxer_cache_duration: None,
+            brightness: 0,
+            contrast: 0,
+            gamma: 0,
+            saturation: 0,
+            deinterlace: false,
+            speed: 1.0,
+            sub_scale: DEFAULT_SUB_SCALE,
+            sub_pos: DEFAULT_SUB_POS,
+            volume: 100.0,
+            loop_file: false,
+            force_mono: false,
+            ui_pinned: false,
 
+            reset_forget_files: false,
             tmp_str: String::new(),
         }
     }
 
+    // seek slider with a time tooltip on hover, item rect is the slider's after the call
+    fn seek_slider(&mut self, action_bin: &mut ActionBin, timeline: &Timeline, ui: &imgui::Ui, width: f32) {
+        let mut value = self.percent_pos;
+        ui.set_next_item_width(width);
+        if imgui::Slider::new("##seek", 0.0, 100.0)
+            .display_format("")
+            .build(ui, &mut value)
+        {
+            if self.percent_pos != value {
+                action_bin.put(Action::Command(vec![
+                    "seek".to_owned(),
+                    format!("{}", value),
+                    "absolute-percent".to_owned(),
+                ]));
+                self.percent_pos = value;
+            }
+        }
+        let [x0, _] = ui.item_rect_min();
+        let [w, _] = ui.item_rect_size();
+        if ui.is_item_hovered() {
+            let [mx, _] = ui.io().mouse_pos;
+            let rx = mx - x0;
+            let fr = (rx / w.max(1.0)).clamp(0.0, 1.0); // clamp to be sure
+            let p = fr * 100.0;
+            let tmp_str = &mut self.tmp_str;
+            tmp_str.clear();
+            write_hms(tmp_str, timeline.secs_at(fr as f64));
+            write!(tmp_str, " ({:.2}%)", p).unwrap();
+            ui.tooltip_text(tmp_str);
+        }
+    }
+
+    // Minimal alternative to the full UI: play/pause, seek and volume in a single bar.
+    pub fn render_mini_bar(
+        &mut self,
+        action_bin: &mut ActionBin,
+        timeline: &Timeline,
+        ui: &imgui::Ui,
+        position: [f32; 2],
+        size: [f32; 2],
+    ) {
+        let volume_w = 150.0;
+        imgui::Window::new("Mini Bar")
+            .flags(imgui::WindowFlags::NO_RESIZE | imgui::WindowFlags::NO_TITLE_BAR)
+            .position(position, imgui::Condition::FirstUseEver)
+            .size(size, imgui::Condition::FirstUseEver)
+            .build(ui, || {
+                let _token = ui.push_style_var(imgui::StyleVar::FramePadding([4.0, 15.0]));
+                if ui.button_with_size(cond!(self.playing, fa::PAUSE, fa::PLAY), [60.0, 0.0]) {
+                    action_bin.put(Action::Command(vec!["cycle".to_owned(), "pause".to_owned()]));
+                }
+                ui.same_line();
+                let [avail_w, _] = ui.content_region_avail();
+                let [spacing_x, _] = ui.clone_style().item_spacing;
+                let volume_icon_w = ui.calc_text_size(fa::VOLUME_UP)[0];
+                self.seek_slider(
+                    action_bin,
+                    timeline,
+                    ui,
+                    avail_w - volume_w - volume_icon_w - 2.0 * spacing_x,
+                );
+                ui.same_line();
+                ui.text(fa::VOLUME_UP);
+                ui.same_line();
+                let mut volume = self.volume;
+                ui.set_next_item_width(volume_w);
+                if imgui::Slider::new("##volume", 0.0, 100.0)
+                    .display_format("%.0f")
+                    .build(ui, &mut volume)
+                    && volume != self.volume
+                {
+                    // set locally as well, so that slider doesn't jump back while waiting for mpv
+                    self.volume = volume;
+                    action_bin.put(Action::Command(vec![
+                        "set".to_owned(),
+                        "volume".to_owned(),
+                        format!("{}", volume),
+                    ]));
+                }
+            });
+    }
+
+    // Projection list only, picking one closes it, see Action::ToggleQuickProjection.
+    pub fn render_quick_projection(
+        &mut self,
+        action_bin: &mut ActionBin,
+        fdata: Option<&mut FileData>,
+        ui: &imgui::Ui,
+        position: [f32; 2],
+        size: [f32; 2],
+    ) {
+        imgui::Window::new("Quick Projection")
+            .flags(imgui::WindowFlags::NO_RESIZE | imgui::WindowFlags::NO_TITLE_BAR)
+            .position(position, imgui::Condition::FirstUseEver)
+            .size(size, imgui::Condition::FirstUseEver)
+            .build(ui, || {
+                let _token = ui.push_style_var(imgui::StyleVar::FramePadding([4.0, 15.0]));
+                let fdata = match fdata {
+                    Some(fdata) => fdata,
+                    None => {
+                        ui.text_disabled("No file loaded");
+                        return;
+                    }
+                };
+                ui.text("Projection:");
+                let [avail_w, _] = ui.content_region_avail();
+                for &(m, label, tooltip) in PROJECTIONS {
+                    let _token = (fdata.projection == m).then(|| {
+                        (
+                            ui.push_style_color(StyleColor::Button, hex("#816300")),
+                            ui.push_style_color(StyleColor::ButtonHovered, hex("#AE9400")),
+                        )
+                    });
+                    if ui.button_with_size(label, [avail_w, 0.0]) {
+                        fdata.projection = m;
+                        action_bin.put(Action::ToggleQuickProjection);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(tooltip);
+                    }
+                }
+            });
+    }
+
     pub fn render(
         &mut self,
         action_bin: &mut ActionBin,
         config_syncer: &mut ConfigSyncer,
+        timeline: &Timeline,
         tracks: Option<&Tracks>,
         mut fdata: Option<&mut FileData>,
         ui: &imgui::Ui,
@@ -59,45 +266,53 @@ impl General {
             .build(ui, || {
                 if ui.collapsing_header("Playback", imgui::TreeNodeFlags::DEFAULT_OPEN) {
                     let _token = ui.push_style_var(imgui::StyleVar::FramePadding([4.0, 15.0]));
+                    if ui.button_with_size(fa::STEP_BACKWARD, [40.0, 0.0]) {
+                        action_bin.put(Action::PreviousFile);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Previous file in directory");
+                    }
+                    ui.same_line();
                     if ui.button_with_size(cond!(self.playing, fa::PAUSE, fa::PLAY), [60.0, 0.0]) {
                         action_bin.put(Action::Command(vec!["cycle".to_owned(), "pause".to_owned()]));
                     }
-
                     ui.same_line();
-
-                    let mut value = self.percent_pos;
-                    ui.set_next_item_width(-1.0);
-                    if imgui::Slider::new("##seek", 0.0, 100.0)
-                        .display_format("")
-                        .build(ui, &mut value)
+                    if ui.button_with_size(fa::STEP_FORWARD, [40.0, 0.0]) {
+                        action_bin.put(Action::NextFile);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Next file in directory");
+                    }
+                    ui.same_line();
                     {
-                        if self.percent_pos != value {
+                        let _token = self.loop_file.then(|| {
+                            (
+                                ui.push_style_color(StyleColor::Button, hex("#816300")),
+                                ui.push_style_color(StyleColor::ButtonHovered, hex("#AE9400")),
+                            )
+                        });
+                        if ui.button_with_size(cond!(self.loop_file, fa::REDO, fa::LONG_ARROW_ALT_RIGHT), [40.0, 0.0]) {
                             action_bin.put(Action::Command(vec![
-                                "seek".to_owned(),
-                                format!("{}", value),
-                                "absolute-percent".to_owned(),
+                                "set".to_owned(),
+                                "loop-file".to_owned(),
+                                cond!(self.loop_file, "no", "inf").to_owned(),
                             ]));
-                            self.percent_pos = value;
                         }
                     }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(cond!(
+                            self.loop_file,
+                            "Looping current file, remembered for this file",
+                            "Loop current file, remembered for this file"
+                        ));
+                    }
+
+                    ui.same_line();
+
+                    self.seek_slider(action_bin, timeline, ui, -1.0);
                     let [x0, _] = ui.item_rect_min();
                     let [_, y1] = ui.item_rect_max();
                     let [w, _] = ui.item_rect_size();
-                    if ui.is_item_hovered() {
-                        let [mx, _] = ui.io().mouse_pos;
-                        let rx = mx - x0;
-                        let fr = (rx / w.max(1.0)).clamp(0.0, 1.0); // clamp to be sure
-                        let p = fr * 100.0;
-                        let dur = std::time::Duration::from_secs(self.duration as u64);
-                        let cdur = dur.mul_f32(fr);
-                        let seconds = cdur.as_secs() % 60;
-                        let minutes = (cdur.as_secs() / 60) % 60;
-                        let hours = (cdur.as_secs() / 60) / 60;
-                        let tmp_str = &mut self.tmp_str;
-                        tmp_str.clear();
-                        write!(tmp_str, "{:02}:{:02}:{:02} ({:.2}%)", hours, minutes, seconds, p).unwrap();
-                        ui.tooltip_text(tmp_str);
-                    }
 
                     if let Some(fdata) = fdata.as_deref_mut() {
                         let dl = ui.get_window_draw_list();
@@ -138,6 +353,43 @@ impl General {
                             .build();
                         });
                     }
+
+                    // elapsed / total, under the seek bar
+                    let tmp_str = &mut self.tmp_str;
+                    tmp_str.clear();
+                    timeline.write_pos(tmp_str);
+                    tmp_str.push_str(" / ");
+                    timeline.write_duration(tmp_str);
+                    ui.set_cursor_screen_pos([x0, ui.cursor_screen_pos()[1]]);
+                    ui.text(tmp_str);
+
+                    // SPEED
+                    if ui.button(fa::UNDO) && self.speed != 1.0 {
+                        self.speed = 1.0;
+                        action_bin.put(Action::Command(vec![
+                            "set".to_owned(),
+                            "speed".to_owned(),
+                            "1".to_owned(),
+                        ]));
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Reset");
+                    }
+                    ui.same_line();
+                    let mut speed = self.speed;
+                    if imgui::Slider::new("Speed", MIN_SPEED, MAX_SPEED)
+                        .display_format("%.2fx")
+                        .build(ui, &mut speed)
+                        && speed != self.speed
+                    {
+                        // set locally as well, so that slider doesn't jump back while waiting for mpv
+                        self.speed = speed;
+                        action_bin.put(Action::Command(vec![
+                            "set".to_owned(),
+                            "speed".to_owned(),
+                            format!("{}", speed),
+                        ]));
+                    }
                 }
 
                 if ui.collapsing_header("Video Settings", imgui::TreeNodeFlags::empty()) {
@@ -145,7 +397,7 @@ impl General {
                     if let Some(fdata) = fdata.as_deref_mut() {
                         ui.align_text_to_frame_padding();
                         ui.text("Projection:");
-                        let mut projection_button = |label: &str, m: Projection, tooltip: &str| {
+                        for &(m, label, tooltip) in PROJECTIONS {
                             ui.same_line();
                             let _token = (fdata.projection == m).then(|| {
                                 (
@@ -160,12 +412,46 @@ impl General {
                             if ui.is_item_hovered() {
                                 ui.tooltip_text(tooltip);
                             }
-                        };
-                        projection_button("ER 360", Projection::Er360, "Equirectangular 360°");
-                        projection_button("ER 180", Projection::Er180, "Equirectangular 180°");
-                        projection_button("Fisheye", Projection::Fisheye, "Fisheye 180°");
-                        projection_button("EAC", Projection::Eac, "Equi-Angular Cubemap");
-                        projection_button("Flat", Projection::Flat, "Flat Screen");
+                        }
+                        ui.same_line();
+                        if ui.button(fa::TRASH) {
+                            action_bin.put(Action::ForgetCurrentFile);
+                        }
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Forget all stored settings of this file");
+                        }
+                        ui.same_line();
+                        if ui.button(fa::HISTORY) {
+                            action_bin.put(Action::Undo);
+                        }
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Undo last change of projection, mode, convergence or screen (Ctrl+Z)");
+                        }
+                    }
+
+                    // PRESET
+                    if let Some(fdata) = fdata.as_deref_mut() {
+                        let presets = &config_syncer.get().projection_presets;
+                        if !presets.is_empty() {
+                            ui.align_text_to_frame_padding();
+                            ui.text("Preset:");
+                            ui.same_line();
+                            ui.set_next_item_width(200.0);
+                            imgui::ComboBox::new("##projection-preset")
+                                .preview_value("Apply...")
+                                .build(ui, || {
+                                    for preset in presets {
+                                        if imgui::Selectable::new(&preset.name).build(ui) {
+                                            fdata.apply_preset(preset);
+                                        }
+                                    }
+                                });
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text(
+                                    "Set projection, mode, convergence and FOV at once, edit in config.ron",
+                                );
+                            }
+                        }
                     }
 
                     // MODE
@@ -197,6 +483,37 @@ impl General {
                         if ui.button_with_size(fa::EXCHANGE_ALT, [80.0, 0.0]) {
                             fdata.flip_eyes();
                         }
+                        ui.same_line();
+                        let mut force_mono = self.force_mono;
+                        if ui.checkbox("Force Mono", &mut force_mono) {
+                            action_bin.put(Action::ToggleForceMono);
+                        }
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Show as mono until the next file, without changing the saved mode");
+                        }
+
+                        ui.align_text_to_frame_padding();
+                        ui.text("Flip:");
+                        ui.same_line();
+                        ui.checkbox("Horizontal##flip", &mut fdata.flip_horizontal);
+                        ui.same_line();
+                        ui.checkbox("Vertical##flip", &mut fdata.flip_vertical);
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Correct mirrored footage, each eye is flipped in place");
+                        }
+                    }
+
+                    // DEINTERLACE
+                    {
+                        let mut deinterlace = self.deinterlace;
+                        if ui.checkbox("Deinterlace", &mut deinterlace) {
+                            self.deinterlace = deinterlace;
+                            action_bin.put(Action::Command(vec![
+                                "set".to_owned(),
+                                "deinterlace".to_owned(),
+                                cond!(deinterlace, "yes", "no").to_owned(),
+                            ]));
+                        }
                     }
 
                     // FLAT SCREEN
@@ -217,6 +534,23 @@ impl General {
                                     .build(&ui, &mut fdata.flat_distance);
                             }
                             ui.align_text_to_frame_padding();
+                            ui.text("Screen Offset:");
+                            ui.same_line();
+                            {
+                                let tmp_str = &mut self.tmp_str;
+                                tmp_str.clear();
+                                write!(tmp_str, "{:.3}m", fdata.flat_offset).unwrap();
+
+                                imgui::Drag::new("##flat_offset")
+                                    .range(-20.0, 20.0)
+                                    .speed(0.01)
+                                    .display_format(tmp_str)
+                                    .build(&ui, &mut fdata.flat_offset);
+                            }
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Without UI: Shift + left mouse button drag moves the screen");
+                            }
+                            ui.align_text_to_frame_padding();
                             ui.text("Screen Scale:");
                             ui.same_line();
                             {
@@ -225,6 +559,81 @@ impl General {
                                     .speed(0.01)
                                     .build(&ui, &mut fdata.flat_scale);
                             }
+                            ui.align_text_to_frame_padding();
+                            ui.text("Zoom:");
+                            ui.same_line();
+                            {
+                                let mut flat_zoom = fdata.flat_zoom;
+                                if imgui::Drag::new("##flat_zoom")
+                                    .range(1.0, 10.0)
+                                    .speed(0.01)
+                                    .display_format("%.2fx")
+                                    .build(&ui, &mut flat_zoom)
+                                {
+                                    fdata.set_flat_zoom_pan(flat_zoom, fdata.flat_pan);
+                                }
+                            }
+                            ui.align_text_to_frame_padding();
+                            ui.text("Pan:");
+                            ui.same_line();
+                            {
+                                let mut flat_pan = fdata.flat_pan.to_array();
+                                if imgui::Drag::new("##flat_pan")
+                                    .range(-0.5, 0.5)
+                                    .speed(0.001)
+                                    .build_array(&ui, &mut flat_pan)
+                                {
+                                    fdata.set_flat_zoom_pan(fdata.flat_zoom, flat_pan.into());
+                                }
+                            }
+                            ui.same_line();
+                            if ui.button("Reset##flat_zoom_pan") {
+                                fdata.set_flat_zoom_pan(1.0, Vec2::ZERO);
+                            }
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Without UI: mouse wheel zooms, middle mouse button drag pans");
+                            }
+                            if ui.button("Snap to Default") {
+                                fdata.flat_distance = config_syncer.get().flat_distance;
+                                fdata.flat_scale = config_syncer.get().flat_scale;
+                                fdata.flat_offset = 0.0;
+                                // screen is placed relative to world origin, bring it in front of the viewer
+                                action_bin.put(Action::ResetWorldOrigin);
+                            }
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Restore default screen distance and scale, and center the screen");
+                            }
+                            ui.same_line();
+                            if ui.button("Face Me") {
+                                action_bin.put(Action::RecenterScreen);
+                            }
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Move the screen and UI in front of you (C)");
+                            }
+                        }
+                    }
+
+                    // ORIENTATION
+                    if let Some(fdata) = fdata.as_deref_mut() {
+                        if fdata.projection != Projection::Flat {
+                            ui.align_text_to_frame_padding();
+                            ui.text("Orientation:");
+                            ui.same_line();
+                            if ui.button("Save Current") {
+                                action_bin.put(Action::SaveFileOrientation);
+                            }
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Look where the video should be centered, it's restored on every load");
+                            }
+                            if fdata.saved_orientation.is_some() {
+                                ui.same_line();
+                                if ui.button("Clear##orientation") {
+                                    fdata.saved_orientation = None;
+                                }
+                                if ui.is_item_hovered() {
+                                    ui.tooltip_text("Use regular world origin for this file again");
+                                }
+                            }
                         }
                     }
 
@@ -233,7 +642,7 @@ impl General {
                         if fdata.projection == Projection::Flat {
                             ui.align_text_to_frame_padding();
                             ui.text("Aspect Ratio:");
-                            let mut aspect_button = |label: &str, v: AspectRatio| {
+                            let mut aspect_button = |v: AspectRatio| {
                                 ui.same_line();
                                 let _token = (fdata.aspect_ratio == v).then(|| {
                                     (
@@ -241,13 +650,45 @@ impl General {
                                         ui.push_style_color(StyleColor::ButtonHovered, hex("#AE9400")),
                                     )
                                 });
-                                if ui.button(label) {
+                                if ui.button(v.label()) {
                                     fdata.aspect_ratio = v;
                                 }
                             };
-                            aspect_button("1/2", AspectRatio::Half);
-                            aspect_button("1", AspectRatio::One);
-                            aspect_button("2", AspectRatio::Two);
+                            aspect_button(AspectRatio::Half);
+                            aspect_button(AspectRatio::One);
+                            aspect_button(AspectRatio::Two);
+                        }
+                    }
+
+                    // ER 180 COVERAGE
+                    if let Some(fdata) = fdata.as_deref_mut() {
+                        if fdata.projection == Projection::Er180 {
+                            ui.align_text_to_frame_padding();
+                            ui.text("Vertical FOV:");
+                            ui.same_line();
+                            imgui::Slider::new("##er180_v_fov", 90.0, 180.0)
+                                .display_format("%.1f°")
+                                .build(&ui, &mut fdata.er180_v_fov);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Vertical coverage of the video, area outside of it is left empty");
+                            }
+                            ui.same_line();
+                            if ui.button("Reset##er180_v_fov") {
+                                fdata.er180_v_fov = default_er180_v_fov();
+                            }
+                            ui.align_text_to_frame_padding();
+                            ui.text("Pitch Offset:");
+                            ui.same_line();
+                            imgui::Slider::new("##er180_pitch_offset", -45.0, 45.0)
+                                .display_format("%.1f°")
+                                .build(&ui, &mut fdata.er180_pitch_offset);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Moves the picture up (positive value) or down (negative value)");
+                            }
+                            ui.same_line();
+                            if ui.button("Reset##er180_pitch_offset") {
+                                fdata.er180_pitch_offset = 0.0;
+                            }
                         }
                     }
 
@@ -358,6 +799,21 @@ impl General {
                     let vid = tracks.as_ref().map(|v| v.vid).unwrap_or(0);
                     let aid = tracks.as_ref().map(|v| v.aid).unwrap_or(0);
                     let sid = tracks.as_ref().map(|v| v.sid).unwrap_or(0);
+                    if let Some(bitrate) = self.video_bitrate {
+                        let tmp_str = &mut self.tmp_str;
+                        tmp_str.clear();
+                        write!(tmp_str, "Video Bitrate: {:.0} kbps", bitrate / 1000.0).unwrap();
+                        ui.text(tmp_str);
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("For adaptive streams pick a video track below to switch quality");
+                        }
+                    }
+                    if let Some(cached) = self.demuxer_cache_duration {
+                        let tmp_str = &mut self.tmp_str;
+                        tmp_str.clear();
+                        write!(tmp_str, "Cached Ahead: {:.1}s", cached).unwrap();
+                        ui.text(tmp_str);
+                    }
                     let track_to_str = |t: &Track, str: &mut String| {
                         str.clear();
                         write!(str, "{}", t.id).unwrap();
@@ -375,6 +831,12 @@ impl General {
                             *str += &t.codec;
                             *str += ")";
                         }
+                        if t.width > 0 && t.height > 0 {
+                            write!(str, " {}x{}", t.width, t.height).unwrap();
+                        }
+                        if t.bitrate > 0 {
+                            write!(str, " {} kbps", t.bitrate / 1000).unwrap();
+                        }
                     };
                     let mut track_list = |title: &str, idx: i64, list: Option<&[Track]>| -> Option<i64> {
                         imgui::TreeNode::new(title)
@@ -413,15 +875,167 @@ impl General {
                             format!("{}", sid),
                         ]));
                     }
+
+                    const SUBTITLES_TOOLTIP: &str = "Subtitles are rendered by mpv into the video frame, on 180/360 \
+                                                     projections they stay at a fixed spot of the picture";
+                    {
+                        let _id = ui.push_id("sub-scale");
+                        if ui.button(fa::UNDO) && self.sub_scale != DEFAULT_SUB_SCALE {
+                            action_bin.put(Action::Command(vec![
+                                "set".to_owned(),
+                                "sub-scale".to_owned(),
+                                format!("{}", DEFAULT_SUB_SCALE),
+                            ]));
+                        }
+                        ui.same_line();
+                        let mut sub_scale = self.sub_scale;
+                        if imgui::Slider::new("Subtitle Scale", 0.5, 4.0)
+                            .display_format("%.2f")
+                            .build(ui, &mut sub_scale)
+                            && sub_scale != self.sub_scale
+                        {
+                            // set locally as well, so that slider doesn't jump back while waiting for mpv
+                            self.sub_scale = sub_scale;
+                            action_bin.put(Action::Command(vec![
+                                "set".to_owned(),
+                                "sub-scale".to_owned(),
+                                format!("{}", sub_scale),
+                            ]));
+                        }
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text(SUBTITLES_TOOLTIP);
+                        }
+                    }
+                    {
+                        let _id = ui.push_id("sub-pos");
+                        if ui.button(fa::UNDO) && self.sub_pos != DEFAULT_SUB_POS {
+                            action_bin.put(Action::Command(vec![
+                                "set".to_owned(),
+                                "sub-pos".to_owned(),
+                                format!("{}", DEFAULT_SUB_POS),
+                            ]));
+                        }
+                        ui.same_line();
+                        let mut sub_pos = self.sub_pos as i32;
+                        if imgui::Slider::new("Subtitle Position", 0, 150).build(ui, &mut sub_pos)
+                            && sub_pos as i64 != self.sub_pos
+                        {
+                            self.sub_pos = sub_pos as i64;
+                            action_bin.put(Action::Command(vec![
+                                "set".to_owned(),
+                                "sub-pos".to_owned(),
+                                format!("{}", sub_pos),
+                            ]));
+                        }
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text(SUBTITLES_TOOLTIP);
+                        }
+                    }
+                }
+
+                if ui.collapsing_header("Color", imgui::TreeNodeFlags::empty()) {
+                    let mut color_slider = |label: &str, property: &str, value: &mut i64| {
+                        let _id = ui.push_id(property);
+                        if ui.button(fa::UNDO) && *value != 0 {
+                            *value = 0;
+                            action_bin.put(Action::Command(vec![
+                                "set".to_owned(),
+                                property.to_owned(),
+                                "0".to_owned(),
+                            ]));
+                        }
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Reset");
+                        }
+                        ui.same_line();
+                        let mut v = *value as i32;
+                        if imgui::Slider::new(label, -100, 100).build(ui, &mut v) && v as i64 != *value {
+                            // set locally as well, so that slider doesn't jump back while waiting for mpv
+                            *value = v.clamp(-100, 100) as i64;
+                            action_bin.put(Action::Command(vec![
+                                "set".to_owned(),
+                                property.to_owned(),
+                                format!("{}", *value),
+                            ]));
+                        }
+                    };
+                    color_slider("Brightness", "brightness", &mut self.brightness);
+                    color_slider("Contrast", "contrast", &mut self.contrast);
+                    color_slider("Gamma", "gamma", &mut self.gamma);
+                    color_slider("Saturation", "saturation", &mut self.saturation);
+
+                    // PER-EYE GAIN
+                    if let Some(fdata) = fdata.as_deref_mut() {
+                        let eye_gain_slider = |label: &str, value: &mut f32| {
+                            let _id = ui.push_id(label);
+                            if ui.button(fa::UNDO) {
+                                *value = 1.0;
+                            }
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Reset");
+                            }
+                            ui.same_line();
+                            imgui::Slider::new(label, 0.5, 1.5)
+                                .display_format("%.2f")
+                                .build(ui, value);
+                        };
+                        eye_gain_slider("Left Eye Gain", &mut fdata.left_eye_gain);
+                        eye_gain_slider("Right Eye Gain", &mut fdata.right_eye_gain);
+                        ui.same_line();
+                        ui.text_disabled(fa::QUESTION_CIRCLE);
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Brightness multiplier per eye, for stereo videos where eyes don't match.");
+                        }
+                    }
                 }
 
                 if ui.collapsing_header("Settings", imgui::TreeNodeFlags::empty()) {
                     let mut ui_angle = config_syncer.get().ui_angle;
                     let mut ui_distance = config_syncer.get().ui_distance;
                     let mut ui_scale = config_syncer.get().ui_scale;
+                    let mut ui_curvature = config_syncer.get().ui_curvature;
                     let mut camera_movement_speed = config_syncer.get().camera_movement_speed;
                     let mut camera_sensitivity = config_syncer.get().camera_sensitivity;
                     let mut cursor_sensitivity = config_syncer.get().cursor_sensitivity;
+                    let mut snap_turn = config_syncer.get().snap_turn;
+                    let mut snap_turn_degrees = config_syncer.get().snap_turn_degrees;
+                    let mut flat_distance = config_syncer.get().flat_distance;
+                    let mut flat_scale = config_syncer.get().flat_scale;
+                    let mut default_stereo_convergence = config_syncer.get().default_stereo_convergence;
+                    let mut supersample_factor = config_syncer.get().supersample_factor;
+                    let mut world_scale = config_syncer.get().world_scale;
+                    let mut static_frame_skip = config_syncer.get().static_frame_skip;
+                    let mut sphere_mesh = config_syncer.get().sphere_mesh;
+                    let mut max_video_size = config_syncer.get().max_video_size as i32;
+                    let mut companion_fov_degrees = config_syncer.get().companion_fov_degrees;
+                    let mut near_plane = config_syncer.get().near_plane;
+                    let mut background_color = config_syncer.get().background_color;
+                    let mut far_plane = config_syncer.get().far_plane;
+                    let mut continue_watching_min_percent = config_syncer.get().continue_watching_min_percent;
+                    let mut continue_watching_max_percent = config_syncer.get().continue_watching_max_percent;
+                    let mut auto_detect_stereo_mode = config_syncer.get().auto_detect_stereo_mode;
+                    let mut reset_origin_on_file_load = config_syncer.get().reset_origin_on_file_load;
+                    let mut pause_on_focus_loss = config_syncer.get().pause_on_focus_loss;
+                    let mut gamepad_ui_navigation = config_syncer.get().gamepad_ui_navigation;
+                    let mut fullscreen_on_play = config_syncer.get().fullscreen_on_play;
+                    let mut volume_fade_in_secs = config_syncer.get().volume_fade_in_secs;
+                    let mut default_volume = config_syncer.get().default_volume as f32;
+                    let mut companion_mirror_vr = config_syncer.get().companion_mirror_vr;
+                    let mut comfort_vignette = config_syncer.get().comfort_vignette;
+                    let mut comfort_vignette_strength = config_syncer.get().comfort_vignette_strength;
+                    let mut comfort_vignette_speed = config_syncer.get().comfort_vignette_speed;
+                    let mut comfort_fov_reduction = config_syncer.get().comfort_fov_reduction;
+                    let mut comfort_fov_reduction_secs = config_syncer.get().comfort_fov_reduction_secs;
+                    let mut idle_power_saving = config_syncer.get().idle_power_saving;
+                    let mut idle_power_saving_secs = config_syncer.get().idle_power_saving_secs;
+
+                    let mut ui_pinned = self.ui_pinned;
+                    if ui.checkbox("Pin UI", &mut ui_pinned) {
+                        action_bin.put(Action::TogglePinUI);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Keep UI at its current place instead of showing it in front of you");
+                    }
 
                     if imgui::InputFloat::new(ui, "UI Angle", &mut ui_angle).step(1.0).build() {
                         config_syncer.get_mut().ui_angle = ui_angle;
@@ -438,6 +1052,13 @@ impl General {
                         config_syncer.get_mut().ui_scale = ui_scale;
                     }
 
+                    if imgui::Slider::new("UI Curvature", 0.0, 1.0).build(ui, &mut ui_curvature) {
+                        config_syncer.get_mut().ui_curvature = ui_curvature;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("0 is flat, at 1 the UI is bent around you");
+                    }
+
                     if imgui::InputFloat::new(ui, "Camera Movement Speed", &mut camera_movement_speed)
                         .step(0.1)
                         .build()
@@ -452,22 +1073,381 @@ impl General {
                         config_syncer.get_mut().camera_sensitivity = camera_sensitivity;
                     }
 
+                    if ui.checkbox("Snap Turn", &mut snap_turn) {
+                        config_syncer.get_mut().snap_turn = snap_turn;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Desktop window only, Q/E turn in steps, mouse only looks up and down");
+                    }
+
+                    if imgui::InputFloat::new(ui, "Snap Turn Angle", &mut snap_turn_degrees)
+                        .step(5.0)
+                        .build()
+                    {
+                        config_syncer.get_mut().snap_turn_degrees = snap_turn_degrees.clamp(5.0, 180.0);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("In degrees");
+                    }
+
                     if imgui::InputFloat::new(ui, "Cursor Sensitivity", &mut cursor_sensitivity)
                         .step(0.1)
                         .build()
                     {
                         config_syncer.get_mut().cursor_sensitivity = cursor_sensitivity;
                     }
+
+                    if imgui::InputFloat::new(ui, "Default Screen Distance", &mut flat_distance)
+                        .step(0.01)
+                        .build()
+                    {
+                        config_syncer.get_mut().flat_distance = flat_distance.clamp(0.2, 20.0);
+                    }
+
+                    if imgui::InputFloat::new(ui, "Default Screen Scale", &mut flat_scale)
+                        .step(0.01)
+                        .build()
+                    {
+                        config_syncer.get_mut().flat_scale = flat_scale.clamp(0.1, 10.0);
+                    }
+
+                    if imgui::InputFloat::new(ui, "Default Stereo Convergence", &mut default_stereo_convergence)
+                        .step(0.1)
+                        .build()
+                    {
+                        config_syncer.get_mut().default_stereo_convergence =
+                            default_stereo_convergence.clamp(-10.0, 10.0);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("In degrees, applied to files which were never seen before");
+                    }
+
+                    if imgui::InputFloat::new(ui, "Supersample Factor", &mut supersample_factor)
+                        .step(0.1)
+                        .build()
+                    {
+                        config_syncer.get_mut().supersample_factor =
+                            supersample_factor.clamp(MIN_SUPERSAMPLE_FACTOR, MAX_SUPERSAMPLE_FACTOR);
+                    }
+
+                    if ui.button(fa::UNDO) && world_scale != default_world_scale() {
+                        config_syncer.get_mut().world_scale = default_world_scale();
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Reset");
+                    }
+                    ui.same_line();
+                    if imgui::Slider::new("World Scale", MIN_WORLD_SCALE, MAX_WORLD_SCALE)
+                        .display_format("%.2fx")
+                        .build(ui, &mut world_scale)
+                    {
+                        config_syncer.get_mut().world_scale = world_scale.clamp(MIN_WORLD_SCALE, MAX_WORLD_SCALE);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("VR only, makes content feel bigger or smaller by adjusting eye separation");
+                    }
+
+                    if ui.checkbox("Reuse Static Frames", &mut static_frame_skip) {
+                        config_syncer.get_mut().static_frame_skip = static_frame_skip;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("VR only, skip rendering eyes while paused and nothing moves");
+                    }
+
+                    if ui.checkbox("Sphere Mesh Rendering", &mut sphere_mesh) {
+                        config_syncer.get_mut().sphere_mesh = sphere_mesh;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(
+                            "Draw ER 360 and ER 180 video on a sphere mesh, smoother picture near the poles",
+                        );
+                    }
+
+                    if imgui::InputInt::new(ui, "Max Video Size", &mut max_video_size)
+                        .step(256)
+                        .build()
+                    {
+                        config_syncer.get_mut().max_video_size = max_video_size.clamp(0, 16384) as u32;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(
+                            "Longest side in pixels, bigger videos are downscaled, 0 is unlimited. Downscaling runs \
+                             on CPU, hardware decoded frames are copied back from GPU for it",
+                        );
+                    }
+
+                    if ui.button(fa::UNDO) && background_color != default_background_color() {
+                        config_syncer.get_mut().background_color = default_background_color();
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Reset");
+                    }
+                    ui.same_line();
+                    if imgui::ColorEdit::new("Background Color", &mut background_color).build(ui) {
+                        config_syncer.get_mut().background_color = background_color;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Shown around the video, e.g. behind 180 degree content");
+                    }
+
+                    if imgui::InputFloat::new(ui, "Desktop Window FOV", &mut companion_fov_degrees)
+                        .step(1.0)
+                        .build()
+                    {
+                        config_syncer.get_mut().companion_fov_degrees = companion_fov_degrees.clamp(30.0, 120.0);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Vertical field of view of the desktop window camera, in degrees");
+                    }
+
+                    let present_mode = config_syncer.get().present_mode;
+                    imgui::ComboBox::new("Desktop Window Present Mode")
+                        .preview_value(present_mode.label())
+                        .build(ui, || {
+                            for mode in PresentMode::ALL {
+                                let selected = mode == present_mode;
+                                if imgui::Selectable::new(mode.label()).selected(selected).build(ui) && !selected {
+                                    config_syncer.get_mut().present_mode = mode;
+                                }
+                            }
+                        });
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Fifo limits desktop window to monitor refresh rate and saves power");
+                    }
+
+                    if imgui::InputFloat::new(ui, "Near Plane", &mut near_plane)
+                        .step(0.01)
+                        .build()
+                    {
+                        config_syncer.get_mut().near_plane = near_plane.clamp(0.001, 1.0);
+                    }
+
+                    if imgui::InputFloat::new(ui, "Far Plane", &mut far_plane)
+                        .step(10.0)
+                        .build()
+                    {
+                        config_syncer.get_mut().far_plane = far_plane.clamp(10.0, 10000.0);
+                    }
+
+                    if imgui::InputFloat::new(ui, "Continue Watching Min %", &mut continue_watching_min_percent)
+                        .step(1.0)
+                        .build()
+                    {
+                        config_syncer.get_mut().continue_watching_min_percent =
+                            continue_watching_min_percent.clamp(0.0, 100.0);
+                    }
+
+                    if imgui::InputFloat::new(ui, "Continue Watching Max %", &mut continue_watching_max_percent)
+                        .step(1.0)
+                        .build()
+                    {
+                        config_syncer.get_mut().continue_watching_max_percent =
+                            continue_watching_max_percent.clamp(0.0, 100.0);
+                    }
+
+                    if ui.checkbox("Auto-detect Stereo Mode", &mut auto_detect_stereo_mode) {
+                        config_syncer.get_mut().auto_detect_stereo_mode = auto_detect_stereo_mode;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Use stereo metadata of files which were never seen before");
+                    }
+
+                    if ui.checkbox("Reset Origin On File Load", &mut reset_origin_on_file_load) {
+                        config_syncer.get_mut().reset_origin_on_file_load = reset_origin_on_file_load;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Every new file starts centered in front of you");
+                    }
+
+                    if ui.checkbox("Pause On Focus Loss", &mut pause_on_focus_loss) {
+                        config_syncer.get_mut().pause_on_focus_loss = pause_on_focus_loss;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Pause when the desktop window loses focus, not used in VR mode");
+                    }
+
+                    if ui.checkbox("Gamepad UI Navigation", &mut gamepad_ui_navigation) {
+                        config_syncer.get_mut().gamepad_ui_navigation = gamepad_ui_navigation;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("D-pad moves focus, A activates, B goes back, bumpers switch windows");
+                    }
+
+                    if ui.checkbox("Fullscreen On Play", &mut fullscreen_on_play) {
+                        config_syncer.get_mut().fullscreen_on_play = fullscreen_on_play;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Desktop window goes fullscreen during playback, not used in VR mode");
+                    }
+
+                    if imgui::InputFloat::new(ui, "Volume Fade In", &mut volume_fade_in_secs)
+                        .step(0.5)
+                        .build()
+                    {
+                        config_syncer.get_mut().volume_fade_in_secs = volume_fade_in_secs.clamp(0.0, 10.0);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Seconds to ramp volume up at file start, 0 to disable");
+                    }
+
+                    if imgui::Slider::new("Default Volume", 0.0, 100.0).build(ui, &mut default_volume) {
+                        config_syncer.get_mut().default_volume = default_volume as f64;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Volume of files which don't have a saved one");
+                    }
+
+                    if ui.checkbox("Mirror VR In Desktop Window", &mut companion_mirror_vr) {
+                        config_syncer.get_mut().companion_mirror_vr = companion_mirror_vr;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Desktop window follows the headset instead of its own camera");
+                    }
+
+                    if ui.checkbox("Comfort Vignette", &mut comfort_vignette) {
+                        config_syncer.get_mut().comfort_vignette = comfort_vignette;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Darken the periphery in VR while the view moves fast");
+                    }
+
+                    if imgui::Slider::new("Vignette Strength", 0.0, 1.0).build(ui, &mut comfort_vignette_strength) {
+                        config_syncer.get_mut().comfort_vignette_strength = comfort_vignette_strength;
+                    }
+
+                    if imgui::InputFloat::new(ui, "Vignette Full Speed", &mut comfort_vignette_speed)
+                        .step(10.0)
+                        .build()
+                    {
+                        config_syncer.get_mut().comfort_vignette_speed = comfort_vignette_speed.clamp(10.0, 1000.0);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Rotation speed in degrees per second at which vignette is the strongest");
+                    }
+
+                    if ui.checkbox("Reduce FOV On Recenter", &mut comfort_fov_reduction) {
+                        config_syncer.get_mut().comfort_fov_reduction = comfort_fov_reduction;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Narrow the view in VR for a moment when world origin is reset");
+                    }
+
+                    if imgui::InputFloat::new(ui, "FOV Restore Time", &mut comfort_fov_reduction_secs)
+                        .step(0.1)
+                        .build()
+                    {
+                        config_syncer.get_mut().comfort_fov_reduction_secs = comfort_fov_reduction_secs.clamp(0.1, 3.0);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("In seconds");
+                    }
+
+                    if ui.checkbox("Idle Power Saving", &mut idle_power_saving) {
+                        config_syncer.get_mut().idle_power_saving = idle_power_saving;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Lower VR eye resolution while paused, restored on resume or head turn");
+                    }
+
+                    if imgui::InputFloat::new(ui, "Idle Power Saving Delay", &mut idle_power_saving_secs)
+                        .step(5.0)
+                        .build()
+                    {
+                        config_syncer.get_mut().idle_power_saving_secs = idle_power_saving_secs.clamp(1.0, 600.0);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Seconds of pause before eye resolution is lowered");
+                    }
+
+                    if ui.button("Export File DB") {
+                        action_bin.put(Action::ExportFileDB);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Export per-file settings to files-export.ron in vrmp data directory");
+                    }
+                    ui.same_line();
+                    if ui.button("Import File DB") {
+                        action_bin.put(Action::ImportFileDB);
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Import per-file settings from files-export.ron in vrmp data directory");
+                    }
+
+                    if ui.button("Reset All Settings") {
+                        self.reset_forget_files = false;
+                        ui.open_popup("Reset All Settings?");
+                    }
+                    imgui::PopupModal::new("Reset All Settings?")
+                        .always_auto_resize(true)
+                        .build(ui, || {
+                            ui.text("All settings will be reset to their defaults.");
+                            ui.checkbox("Also forget per-file settings", &mut self.reset_forget_files);
+                            if ui.button("Reset") {
+                                action_bin.put(Action::ResetAllSettings {
+                                    forget_files: self.reset_forget_files,
+                                });
+                                ui.close_current_popup();
+                            }
+                            ui.same_line();
+                            if ui.button("Cancel") {
+                                ui.close_current_popup();
+                            }
+                        });
                 }
 
                 if ui.collapsing_header("Debug", imgui::TreeNodeFlags::empty()) {
-                    imgui::Drag::new("Shader Debug")
-                        .speed(0.01)
-                        .build(ui, &mut self.shader_debug);
+                    let preview = DEBUG_MODES
+                        .iter()
+                        .find(|(m, _)| *m == self.debug_mode)
+                        .map(|(_, name)| *name)
+                        .unwrap_or("");
+                    imgui::ComboBox::new("Shader Debug")
+                        .preview_value(preview)
+                        .build(ui, || {
+                            for (mode, name) in DEBUG_MODES {
+                                let selected = *mode == self.debug_mode;
+                                if imgui::Selectable::new(name).selected(selected).build(ui) {
+                                    self.debug_mode = *mode;
+                                }
+                            }
+                        });
+
+                    let preview = EYE_FILTERS
+                        .iter()
+                        .find(|(f, _)| *f == self.eye_filter)
+                        .map(|(_, name)| *name)
+                        .unwrap_or("");
+                    imgui::ComboBox::new("Render Eyes")
+                        .preview_value(preview)
+                        .build(ui, || {
+                            for (filter, name) in EYE_FILTERS {
+                                let selected = *filter == self.eye_filter;
+                                if imgui::Selectable::new(name).selected(selected).build(ui) {
+                                    self.eye_filter = *filter;
+                                }
+                            }
+                        });
 
                     if ui.button("Show Demo") {
                         self.show_demo = true;
                     }
+
+                    ui.checkbox("Show Stats (F3)", &mut self.show_stats);
+
+                    if ui.button("A/V Sync Test") {
+                        action_bin.put(Action::Command(vec![
+                            "loadfile".to_owned(),
+                            AV_SYNC_TEST_SOURCE.to_owned(),
+                        ]));
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(indoc!(
+                            r#"
+                            Plays a white flash with a beep once per second. Record the screen (or headset lenses)
+                            and speakers with a camera to measure A/V offset.
+                            "#
+                        ));
+                    }
                 }
             });
 
@@ -1,4 +1,6 @@
 pub mod file_browser;
 pub mod font_awesome;
 pub mod general;
+pub mod stats;
+pub mod toasts;
 pub mod util;
@@ -0,0 +1,72 @@
+use std::{fmt::Write, time::Duration};
+
+// number of frames kept for the frame time graph, a couple of seconds at VR refresh rates
+const FRAME_TIME_HISTORY: usize = 240;
+
+pub struct FrameStats {
+    // ring buffer, "next" is the oldest entry and the place where the next one goes
+    frame_times_ms: [f32; FRAME_TIME_HISTORY],
+    next: usize,
+    pub estimated_vf_fps: Option<f64>,
+    pub frame_drop_count: Option<i64>,
+    tmp_str: String,
+}
+
+impl FrameStats {
+    pub fn new() -> FrameStats {
+        FrameStats {
+            frame_times_ms: [0.0; FRAME_TIME_HISTORY],
+            next: 0,
+            estimated_vf_fps: None,
+            frame_drop_count: None,
+            tmp_str: String::new(),
+        }
+    }
+
+    pub fn push(&mut self, delta: Duration) {
+        self.frame_times_ms[self.next] = delta.as_secs_f32() * 1000.0;
+        self.next = (self.next + 1) % FRAME_TIME_HISTORY;
+    }
+
+    pub fn reset_mpv_stats(&mut self) {
+        self.estimated_vf_fps = None;
+        self.frame_drop_count = None;
+    }
+
+    pub fn render(&mut self, ui: &imgui::Ui, eye_size: Option<(u32, u32)>, opened: &mut bool) {
+        let total_ms: f32 = self.frame_times_ms.iter().sum();
+        let max_ms = self.frame_times_ms.iter().cloned().fold(0.0, f32::max);
+        let fps = cond!(total_ms > 0.0, FRAME_TIME_HISTORY as f32 * 1000.0 / total_ms, 0.0);
+
+        imgui::Window::new("Stats")
+            .opened(opened)
+            .always_auto_resize(true)
+            .position([20.0, 20.0], imgui::Condition::FirstUseEver)
+            .build(ui, || {
+                ui.text(format!("App FPS: {:.1} (max frame time {:.2} ms)", fps, max_ms));
+                let s = &mut self.tmp_str;
+                s.clear();
+                let last_ms = self.frame_times_ms[(self.next + FRAME_TIME_HISTORY - 1) % FRAME_TIME_HISTORY];
+                write!(s, "{:.2} ms", last_ms).unwrap();
+                imgui::PlotLines::new(ui, "##frame-times", &self.frame_times_ms)
+                    .values_offset(self.next)
+                    .scale_min(0.0)
+                    .scale_max(max_ms.max(1.0))
+                    .overlay_text(s)
+                    .graph_size([400.0, 80.0])
+                    .build();
+                match eye_size {
+                    Some((w, h)) => ui.text(format!("Eye Resolution: {}x{}", w, h)),
+                    None => ui.text("Eye Resolution: no VR"),
+                }
+                match self.estimated_vf_fps {
+                    Some(v) => ui.text(format!("Video FPS (estimated): {:.2}", v)),
+                    None => ui.text("Video FPS (estimated): n/a"),
+                }
+                match self.frame_drop_count {
+                    Some(v) => ui.text(format!("Dropped Frames: {}", v)),
+                    None => ui.text("Dropped Frames: n/a"),
+                }
+            });
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+use super::font_awesome as fa;
+use super::util::hex;
+
+// how long a toast is visible, the last second of it is fading out
+pub const TOAST_DURATION: Duration = Duration::from_secs(4);
+const TOAST_FADE_SECS: f32 = 1.0;
+const MAX_TOASTS: usize = 5;
+
+pub fn push_toast(toasts: &mut Vec<(String, Instant)>, msg: String) {
+    toasts.push((msg, Instant::now()));
+    if toasts.len() > MAX_TOASTS {
+        toasts.remove(0);
+    }
+}
+
+pub fn expire_toasts(toasts: &mut Vec<(String, Instant)>) {
+    toasts.retain(|(_, t)| t.elapsed() < TOAST_DURATION);
+}
+
+// Buffering indicator in the top-center, drawn the same way as toasts.
+pub fn render_buffering(ui: &imgui::Ui, percent: i64, gap: f32) {
+    let padding = 8.0;
+    let [w, _] = ui.io().display_size;
+    let dl = ui.get_foreground_draw_list();
+    let msg = format!("{} Buffering {}%", fa::HOURGLASS_HALF, percent);
+    let [tw, th] = ui.calc_text_size(&msg);
+    let x0 = (w - tw) / 2.0 - padding;
+    let x1 = x0 + tw + 2.0 * padding;
+    let y1 = gap + th + 2.0 * padding;
+    let [r, g, b, _] = hex("#202020");
+    dl.add_rect([x0, gap], [x1, y1], [r, g, b, 0.9])
+        .filled(true)
+        .rounding(4.0)
+        .build();
+    dl.add_text([x0 + padding, gap + padding], [1.0, 1.0, 1.0, 1.0], &msg);
+}
+
+// Toasts are drawn via foreground draw list, so that they are always on top of the panels and never take input.
+// Newest toast is at the top, stacked in the top-right corner.
+pub fn render_toasts(ui: &imgui::Ui, toasts: &[(String, Instant)], gap: f32) {
+    let padding = 8.0;
+    let [w, _] = ui.io().display_size;
+    let dl = ui.get_foreground_draw_list();
+    let mut y = gap;
+    for (msg, t) in toasts.iter().rev() {
+        let left = TOAST_DURATION.as_secs_f32() - t.elapsed().as_secs_f32();
+        let alpha = (left / TOAST_FADE_SECS).clamp(0.0, 1.0);
+        let [tw, th] = ui.calc_text_size(msg);
+        let x1 = w - gap;
+        let x0 = x1 - tw - 2.0 * padding;
+        let y1 = y + th + 2.0 * padding;
+        let [r, g, b, _] = hex("#202020");
+        dl.add_rect([x0, y], [x1, y1], [r, g, b, 0.9 * alpha])
+            .filled(true)
+            .rounding(4.0)
+            .build();
+        dl.add_text([x0 + padding, y + padding], [1.0, 1.0, 1.0, alpha], msg);
+        y = y1 + padding;
+    }
+}
@@ -22,6 +22,7 @@ mod camera_state;
 mod config;
 mod controls;
 mod danger;
+mod device_models;
 mod enums;
 mod filedb;
 mod global;
@@ -29,12 +30,15 @@ mod imgui;
 mod multilog;
 mod pipeline;
 mod scene;
+mod status;
+mod timeline;
 mod tracks;
+mod undo;
 mod vrinfo;
 mod vscreen;
 
 fn main() {
     env_logger::init();
-    let mut global = global::Global::init();
+    let global = global::Global::init();
     global.run();
 }
@@ -0,0 +1,73 @@
+use std::mem;
+
+use glam::{Vec2, Vec3};
+
+use super::textured_quad::Vertex;
+
+// number of vertical strips a curved quad is made of
+const SEGMENTS: usize = 32;
+
+// Unit quad in XY plane, same as TexturedQuad's one, optionally bent around a vertical axis. Bending happens in
+// meters, so the quad is expected to be scaled to (width, height, 1) by the model matrix. Drawn with TexturedQuad
+// pipeline.
+pub struct CurvedQuad {
+    pub vertex_buf: wgpu::Buffer,
+    pub vertex_count: u32,
+    // (width, radius) the mesh was built for, see update
+    params: Option<(f32, Option<f32>)>,
+}
+
+impl CurvedQuad {
+    pub fn create(device: &wgpu::Device) -> CurvedQuad {
+        let vertex_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (mem::size_of::<Vertex>() * 6 * SEGMENTS) as _,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        CurvedQuad {
+            vertex_buf,
+            vertex_count: 0,
+            params: None,
+        }
+    }
+
+    // Width is in meters, radius is the radius of the cylinder the quad is bent around, None means flat. Quad's center
+    // stays in place and its edges come towards -Z (towards the viewer). Arc length matches the width.
+    pub fn update(&mut self, queue: &wgpu::Queue, width: f32, radius: Option<f32>) {
+        if self.params == Some((width, radius)) {
+            return;
+        }
+        self.params = Some((width, radius));
+
+        let segments = cond!(radius.is_some(), SEGMENTS, 1);
+        let column = |i: usize| -> (f32, f32, f32) {
+            let u = i as f32 / segments as f32;
+            match radius {
+                Some(r) => {
+                    let a = (u - 0.5) * width / r;
+                    (r * a.sin() / width, -r * (1.0 - a.cos()), u)
+                }
+                None => (u - 0.5, 0.0, u),
+            }
+        };
+        let vertex = |(x, z, u): (f32, f32, f32), v: f32| Vertex {
+            position: Vec3::new(x, v - 0.5, z),
+            texcoord: Vec2::new(u, v),
+        };
+        let mut vertices = Vec::with_capacity(segments * 6);
+        for i in 0..segments {
+            let (l, r) = (column(i), column(i + 1));
+            vertices.extend_from_slice(&[
+                vertex(l, 0.0),
+                vertex(r, 0.0),
+                vertex(l, 1.0),
+                vertex(l, 1.0),
+                vertex(r, 0.0),
+                vertex(r, 1.0),
+            ]);
+        }
+        queue.write_buffer(&self.vertex_buf, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+}
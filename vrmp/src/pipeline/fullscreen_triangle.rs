@@ -2,6 +2,7 @@ use std::borrow::Cow;
 
 pub struct FullscreenTriangle {
     pub pipeline: wgpu::RenderPipeline,
+    pub sample_count: u32,
 }
 
 impl FullscreenTriangle {
@@ -9,6 +10,7 @@ impl FullscreenTriangle {
         device: &wgpu::Device,
         color_target_state: wgpu::ColorTargetState,
         pipeline_layout: &wgpu::PipelineLayout,
+        sample_count: u32,
         shader_source: &'static str,
     ) -> FullscreenTriangle {
         let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
@@ -41,10 +43,13 @@ impl FullscreenTriangle {
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             }),
-            multisample: wgpu::MultisampleState::default(),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
             multiview: None,
         });
 
-        FullscreenTriangle { pipeline }
+        FullscreenTriangle { pipeline, sample_count }
     }
 }
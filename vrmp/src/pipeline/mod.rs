@@ -1,2 +1,5 @@
+pub mod curved_quad;
 pub mod fullscreen_triangle;
+pub mod render_model;
+pub mod sphere_mesh;
 pub mod textured_quad;
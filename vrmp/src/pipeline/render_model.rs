@@ -0,0 +1,156 @@
+use std::{borrow::Cow, mem};
+
+use bytemuck_derive::{Pod, Zeroable};
+use glam::{Vec2, Vec3};
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Vertex {
+    position: Vec3,
+    normal: Vec3,
+    texcoord: Vec2,
+}
+
+// Pipeline for openvr render models (controllers, etc), model matrix is supplied via push constant.
+pub struct RenderModelPipeline {
+    pub pipeline: wgpu::RenderPipeline,
+    pub sample_count: u32,
+}
+
+impl RenderModelPipeline {
+    pub fn create(
+        device: &wgpu::Device,
+        color_target_state: wgpu::ColorTargetState,
+        pipeline_layout: &wgpu::PipelineLayout,
+        sample_count: u32,
+        shader_source: &'static str,
+    ) -> RenderModelPipeline {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(shader_source)),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[color_target_state],
+            }),
+            // models are mirrored by swap_z, winding is flipped, so no culling
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        RenderModelPipeline { pipeline, sample_count }
+    }
+}
+
+// Render model uploaded to GPU, ready to be drawn with RenderModelPipeline.
+pub struct GpuRenderModel {
+    pub vertex_buf: wgpu::Buffer,
+    pub index_buf: wgpu::Buffer,
+    pub index_count: u32,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl GpuRenderModel {
+    pub fn create(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        model: &libopenvr::RenderModel,
+        texture: &libopenvr::RenderModelTexture,
+    ) -> GpuRenderModel {
+        let vertices: Vec<Vertex> = model
+            .vertices
+            .iter()
+            .map(|v| Vertex {
+                position: Vec3::from(v.position),
+                normal: Vec3::from(v.normal),
+                texcoord: Vec2::from(v.texcoord),
+            })
+            .collect();
+        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        // index buffer size must be a multiple of 4 bytes
+        let mut indices = model.indices.clone();
+        if indices.len() % 2 != 0 {
+            indices.push(0);
+        }
+        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let texture_extent = wgpu::Extent3d {
+            width: texture.width,
+            height: texture.height,
+            depth_or_array_layers: 1,
+        };
+        let gpu_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: texture_extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            gpu_texture.as_image_copy(),
+            &texture.data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(texture.width * 4),
+                rows_per_image: None,
+            },
+            texture_extent,
+        );
+        let texture_view = gpu_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: texture_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture_view),
+            }],
+        });
+
+        GpuRenderModel {
+            vertex_buf,
+            index_buf,
+            index_count: model.indices.len() as u32,
+            bind_group,
+        }
+    }
+}
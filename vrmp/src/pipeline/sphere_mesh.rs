@@ -0,0 +1,158 @@
+use std::{borrow::Cow, f32::consts::PI, mem};
+
+use glam::{Vec2, Vec3};
+use wgpu::util::DeviceExt;
+
+use super::textured_quad::Vertex;
+
+// number of rows from pole to pole, columns are derived from the longitude span to keep cells square
+const LATITUDE_SEGMENTS: u32 = 64;
+
+// Sphere (or a part of it) with the video mapped onto it, alternative to FullscreenTriangle for equirectangular
+// projections, see Config::sphere_mesh. Vertex positions are directions, the shader draws them at infinity, so the
+// sphere always surrounds the eye. Texcoord holds spherical coordinates (theta, phi) in radians as produced by
+// ws_to_spherical_coords, so fragment shaders share projection math with the fullscreen ones, but get smooth
+// derivatives at the poles and don't run for pixels outside of the mesh.
+//
+// It's still the background: no depth test and no depth writes, like FullscreenTriangle. Anything drawn later (UI,
+// models) is simply on top of it, there is no depth based compositing with the video.
+pub struct SphereMesh {
+    pub pipeline: wgpu::RenderPipeline,
+    pub sample_count: u32,
+    pub vertex_buf: wgpu::Buffer,
+    pub index_buf: wgpu::Buffer,
+    pub index_count: u32,
+}
+
+impl SphereMesh {
+    // longitude span is in radians and centered around +Z, 2 * PI makes a full sphere
+    pub fn create(
+        device: &wgpu::Device,
+        color_target_state: wgpu::ColorTargetState,
+        pipeline_layout: &wgpu::PipelineLayout,
+        sample_count: u32,
+        shader_source: &'static str,
+        longitude_span: f32,
+    ) -> SphereMesh {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(shader_source)),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[color_target_state],
+            }),
+            // viewed from the inside, no culling
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            // it's the background, same as FullscreenTriangle
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        let (vertices, indices) = sphere_geometry(longitude_span);
+        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        SphereMesh {
+            pipeline,
+            sample_count,
+            vertex_buf,
+            index_buf,
+            index_count: indices.len() as u32,
+        }
+    }
+}
+
+// Grid of (columns + 1) x (rows + 1) vertices, seam and pole vertices are duplicated so that each one has its own
+// spherical coordinates.
+fn sphere_geometry(longitude_span: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let rows = LATITUDE_SEGMENTS;
+    let columns = ((rows as f32 * longitude_span / PI).round() as u32).max(1);
+    let mut vertices = Vec::with_capacity(((columns + 1) * (rows + 1)) as usize);
+    for row in 0..=rows {
+        let phi = (row as f32 / rows as f32 - 0.5) * PI;
+        for column in 0..=columns {
+            let theta = (column as f32 / columns as f32 - 0.5) * longitude_span;
+            vertices.push(Vertex {
+                // inverse of ws_to_spherical_coords, y is flipped
+                position: Vec3::new(phi.cos() * theta.sin(), -phi.sin(), phi.cos() * theta.cos()),
+                texcoord: Vec2::new(theta, phi),
+            });
+        }
+    }
+    let mut indices = Vec::with_capacity((columns * rows * 6) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let i = row * (columns + 1) + column;
+            let below = i + columns + 1;
+            indices.extend_from_slice(&[i, i + 1, below, below, i + 1, below + 1]);
+        }
+    }
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_sizes() {
+        let (vertices, indices) = sphere_geometry(2.0 * PI);
+        let (rows, columns) = (LATITUDE_SEGMENTS as usize, 2 * LATITUDE_SEGMENTS as usize);
+        assert_eq!(vertices.len(), (rows + 1) * (columns + 1));
+        assert_eq!(indices.len(), rows * columns * 6);
+        assert!(indices.iter().all(|&i| (i as usize) < vertices.len()));
+
+        let (vertices, indices) = sphere_geometry(PI);
+        assert_eq!(vertices.len(), (rows + 1) * (rows + 1));
+        assert_eq!(indices.len(), rows * rows * 6);
+    }
+
+    #[test]
+    fn seam_vertices_are_duplicated() {
+        let (vertices, _) = sphere_geometry(2.0 * PI);
+        let columns = 2 * LATITUDE_SEGMENTS as usize;
+        for row in vertices.chunks(columns + 1) {
+            let (first, last) = (row[0], row[columns]);
+            // same spot of the sphere, but each side of the seam has its own longitude
+            assert!(first.position.abs_diff_eq(last.position, 1e-5));
+            assert!((last.texcoord.x - first.texcoord.x - 2.0 * PI).abs() < 1e-5);
+            assert_eq!(first.texcoord.y, last.texcoord.y);
+        }
+    }
+}
@@ -7,13 +7,14 @@ use crate::enums::AspectRatio;
 
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
-struct Vertex {
-    position: glam::Vec3,
-    texcoord: glam::Vec2,
+pub struct Vertex {
+    pub position: glam::Vec3,
+    pub texcoord: glam::Vec2,
 }
 
 pub struct TexturedQuad {
     pub pipeline: wgpu::RenderPipeline,
+    pub sample_count: u32,
     pub vertex_buf: wgpu::Buffer,
 }
 
@@ -23,6 +24,7 @@ impl TexturedQuad {
         queue: &wgpu::Queue,
         color_target_state: wgpu::ColorTargetState,
         pipeline_layout: &wgpu::PipelineLayout,
+        sample_count: u32,
         shader_source: &str,
     ) -> TexturedQuad {
         let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
@@ -59,7 +61,10 @@ impl TexturedQuad {
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             }),
-            multisample: wgpu::MultisampleState::default(),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
             multiview: None,
         });
 
@@ -101,7 +106,11 @@ impl TexturedQuad {
             ]),
         );
 
-        TexturedQuad { pipeline, vertex_buf }
+        TexturedQuad {
+            pipeline,
+            sample_count,
+            vertex_buf,
+        }
     }
 
     // create a scale matrix based on w/h (aspect ratio)
@@ -1,46 +1,129 @@
 use crate::{
     config::Config,
+    device_models::DeviceModels,
     enums::AspectRatio,
-    pipeline::{fullscreen_triangle::FullscreenTriangle, textured_quad::TexturedQuad},
+    pipeline::{
+        curved_quad::CurvedQuad, fullscreen_triangle::FullscreenTriangle, render_model::RenderModelPipeline,
+        sphere_mesh::SphereMesh, textured_quad::TexturedQuad,
+    },
 };
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
 
 #[derive(Copy, Clone)]
 pub enum VideoRenderer<'a> {
     FTri(&'a FullscreenTriangle),
     TQuad(&'a TexturedQuad, Mat4),
+    // placed by world origin orientation
+    Sphere(&'a SphereMesh),
 }
 
 pub struct Scene<'a> {
     pub queue: &'a wgpu::Queue,
     pub device: &'a wgpu::Device,
     pub color: &'a wgpu::TextureView,
+    // when MSAA is enabled scene is rendered here and then resolved into "color"
+    pub msaa_color: Option<&'a wgpu::TextureView>,
     pub depth: &'a wgpu::TextureView,
     pub video: VideoRenderer<'a>,
     pub lines_pipeline: &'a wgpu::RenderPipeline,
     pub lines_buf: &'a wgpu::Buffer,
+    // controller laser pointer, a unit line along -Z scaled by the model matrix
+    pub laser: Option<Mat4>,
+    pub laser_buf: &'a wgpu::Buffer,
     pub camera_bgrp: &'a wgpu::BindGroup,
     pub video_bgrp: &'a wgpu::BindGroup,
     pub tquad_imgui: &'a TexturedQuad,
+    // drawn with tquad_imgui pipeline, see ui_transform
+    pub ui_mesh: &'a CurvedQuad,
     pub vscreen: Option<&'a crate::vscreen::VScreen>,
+    // controllers, only in VR mode
+    pub device_models: Option<(&'a DeviceModels, &'a RenderModelPipeline)>,
+    // drawn on top of everything, intensity comes from CameraState
+    pub comfort_vignette: Option<&'a FullscreenTriangle>,
+    pub swap_z: Mat4,
     pub config: &'a Config,
     pub debug_matrices: &'a [Mat4],
     pub world_origin: Mat4,
+    // flat screen placement, follows world origin unless the screen was recentered
+    pub screen_origin: Mat4,
     pub ui_origin: Mat4,
 }
 
+// transform of the UI panel center, units are meters
+pub fn ui_panel_transform(ui_origin: Mat4, config: &Config) -> Mat4 {
+    let rot_mat = Mat4::from_rotation_x(config.ui_angle.to_radians());
+    let tr_mat = Mat4::from_translation(Vec3::new(0.0, 0.0, config.ui_distance));
+    ui_origin * rot_mat * tr_mat
+}
+
+// UI panel width and height in meters
+pub fn ui_panel_size(config: &Config, vscreen: &crate::vscreen::VScreen) -> Vec2 {
+    let scale_mat = TexturedQuad::scale_for_wh(vscreen.width, vscreen.height, config.ui_scale, AspectRatio::One);
+    Vec2::new(scale_mat.x_axis.x, scale_mat.y_axis.y)
+}
+
+// transform of the UI quad, which is a unit quad in XY plane (possibly curved, see ui_curvature_radius)
+pub fn ui_transform(ui_origin: Mat4, config: &Config, vscreen: &crate::vscreen::VScreen) -> Mat4 {
+    ui_panel_transform(ui_origin, config) * Mat4::from_scale(ui_panel_size(config, vscreen).extend(1.0))
+}
+
+// None means flat UI panel, at curvature 1 the panel is equidistant from ui_origin
+pub fn ui_curvature_radius(config: &Config) -> Option<f32> {
+    let curvature = config.ui_curvature.clamp(0.0, 1.0);
+    (curvature > 0.0).then(|| config.ui_distance / curvature)
+}
+
+// Intersects a ray given in UI panel space (see ui_panel_transform) with the panel. Returns ray parameter and
+// horizontal position along the panel surface in meters, 0 is the center. Panel bounds are not checked.
+pub fn ui_panel_hit(origin: Vec3, dir: Vec3, radius: Option<f32>) -> Option<(f32, f32)> {
+    let r = match radius {
+        Some(r) => r,
+        None => {
+            if dir.z.abs() < f32::EPSILON {
+                return None;
+            }
+            let t = -origin.z / dir.z;
+            return Some((t, origin.x + dir.x * t));
+        }
+    };
+    // cylinder axis is vertical and goes through (0, -r) in XZ plane
+    let o = Vec2::new(origin.x, origin.z + r);
+    let d = Vec2::new(dir.x, dir.z);
+    let a = d.dot(d);
+    let b = 2.0 * o.dot(d);
+    let c = o.dot(o) - r * r;
+    let disc = b * b - 4.0 * a * c;
+    if a < f32::EPSILON || disc < 0.0 {
+        return None;
+    }
+    let sqrt_disc = disc.sqrt();
+    [(-b - sqrt_disc) / (2.0 * a), (-b + sqrt_disc) / (2.0 * a)]
+        .into_iter()
+        .find_map(|t| {
+            let p = o + d * t;
+            // only the half of the cylinder the panel is on
+            (t > 0.0 && p.y > 0.0).then(|| (t, p.x.atan2(p.y) * r))
+        })
+}
+
 pub fn render_scene(s: &Scene) {
     let mut encoder = s
         .device
         .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
     {
+        let [r, g, b] = s.config.background_color_linear();
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: s.color,
-                resolve_target: None,
+                view: s.msaa_color.unwrap_or(s.color),
+                resolve_target: s.msaa_color.map(|_| s.color),
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: r as f64,
+                        g: g as f64,
+                        b: b as f64,
+                        a: 1.0,
+                    }),
                     store: true,
                 },
             }],
@@ -63,12 +146,30 @@ pub fn render_scene(s: &Scene) {
                 rpass.draw(0..3, 0..1);
             }
             VideoRenderer::TQuad(tquad, m) => {
-                let m = s.world_origin * m;
+                let m = s.screen_origin * m;
                 rpass.set_pipeline(&tquad.pipeline);
                 rpass.set_vertex_buffer(0, tquad.vertex_buf.slice(..));
                 rpass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytemuck::bytes_of(&m));
                 rpass.draw(0..6, 0..1);
             }
+            VideoRenderer::Sphere(sphere) => {
+                rpass.set_pipeline(&sphere.pipeline);
+                rpass.set_vertex_buffer(0, sphere.vertex_buf.slice(..));
+                rpass.set_index_buffer(sphere.index_buf.slice(..), wgpu::IndexFormat::Uint32);
+                rpass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytemuck::bytes_of(&s.world_origin));
+                rpass.draw_indexed(0..sphere.index_count, 0, 0..1);
+            }
+        }
+
+        if let Some((device_models, pipeline)) = s.device_models {
+            device_models.draw(&mut rpass, pipeline, s.swap_z);
+        }
+
+        if let Some(m) = s.laser {
+            rpass.set_pipeline(s.lines_pipeline);
+            rpass.set_vertex_buffer(0, s.laser_buf.slice(..));
+            rpass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytemuck::bytes_of(&m));
+            rpass.draw(0..2, 0..1);
         }
 
         // rpass.set_pipeline(s.lines_pipeline);
@@ -79,20 +180,17 @@ pub fn render_scene(s: &Scene) {
         // }
 
         if let Some(vscreen) = s.vscreen {
-            let ui_angle = s.config.ui_angle;
-            let ui_distance = s.config.ui_distance;
-            let ui_scale = s.config.ui_scale;
-
-            let rot_mat = Mat4::from_rotation_x(ui_angle.to_radians());
-            let tr_mat = Mat4::from_translation(Vec3::new(0.0, 0.0, ui_distance));
-            let scale_mat = TexturedQuad::scale_for_wh(vscreen.width, vscreen.height, ui_scale, AspectRatio::One);
-
             rpass.set_pipeline(&s.tquad_imgui.pipeline);
             rpass.set_bind_group(1, &vscreen.bind_group, &[]);
-            let pos = s.ui_origin * rot_mat * tr_mat * scale_mat;
+            let pos = ui_transform(s.ui_origin, s.config, vscreen);
             rpass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytemuck::bytes_of(&pos));
-            rpass.set_vertex_buffer(0, s.tquad_imgui.vertex_buf.slice(..));
-            rpass.draw(0..6, 0..1);
+            rpass.set_vertex_buffer(0, s.ui_mesh.vertex_buf.slice(..));
+            rpass.draw(0..s.ui_mesh.vertex_count, 0..1);
+        }
+
+        if let Some(tri) = s.comfort_vignette {
+            rpass.set_pipeline(&tri.pipeline);
+            rpass.draw(0..3, 0..1);
         }
     }
     s.queue.submit(Some(encoder.finish()));
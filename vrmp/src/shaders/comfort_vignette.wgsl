@@ -0,0 +1,13 @@
+{% include "inc_common.wgsl" %}
+{% include "inc_fullscreen.wgsl" %}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  // angle between the ray and the eye's optical axis
+  let d = normalize(in.inv_pos);
+  let angle = atan2(length(d.xy), abs(d.z));
+  // visible area shrinks from ~80° to ~25° (half angle) as the vignette gets stronger
+  let radius = mix(1.4, 0.45, camera_state.vignette);
+  let alpha = smoothstep(radius, radius + 0.3, angle) * min(camera_state.vignette * 4.0, 1.0);
+  return vec4<f32>(0.0, 0.0, 0.0, alpha);
+}
@@ -18,7 +18,27 @@ struct CameraState {
   mode: u32;
   // in radians, eye-based sign is already applied
   stereo_adjust: f32;
-  shader_debug: f32;
+  // 0 - off
+  // 1 - uv
+  // 2 - eye index
+  // 3 - depth
+  // 4 - ray direction
+  debug_mode: u32;
+  // brightness multiplier of the video for this eye
+  eye_gain: f32;
+  // comfort vignette intensity, 0 - off, 1 - strongest
+  vignette: f32;
+  // flat projection crop, see FileData::flat_zoom
+  flat_pan: vec2<f32>;
+  flat_zoom: f32;
+  // bit 0 - horizontal
+  // bit 1 - vertical
+  flip: u32;
+  // ER 180 vertical coverage and pitch offset, in radians
+  er180_v_fov: f32;
+  er180_pitch: f32;
+  // linear RGB shown where there is no video, e.g. behind 180 degree content
+  background: vec4<f32>;
 };
 
 [[group(0), binding(0)]]
@@ -0,0 +1,37 @@
+[[group(1), binding(0)]]
+var shared_tex: texture_2d<f32>;
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] texcoord: vec2<f32>;
+};
+
+struct VertexOutput {
+  [[builtin(position)]] position: vec4<f32>;
+  // direction in world origin space, same as "ws" of fullscreen projections
+  [[location(0)]] ws: vec3<f32>;
+  // spherical coordinates without stereo adjust, see SphereMesh
+  [[location(1)]] sc: vec2<f32>;
+};
+
+struct PushConstants {
+  model: mat4x4<f32>;
+};
+
+var<push_constant> push: PushConstants;
+
+[[stage(vertex)]]
+fn vs_main(in: VertexInput) -> VertexOutput {
+  var out: VertexOutput;
+  // w = 0 drops translation, so the sphere is at infinity, depth is put onto the far plane
+  let p = (camera_state.mvp * push.model) * vec4<f32>(in.position, 0.0);
+  out.position = p.xyww;
+  out.ws = in.position;
+  out.sc = in.texcoord;
+  return out;
+}
+
+// NOTE: applies "stereo adjust", same as ws_to_spherical_coords
+fn sphere_mesh_spherical_coords(sc: vec2<f32>) -> vec2<f32> {
+  return vec2<f32>(sc.x + camera_state.stereo_adjust, sc.y);
+}
@@ -46,7 +46,20 @@ fn stereo_top_bottom(uv: vec2<f32>) -> vec2<f32> {
   return uv;
 }
 
+// mirrors the picture of a single eye, see FileData::flip_horizontal
+fn flip(uv: vec2<f32>) -> vec2<f32> {
+  var uv = uv;
+  if ((camera_state.flip & 1u) != 0u) {
+    uv.x = 1.0 - uv.x;
+  }
+  if ((camera_state.flip & 2u) != 0u) {
+    uv.y = 1.0 - uv.y;
+  }
+  return uv;
+}
+
 fn stereo(uv: vec2<f32>) -> vec2<f32> {
+  let uv = flip(uv);
   if (camera_state.mode == 0u) {
     return uv; // mono
   } else if (camera_state.mode == 1u) {
@@ -63,11 +76,13 @@ fn equirectangular_360(sc: vec2<f32>) -> vec2<f32> {
   return stereo(uv);
 }
 
+// unlike other projections returns uv before stereo mapping, so that the caller can tell whether it's outside of
+// the vertical coverage
 fn equirectangular_180(sc: vec2<f32>) -> vec2<f32> {
   var uv = sc / PI;
   uv.x = uv.x + 0.5;
-  uv.y = uv.y + 0.5;
-  return stereo(uv);
+  uv.y = (sc.y + camera_state.er180_pitch) / camera_state.er180_v_fov + 0.5;
+  return uv;
 }
 
 fn fisheye_180(ws: vec3<f32>) -> vec2<f32> {
@@ -197,3 +212,24 @@ fn weird_checkerboard(ws: vec3<f32>) -> vec4<f32> {
     return vec4<f32>(c, c, c, 1.0);
   }
 }
+
+
+// replaces color with a debug visualization if it's enabled, see debug_mode in CameraState
+fn debug_visualization(color: vec4<f32>, uv: vec2<f32>, ws: vec3<f32>, depth: f32) -> vec4<f32> {
+  let mode = camera_state.debug_mode;
+  if (mode == 1u) {
+    return vec4<f32>(fract(uv), 0.0, 1.0);
+  } else if (mode == 2u) {
+    if (camera_state.eye_index == 0u) {
+      return vec4<f32>(mix(color.rgb, vec3<f32>(1.0, 0.0, 0.0), 0.5), 1.0);
+    }
+    return vec4<f32>(mix(color.rgb, vec3<f32>(0.0, 1.0, 0.0), 0.5), 1.0);
+  } else if (mode == 3u) {
+    let p = camera_state.inverse_projection * vec4<f32>(0.0, 0.0, depth, 1.0);
+    let z = clamp(abs(p.z / p.w) / 10.0, 0.0, 1.0);
+    return vec4<f32>(z, z, z, 1.0);
+  } else if (mode == 4u) {
+    return vec4<f32>(ws * 0.5 + 0.5, 1.0);
+  }
+  return color;
+}
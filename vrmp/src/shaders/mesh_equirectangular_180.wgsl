@@ -0,0 +1,17 @@
+{% include "inc_common.wgsl" %}
+{% include "inc_util.wgsl" %}
+{% include "inc_sphere_mesh.wgsl" %}
+
+// the mesh is a front hemisphere, the rest is the clear color
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let ws = normalize(in.ws);
+  let sc = sphere_mesh_spherical_coords(in.sc);
+  let frame_uv = equirectangular_180(sc);
+  let uv = stereo(frame_uv);
+  let tex = textureSample(shared_tex, sampler_tex, uv);
+  if (frame_uv.y < 0.0 || frame_uv.y > 1.0) {
+    return debug_visualization(vec4<f32>(camera_state.background.rgb, 1.0), uv, ws, in.position.z);
+  }
+  return debug_visualization(vec4<f32>(tex.rgb * camera_state.eye_gain, 1.0), uv, ws, in.position.z);
+}
@@ -0,0 +1,12 @@
+{% include "inc_common.wgsl" %}
+{% include "inc_util.wgsl" %}
+{% include "inc_sphere_mesh.wgsl" %}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let ws = normalize(in.ws);
+  let sc = sphere_mesh_spherical_coords(in.sc);
+  let uv = equirectangular_360(sc);
+  let tex = textureSample(shared_tex, sampler_tex, uv);
+  return debug_visualization(vec4<f32>(tex.rgb * camera_state.eye_gain, 1.0), uv, ws, in.position.z);
+}
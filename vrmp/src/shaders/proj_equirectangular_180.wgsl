@@ -7,10 +7,11 @@ fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
   let m = mat4_to_mat3(camera_state.view_orientation);
   let ws = m * normalize(in.inv_pos);
   let sc = ws_to_spherical_coords(ws);
-  let uv = equirectangular_180(sc);
+  let frame_uv = equirectangular_180(sc);
+  let uv = stereo(frame_uv);
   let tex = textureSample(shared_tex, sampler_tex, uv);
-  if (ws.z < 0.0) {
-    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+  if (ws.z < 0.0 || frame_uv.y < 0.0 || frame_uv.y > 1.0) {
+    return debug_visualization(vec4<f32>(camera_state.background.rgb, 1.0), uv, ws, in.position.z);
   }
-  return vec4<f32>(tex.rgb, 1.0);
+  return debug_visualization(vec4<f32>(tex.rgb * camera_state.eye_gain, 1.0), uv, ws, in.position.z);
 }
\ No newline at end of file
@@ -9,5 +9,5 @@ fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
   let sc = ws_to_spherical_coords(ws);
   let uv = equirectangular_360(sc);
   let tex = textureSample(shared_tex, sampler_tex, uv);
-  return vec4<f32>(tex.rgb, 1.0);
+  return debug_visualization(vec4<f32>(tex.rgb * camera_state.eye_gain, 1.0), uv, ws, in.position.z);
 }
\ No newline at end of file
@@ -9,7 +9,7 @@ fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
   let uv = fisheye_180(ws);
   let tex = textureSample(shared_tex, sampler_tex, uv);
   if (ws.z < 0.0) {
-    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    return debug_visualization(vec4<f32>(camera_state.background.rgb, 1.0), uv, ws, in.position.z);
   }
-  return vec4<f32>(tex.rgb, 1.0);
+  return debug_visualization(vec4<f32>(tex.rgb * camera_state.eye_gain, 1.0), uv, ws, in.position.z);
 }
\ No newline at end of file
@@ -38,6 +38,10 @@ fn vs_main(in: VertexInput) -> VertexOutput {
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
   var tc = in.texcoord;
   tc.y = 1.0 - tc.y;
+  tc = (tc - 0.5) / camera_state.flat_zoom + 0.5 + camera_state.flat_pan;
   tc = stereo(tc);
-  return textureSample(vscreen_tex, sampler_tex, tc);
+  let tex = textureSample(vscreen_tex, sampler_tex, tc);
+  let color = vec4<f32>(tex.rgb * camera_state.eye_gain, tex.a);
+  // there is no ray direction for flat screen, it shows up as gray
+  return debug_visualization(color, tc, vec3<f32>(0.0, 0.0, 0.0), in.position.z);
 }
\ No newline at end of file
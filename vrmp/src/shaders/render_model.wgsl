@@ -0,0 +1,39 @@
+{% include "inc_common.wgsl" %}
+
+[[group(1), binding(0)]]
+var diffuse_tex: texture_2d<f32>;
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] normal: vec3<f32>;
+  [[location(2)]] texcoord: vec2<f32>;
+};
+
+struct VertexOutput {
+  [[builtin(position)]] position: vec4<f32>;
+  [[location(0)]] normal: vec3<f32>;
+  [[location(1)]] texcoord: vec2<f32>;
+};
+
+struct PushConstants {
+  model: mat4x4<f32>;
+};
+
+var<push_constant> push: PushConstants;
+
+[[stage(vertex)]]
+fn vs_main(in: VertexInput) -> VertexOutput {
+  var out: VertexOutput;
+  out.position = (camera_state.mvp * push.model) * vec4<f32>(in.position, 1.0);
+  out.normal = (push.model * vec4<f32>(in.normal, 0.0)).xyz;
+  out.texcoord = in.texcoord;
+  return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  // simple ambient + top light, just enough to make the shape readable
+  let light = 0.5 + 0.5 * max(dot(normalize(in.normal), vec3<f32>(0.0, 1.0, 0.0)), 0.0);
+  let tex = textureSample(diffuse_tex, sampler_tex, in.texcoord);
+  return vec4<f32>(tex.rgb * light, 1.0);
+}
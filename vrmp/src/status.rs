@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+// Playback status, written to $XDG_RUNTIME_DIR/vrmp/status.json so that external tools/overlays can see what's
+// playing.
+#[derive(Clone, PartialEq, Serialize)]
+pub struct Status {
+    pub path: Option<String>,
+    pub percent_pos: f64,
+    pub duration: Option<u32>,
+    pub playing: bool,
+}
+
+fn status_file_path() -> Result<PathBuf, anyhow::Error> {
+    let dirs = xdg::BaseDirectories::with_prefix("vrmp")?;
+    Ok(dirs.place_runtime_file("status.json")?)
+}
+
+pub struct StatusWriter {
+    path: Option<PathBuf>,
+    last_status: Option<Status>,
+}
+
+impl StatusWriter {
+    pub fn new() -> StatusWriter {
+        let path = match status_file_path() {
+            Ok(path) => Some(path),
+            Err(e) => {
+                log::error!("failed locating status file, status output is disabled: {}", e);
+                None
+            }
+        };
+        StatusWriter {
+            path,
+            last_status: None,
+        }
+    }
+
+    fn write(path: &Path, status: &Status) -> Result<(), anyhow::Error> {
+        // write to a temporary file first and rename it, this way readers never see partially written file
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(status)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn write_maybe(&mut self, status: Status) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        if self.last_status.as_ref() == Some(&status) {
+            return;
+        }
+        if let Err(e) = StatusWriter::write(path, &status) {
+            log::error!("failed writing status file: {}", e);
+        }
+        self.last_status = Some(status);
+    }
+
+    pub fn remove(&mut self) {
+        if let Some(path) = self.path.take() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::error!("failed removing status file: {}", e);
+            }
+        }
+    }
+}
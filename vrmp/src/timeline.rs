@@ -0,0 +1,75 @@
+use std::fmt::Write;
+
+// Playback position and length of the current file in seconds, fed by mpv "time-pos" and "duration" properties.
+// Percent based math is off for VBR files, so anything that shows time uses this instead.
+#[derive(Clone, Copy, Default)]
+pub struct Timeline {
+    pub pos_secs: f64,
+    pub duration_secs: f64,
+}
+
+impl Timeline {
+    // time at a fraction of the whole file, e.g. under the mouse on a seek bar
+    pub fn secs_at(&self, fraction: f64) -> f64 {
+        self.duration_secs * fraction.clamp(0.0, 1.0)
+    }
+
+    pub fn write_pos(&self, s: &mut String) {
+        write_hms(s, self.pos_secs);
+    }
+
+    pub fn write_duration(&self, s: &mut String) {
+        write_hms(s, self.duration_secs);
+    }
+}
+
+// HH:MM:SS, fractions are truncated, negative and NaN values show up as zero
+pub fn write_hms(s: &mut String, secs: f64) {
+    let secs = secs.max(0.0) as u64;
+    let seconds = secs % 60;
+    let minutes = (secs / 60) % 60;
+    let hours = (secs / 60) / 60;
+    write!(s, "{:02}:{:02}:{:02}", hours, minutes, seconds).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hms(secs: f64) -> String {
+        let mut s = String::new();
+        write_hms(&mut s, secs);
+        s
+    }
+
+    #[test]
+    fn formats_sub_hour() {
+        assert_eq!(hms(0.0), "00:00:00");
+        assert_eq!(hms(59.9), "00:00:59");
+        assert_eq!(hms(61.0), "00:01:01");
+        assert_eq!(hms(3599.0), "00:59:59");
+    }
+
+    #[test]
+    fn formats_multi_hour() {
+        assert_eq!(hms(3600.0), "01:00:00");
+        assert_eq!(hms(2.0 * 3600.0 + 5.0 * 60.0 + 7.5), "02:05:07");
+        assert_eq!(hms(100.0 * 3600.0), "100:00:00");
+    }
+
+    #[test]
+    fn clamps_invalid_to_zero() {
+        assert_eq!(hms(-5.0), "00:00:00");
+        assert_eq!(hms(f64::NAN), "00:00:00");
+    }
+
+    #[test]
+    fn secs_at_fraction() {
+        let t = Timeline {
+            pos_secs: 0.0,
+            duration_secs: 120.0,
+        };
+        assert_eq!(t.secs_at(0.5), 60.0);
+        assert_eq!(t.secs_at(2.0), 120.0);
+    }
+}
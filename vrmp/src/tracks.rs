@@ -6,6 +6,11 @@ pub struct Track {
     pub title: String,
     pub codec: String,
     pub lang: String,
+    // 0 if unknown, for adaptive streams (HLS/DASH) each quality level is a separate video track
+    pub width: i64,
+    pub height: i64,
+    // bits per second
+    pub bitrate: i64,
 }
 
 #[derive(Default)]
@@ -44,11 +49,15 @@ impl Tracks {
                 let lang = get_str("lang");
                 let title = get_str("title");
                 let codec = get_str("codec");
+                let get_i64 = |key: &str| -> Option<i64> { m.get(key).and_then(|v| v.as_i64()).copied() };
                 let track = Track {
                     id,
                     title: title.to_owned(),
                     codec: codec.to_owned(),
                     lang: lang.to_owned(),
+                    width: get_i64("demux-w").unwrap_or(0),
+                    height: get_i64("demux-h").unwrap_or(0),
+                    bitrate: get_i64("hls-bitrate").or_else(|| get_i64("demux-bitrate")).unwrap_or(0),
                 };
 
                 match typ.as_str() {
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+
+use glam::Vec2;
+
+use crate::{
+    enums::{AspectRatio, Mode, Projection},
+    filedb::FileData,
+};
+
+// how many edits of the current file can be undone
+const MAX_UNDO_ENTRIES: usize = 32;
+
+// subset of FileData which is easy to change by accident in the UI
+#[derive(Copy, Clone, PartialEq)]
+pub struct ViewSettings {
+    projection: Projection,
+    mode: Mode,
+    stereo_convergence: f32,
+    stereo_convergence_flat: f32,
+    aspect_ratio: AspectRatio,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    flat_distance: f32,
+    flat_scale: f32,
+    flat_offset: f32,
+    flat_zoom: f32,
+    flat_pan: Vec2,
+    er180_v_fov: f32,
+    er180_pitch_offset: f32,
+}
+
+impl ViewSettings {
+    pub fn from_file_data(d: &FileData) -> ViewSettings {
+        ViewSettings {
+            projection: d.projection,
+            mode: d.mode,
+            stereo_convergence: d.stereo_convergence,
+            stereo_convergence_flat: d.stereo_convergence_flat,
+            aspect_ratio: d.aspect_ratio,
+            flip_horizontal: d.flip_horizontal,
+            flip_vertical: d.flip_vertical,
+            flat_distance: d.flat_distance,
+            flat_scale: d.flat_scale,
+            flat_offset: d.flat_offset,
+            flat_zoom: d.flat_zoom,
+            flat_pan: d.flat_pan,
+            er180_v_fov: d.er180_v_fov,
+            er180_pitch_offset: d.er180_pitch_offset,
+        }
+    }
+
+    pub fn apply(&self, d: &mut FileData) {
+        d.projection = self.projection;
+        d.mode = self.mode;
+        d.stereo_convergence = self.stereo_convergence;
+        d.stereo_convergence_flat = self.stereo_convergence_flat;
+        d.aspect_ratio = self.aspect_ratio;
+        d.flip_horizontal = self.flip_horizontal;
+        d.flip_vertical = self.flip_vertical;
+        d.flat_distance = self.flat_distance;
+        d.flat_scale = self.flat_scale;
+        d.flat_offset = self.flat_offset;
+        d.flat_zoom = self.flat_zoom;
+        d.flat_pan = self.flat_pan;
+        d.er180_v_fov = self.er180_v_fov;
+        d.er180_pitch_offset = self.er180_pitch_offset;
+    }
+}
+
+// Undo history of the current file. UI edits are tracked frame by frame, a drag spans many frames, so an edit is
+// committed only once no UI item is active anymore. Mouse and hotkey edits outside of the UI are tracked the same way.
+pub struct UndoHistory {
+    entries: VecDeque<ViewSettings>,
+    // state before the edit in progress
+    pending: Option<ViewSettings>,
+}
+
+impl UndoHistory {
+    pub fn new() -> UndoHistory {
+        UndoHistory {
+            entries: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    // called every frame UI is rendered with the state before and after rendering it, and on every edit outside of
+    // the UI, `editing` is true while the edit is still in progress
+    pub fn track(&mut self, before: Option<ViewSettings>, after: Option<ViewSettings>, editing: bool) {
+        if let (Some(before), Some(after)) = (before, after) {
+            if before != after && self.pending.is_none() {
+                self.pending = Some(before);
+            }
+        }
+        if editing {
+            return;
+        }
+        if let Some(pending) = self.pending.take() {
+            if Some(pending) != after {
+                if self.entries.len() == MAX_UNDO_ENTRIES {
+                    self.entries.pop_front();
+                }
+                self.entries.push_back(pending);
+            }
+        }
+    }
+
+    pub fn undo(&mut self) -> Option<ViewSettings> {
+        self.pending = None;
+        self.entries.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(flat_zoom: f32) -> ViewSettings {
+        ViewSettings {
+            projection: Projection::Flat,
+            mode: Mode::Mono,
+            stereo_convergence: 0.0,
+            stereo_convergence_flat: 0.0,
+            aspect_ratio: AspectRatio::One,
+            flip_horizontal: false,
+            flip_vertical: false,
+            flat_distance: 5.0,
+            flat_scale: 1.0,
+            flat_offset: 0.0,
+            flat_zoom,
+            flat_pan: Vec2::ZERO,
+            er180_v_fov: 180.0,
+            er180_pitch_offset: 0.0,
+        }
+    }
+
+    #[test]
+    fn coalesces_edits_while_item_is_active() {
+        let mut h = UndoHistory::new();
+        h.track(Some(settings(1.0)), Some(settings(1.5)), true);
+        h.track(Some(settings(1.5)), Some(settings(2.0)), true);
+        h.track(Some(settings(2.0)), Some(settings(2.0)), false);
+        assert_eq!(h.undo().map(|s| s.flat_zoom), Some(1.0));
+        assert!(h.undo().is_none());
+    }
+
+    #[test]
+    fn edit_reverted_before_release_is_dropped() {
+        let mut h = UndoHistory::new();
+        h.track(Some(settings(1.0)), Some(settings(1.5)), true);
+        h.track(Some(settings(1.5)), Some(settings(1.0)), false);
+        assert!(h.undo().is_none());
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped_at_cap() {
+        let mut h = UndoHistory::new();
+        let total = MAX_UNDO_ENTRIES + 8;
+        for i in 0..total {
+            h.track(Some(settings(i as f32)), Some(settings(i as f32 + 1.0)), false);
+        }
+        let mut undone = Vec::new();
+        while let Some(s) = h.undo() {
+            undone.push(s.flat_zoom);
+        }
+        assert_eq!(undone.len(), MAX_UNDO_ENTRIES);
+        assert_eq!(undone.first(), Some(&((total - 1) as f32)));
+        assert_eq!(undone.last(), Some(&((total - MAX_UNDO_ENTRIES) as f32)));
+    }
+
+    #[test]
+    fn nothing_to_undo_without_file() {
+        let mut h = UndoHistory::new();
+        h.track(None, None, false);
+        h.track(None, None, true);
+        h.track(None, None, false);
+        assert!(h.undo().is_none());
+    }
+}
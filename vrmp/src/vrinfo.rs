@@ -2,9 +2,35 @@ use glam::{Mat4, Vec3};
 
 use crate::danger;
 
+pub const MIN_SUPERSAMPLE_FACTOR: f32 = 0.5;
+pub const MAX_SUPERSAMPLE_FACTOR: f32 = 4.0;
+pub const MIN_WORLD_SCALE: f32 = 0.5;
+pub const MAX_WORLD_SCALE: f32 = 2.0;
+
+// Perceived size of the world comes from eye separation: eyes further apart make everything look smaller, so eye
+// offsets are divided by the world scale. Rotation part (canted displays) is kept as is.
+pub fn scale_eye_to_head_mat(mat: Mat4, world_scale: f32) -> Mat4 {
+    let world_scale = world_scale.clamp(MIN_WORLD_SCALE, MAX_WORLD_SCALE);
+    let mut mat = mat;
+    mat.w_axis = (mat.w_axis.truncate() / world_scale).extend(1.0);
+    mat
+}
+
+fn eye_size(recommended_eye_size: (u32, u32), supersample_factor: f32) -> (u32, u32) {
+    let (w, h) = recommended_eye_size;
+    (
+        ((w as f32 * supersample_factor).round() as u32).max(1),
+        ((h as f32 * supersample_factor).round() as u32).max(1),
+    )
+}
+
 pub struct VRInfo {
     // recommended eye size as returned from openvr api
     pub recommended_eye_size: (u32, u32),
+    // eye size is recommended eye size multiplied by this factor
+    pub supersample_factor: f32,
+    // near/far planes eye projections were built with, see set_clip_planes
+    pub clip_planes: (f32, f32),
     // actual ipd calculated from eye matrices
     pub ipd: f32,
     pub eye_w: u32,
@@ -24,15 +50,21 @@ pub struct VRInfo {
 }
 
 impl VRInfo {
-    pub fn create(vr_ctx: &libopenvr::Context, wgpu_device: &wgpu::Device) -> VRInfo {
+    pub fn create(
+        vr_ctx: &libopenvr::Context,
+        wgpu_device: &wgpu::Device,
+        supersample_factor: f32,
+        sample_count: u32,
+        clip_planes: (f32, f32),
+    ) -> VRInfo {
         let recommended_eye_size = vr_ctx.system.recommended_render_target_size();
-        let (eye_w, eye_h) = recommended_eye_size;
-        let eye_w = eye_w * 2;
-        let eye_h = eye_h * 2;
-        let left_eye_proj_mat = vr_ctx.system.get_projection_matrix(libopenvr::Eye::Left, 0.1, 100.0);
+        let supersample_factor = supersample_factor.clamp(MIN_SUPERSAMPLE_FACTOR, MAX_SUPERSAMPLE_FACTOR);
+        let (eye_w, eye_h) = eye_size(recommended_eye_size, supersample_factor);
+        let (near, far) = clip_planes;
+        let left_eye_proj_mat = vr_ctx.system.get_projection_matrix(libopenvr::Eye::Left, near, far);
         let left_eye_inv_proj_mat = left_eye_proj_mat.inverse();
         let left_eye_to_head_mat = vr_ctx.system.get_eye_to_head_transform(libopenvr::Eye::Left).inverse();
-        let right_eye_proj_mat = vr_ctx.system.get_projection_matrix(libopenvr::Eye::Right, 0.1, 100.0);
+        let right_eye_proj_mat = vr_ctx.system.get_projection_matrix(libopenvr::Eye::Right, near, far);
 
         let right_eye_inv_proj_mat = right_eye_proj_mat.inverse();
         let right_eye_to_head_mat = vr_ctx.system.get_eye_to_head_transform(libopenvr::Eye::Right).inverse();
@@ -41,10 +73,12 @@ impl VRInfo {
         let rpt = right_eye_to_head_mat.transform_point3(Vec3::splat(0.0));
         let ipd = lpt.distance(rpt);
 
-        let left_eye = danger::vulkan::EyeData::create(wgpu_device, eye_w, eye_h);
-        let right_eye = danger::vulkan::EyeData::create(wgpu_device, eye_w, eye_h);
+        let left_eye = danger::vulkan::EyeData::create(wgpu_device, eye_w, eye_h, sample_count);
+        let right_eye = danger::vulkan::EyeData::create(wgpu_device, eye_w, eye_h, sample_count);
         VRInfo {
             recommended_eye_size,
+            supersample_factor,
+            clip_planes,
             ipd,
             eye_w,
             eye_h,
@@ -60,4 +94,33 @@ impl VRInfo {
             orig_hmd_mat: Mat4::IDENTITY,
         }
     }
+
+    // recreates eye textures if supersample factor differs from the current one
+    pub fn set_supersample_factor(&mut self, wgpu_device: &wgpu::Device, supersample_factor: f32) {
+        let supersample_factor = supersample_factor.clamp(MIN_SUPERSAMPLE_FACTOR, MAX_SUPERSAMPLE_FACTOR);
+        if supersample_factor == self.supersample_factor {
+            return;
+        }
+        let (eye_w, eye_h) = eye_size(self.recommended_eye_size, supersample_factor);
+        self.supersample_factor = supersample_factor;
+        self.eye_w = eye_w;
+        self.eye_h = eye_h;
+        let sample_count = self.left_eye.sample_count;
+        self.left_eye = danger::vulkan::EyeData::create(wgpu_device, eye_w, eye_h, sample_count);
+        self.right_eye = danger::vulkan::EyeData::create(wgpu_device, eye_w, eye_h, sample_count);
+        log::info!("Eye Resolution: {}x{}", eye_w, eye_h);
+    }
+
+    // rebuilds eye projections if clip planes differ from the current ones
+    pub fn set_clip_planes(&mut self, vr_ctx: &libopenvr::Context, clip_planes: (f32, f32)) {
+        if clip_planes == self.clip_planes {
+            return;
+        }
+        let (near, far) = clip_planes;
+        self.clip_planes = clip_planes;
+        self.left_eye_proj_mat = vr_ctx.system.get_projection_matrix(libopenvr::Eye::Left, near, far);
+        self.left_eye_inv_proj_mat = self.left_eye_proj_mat.inverse();
+        self.right_eye_proj_mat = vr_ctx.system.get_projection_matrix(libopenvr::Eye::Right, near, far);
+        self.right_eye_inv_proj_mat = self.right_eye_proj_mat.inverse();
+    }
 }
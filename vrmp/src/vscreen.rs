@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-use imgui::{BackendFlags, Key};
+use imgui::{BackendFlags, ConfigFlags, Key};
 use sdl2::{
     event::Event,
     keyboard::{Mod, Scancode},
@@ -67,6 +67,10 @@ impl VScreen {
         let io = imgui.io_mut();
 
         io.backend_flags.insert(BackendFlags::HAS_SET_MOUSE_POS);
+        // Tab/arrows move focus, Space/Enter activate, Escape cancels. Keyboard isn't captured just because UI has
+        // focus, hotkeys are skipped only while the nav cursor is visible, see Global::handle_sdl2_events
+        io.config_flags
+            .insert(ConfigFlags::NAV_ENABLE_KEYBOARD | ConfigFlags::NAV_NO_CAPTURE_KEYBOARD);
 
         io[Key::Tab] = Scancode::Tab as _;
         io[Key::LeftArrow] = Scancode::Left as _;
@@ -106,6 +110,17 @@ impl VScreen {
         }
     }
 
+    // cursor driven by something other than the mouse, e.g. VR controller laser pointer
+    pub fn set_mouse_position(&mut self, x: f32, y: f32) {
+        self.mouse_x = x;
+        self.mouse_y = y;
+        self.clamp_mouse_position();
+    }
+
+    pub fn set_left_mouse_button(&mut self, pressed: bool) {
+        self.mouse_buttons[0].set(pressed);
+    }
+
     fn clamp_mouse_position(&mut self) {
         self.mouse_x = self.mouse_x.clamp(0.0, self.width as f32);
         self.mouse_y = self.mouse_y.clamp(0.0, self.height as f32);